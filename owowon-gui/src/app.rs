@@ -1,39 +1,84 @@
 use self::{
+    averaging::AveragingState,
+    capture::{CaptureCondition, CaptureState},
+    decode::DecodeSettings,
+    health::HealthWindowState,
+    history::History,
+    logging::MeasurementLogState,
+    markers::Markers,
+    math::MathMode,
+    persistence::{Persistence, PersistenceState},
+    plot::TraceStyle,
+    presets::{Preset, PresetsWindowState},
+    reference::ReferenceTrace,
+    samples::SampleTableState,
     shortcuts::*,
+    statistics::MeasurementStatistics,
+    trigger_alert::{TriggerAlertSettings, TriggerAlertState},
+    undo::UndoHistory,
     utils::{
-        calc_new_horizontal_offset, calc_new_trigger_level, calc_new_vertical_offset,
-        selected_time_base, selected_voltage,
+        calc_anchored_horizontal_offset, calc_new_horizontal_offset, calc_new_trigger_level,
+        calc_new_vertical_offset, selected_time_base, selected_voltage,
     },
 };
 use crate::{
-    device_run::{DeviceRun, DeviceRunState},
-    device_select::DeviceSelector,
+    device_run::{DeviceBackend, DeviceError, DeviceRun, DeviceRunState, Reconnect},
+    device_select::{DeviceList, DeviceSelector},
     optional_sender::OptionalSender,
     selectable_label_full_width::SelectableLabelFullWidth,
 };
 use egui::{
-    vec2, Align, Color32, Context, Direction, FontFamily, FontId, Label, Layout, RichText,
-    ScrollArea, TextStyle, Ui,
+    vec2, Align, Color32, Context, Direction, DragValue, FontFamily, FontId, Grid, Label, Layout,
+    RichText, ScrollArea, Sense, TextStyle, Ui, UserAttentionType, ViewportCommand,
 };
+use egui_plot::PlotPoint;
 use owowon::{
     data::{
         awg::{AwgConfig, AWG_MODES},
-        head::{Channel, DataHeader, RunStatus},
-        measurement::Measurements,
+        head::{Channel, DataHeader, MemoryDepth, PulseCondition, RunStatus, SampleType},
+        limits::DeviceLimits,
+        measurement::{MeasurementKind, Measurements},
+        units::{Time, Voltage},
     },
-    device::Device,
-    InitialDeviceRunConfig, OscilloscopeMessage, OscilloscopeRunCommand,
+    device::{Device, ErrorClass, FromUsbDeviceError, LinkHealth},
+    scaled_number::{NumberFormat, ScaledNumber, DEFAULT_PRECISION},
+    InitialDeviceRunConfig, OscilloscopeMessage, OscilloscopeRunCommand, SettingMismatch,
 };
-use std::{collections::HashMap, fmt::Write, sync::Arc, time::Duration};
-use tokio::sync::RwLock;
-use windows::{core::HSTRING, Devices::Enumeration::DeviceInformation};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt::Write,
+    sync::Arc,
+    time::Duration,
+};
+use windows::core::HSTRING;
 
+mod averaging;
+mod capture;
 mod cmds;
+mod decode;
+mod health;
+mod history;
+mod logging;
+mod markers;
+mod math;
+mod persistence;
 mod plot;
+mod presets;
+mod reference;
+mod samples;
 mod shortcuts;
 mod side_panel;
+mod sparkline;
+mod statistics;
+mod trigger_alert;
+mod undo;
 mod utils;
 
+/// Stands in for a device id to select the simulated device, see [`DeviceBackend::Demo`].
+const DEMO_DEVICE_ID: &str = "demo";
+/// Upper end of the frame interval setting, in seconds
+const MAX_FRAME_INTERVAL: f64 = 10.0;
+
 #[derive(Default)]
 pub struct OscilloscopeUiState {
     head: DataHeader,
@@ -41,13 +86,221 @@ pub struct OscilloscopeUiState {
     ch2_data: Vec<u8>,
     measurements: Option<[Measurements; 2]>,
     acquisition_duration: Duration,
+    history: History,
+    persistence: PersistenceState,
+    averaging: AveragingState,
+    /// Saved traces per channel, see [`ReferenceTrace`]
+    references: [Option<ReferenceTrace>; 2],
+    markers: Markers,
+    /// Whether the latest frame of each channel hits the top or bottom of the sample range
+    clipping: [bool; 2],
 }
 
-#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)]
 pub struct PersistentState {
     selected_device: Option<String>,
+    /// How long to wait on launch for the selected device to show up, 0 to show the picker right
+    /// away
+    auto_connect_secs: u32,
+    measurements_enabled: bool,
+    /// Which channels (CH1, CH2) measurements are queried for
+    measurement_channels: [bool; 2],
+    /// Which measurements are queried and shown
+    measurement_kinds: BTreeSet<MeasurementKind>,
+    /// Whether the bottom panel only shows the first [`COMPACT_MEASUREMENTS`] of those
+    compact_measurements: bool,
+    /// Whether the bottom panel has a row per measurement instead of per channel
+    measurements_by_kind: bool,
+    voltage_display: VoltageDisplay,
+    /// Reference impedance for [`VoltageDisplay::Dbm`], in ohms
+    dbm_impedance: f64,
+    /// Digits shown for measurements and the plot cursor, see [`ScaledNumber::fmt_with`]
+    display_precision: usize,
+    number_format: NumberFormat,
+    /// Number of acquisitions kept for scrolling back
+    history_len: usize,
+    math_mode: MathMode,
+    persistence: Persistence,
+    /// Number of acquisitions averaged in software, 1 for off
+    average_count: usize,
+    /// Whether scales, trigger and sampling rate are shown in the plot corners
+    show_overlay: bool,
+    /// Whether the plot keeps the screen's proportions instead of filling the window
+    square_divisions: bool,
+    /// Whether min/max/mean/σ are shown under the measurements
+    show_statistics: bool,
+    /// Number of readings the statistics are computed over
+    statistics_window: usize,
+    decode: DecodeSettings,
+    /// Minimum time between two acquisitions in seconds, 0 for as fast as possible
+    min_frame_interval: f64,
+    capture: CaptureCondition,
+    presets: Vec<Preset>,
+    /// Per channel (CH1, CH2)
+    trace_styles: [TraceStyle; 2],
+    trigger_alert: TriggerAlertSettings,
+    /// Settings remembered for each device id used before, the ones above are those of
+    /// `selected_device` and the defaults for new devices
+    device_settings: HashMap<String, DeviceSettings>,
+    /// Raw SCPI commands sent on every connect, one per line
+    connect_script: String,
+    /// See [`InitialDeviceRunConfig::batch_commands`]
+    batch_commands: bool,
+    /// Side panel sections left open, see [`side_panel::section`]
+    open_sections: BTreeSet<String>,
+}
+
+/// The part of [`PersistentState`] that is remembered per device.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct DeviceSettings {
     measurements_enabled: bool,
+    measurement_channels: [bool; 2],
+    measurement_kinds: BTreeSet<MeasurementKind>,
+    trace_styles: [TraceStyle; 2],
+}
+
+impl Default for DeviceSettings {
+    fn default() -> Self {
+        let defaults = PersistentState::default();
+        defaults.device_settings()
+    }
+}
+
+impl PersistentState {
+    /// A voltage measurement as selected in [`Self::voltage_display`].
+    fn format_voltage(&self, voltage: Voltage) -> String {
+        self.voltage_display.format(
+            voltage,
+            self.dbm_impedance,
+            self.number_format,
+            self.display_precision,
+        )
+    }
+
+    fn device_settings(&self) -> DeviceSettings {
+        DeviceSettings {
+            measurements_enabled: self.measurements_enabled,
+            measurement_channels: self.measurement_channels,
+            measurement_kinds: self.measurement_kinds.clone(),
+            trace_styles: self.trace_styles,
+        }
+    }
+
+    fn set_device_settings(&mut self, settings: DeviceSettings) {
+        self.measurements_enabled = settings.measurements_enabled;
+        self.measurement_channels = settings.measurement_channels;
+        self.measurement_kinds = settings.measurement_kinds;
+        self.trace_styles = settings.trace_styles;
+    }
+
+    /// Stores the settings in use for `selected_device`.
+    fn remember_device_settings(&mut self) {
+        if let Some(device_id) = &self.selected_device {
+            self.device_settings
+                .insert(device_id.clone(), self.device_settings());
+        }
+    }
+
+    /// Remembers the settings of the previous device, then switches to `device_id` and its settings.
+    /// Devices not seen before start with the defaults.
+    fn switch_device_settings(&mut self, device_id: &str) {
+        self.remember_device_settings();
+        let settings = self
+            .device_settings
+            .get(device_id)
+            .cloned()
+            .unwrap_or_default();
+        self.set_device_settings(settings);
+        self.selected_device = Some(device_id.to_string());
+    }
+
+    /// A persisted interval that's out of range (or NaN) is clamped instead of panicking
+    /// `Duration`.
+    fn min_frame_interval(&self) -> Duration {
+        let seconds = self.min_frame_interval.clamp(0.0, MAX_FRAME_INTERVAL);
+        Duration::try_from_secs_f64(seconds).unwrap_or_default()
+    }
+
+    fn initial_run_config(&self, link_health: &Arc<LinkHealth>) -> InitialDeviceRunConfig {
+        InitialDeviceRunConfig {
+            measurements_enabled: self.measurements_enabled,
+            measurement_channels: self.measurement_channels,
+            measurement_kinds: self.measurement_kinds.clone(),
+            min_frame_interval: self.min_frame_interval(),
+            link_health: Some(link_health.clone()),
+            connect_script: self.connect_script.lines().map(str::to_owned).collect(),
+            batch_commands: self.batch_commands,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for PersistentState {
+    fn default() -> Self {
+        Self {
+            selected_device: Default::default(),
+            auto_connect_secs: 10,
+            measurements_enabled: Default::default(),
+            measurement_channels: [true; 2],
+            measurement_kinds: MeasurementKind::all(),
+            voltage_display: Default::default(),
+            dbm_impedance: 50.0,
+            display_precision: DEFAULT_PRECISION,
+            number_format: Default::default(),
+            history_len: 100,
+            math_mode: Default::default(),
+            persistence: Default::default(),
+            average_count: 1,
+            show_overlay: true,
+            square_divisions: false,
+            show_statistics: Default::default(),
+            compact_measurements: Default::default(),
+            measurements_by_kind: false,
+            statistics_window: 100,
+            decode: Default::default(),
+            min_frame_interval: 0.0,
+            capture: Default::default(),
+            presets: Default::default(),
+            trace_styles: Default::default(),
+            trigger_alert: Default::default(),
+            device_settings: Default::default(),
+            connect_script: Default::default(),
+            batch_commands: false,
+            open_sections: Default::default(),
+        }
+    }
+}
+
+/// How voltage levels (measurements, plot cursor) are shown.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum VoltageDisplay {
+    #[default]
+    Volts,
+    Dbv,
+    Dbm,
+}
+
+impl VoltageDisplay {
+    pub fn format(
+        self,
+        voltage: Voltage,
+        dbm_impedance: f64,
+        format: NumberFormat,
+        precision: usize,
+    ) -> String {
+        // decibels are shown with fixed decimals, one less keeps the default at 2
+        let db_precision = precision.saturating_sub(1);
+        match self {
+            VoltageDisplay::Volts => format!(
+                "{:.precision$}V",
+                ScaledNumber(voltage.0).display_with(format)
+            ),
+            VoltageDisplay::Dbv => format!("{:.db_precision$}", voltage.to_dbv()),
+            VoltageDisplay::Dbm => format!("{:.db_precision$}", voltage.to_dbm(dbm_impedance)),
+        }
+    }
 }
 
 #[derive(Default)]
@@ -56,8 +309,55 @@ pub struct AwgState {
     pub frequency: String,
     pub amplitude: String,
     pub offset: String,
+    pub arb_path: String,
+    pub arb_error: Option<String>,
+    pub frequency_error: Option<String>,
+    pub amplitude_error: Option<String>,
+    pub offset_error: Option<String>,
 
     pub config: AwgConfig,
+    /// Index of the output shown in the UI
+    pub channel: usize,
+    pub limits: DeviceLimits,
+}
+
+impl AwgState {
+    /// Refreshes the text boxes and mode selection from the config of the shown output.
+    pub fn load_inputs(&mut self) {
+        let config = &self.config.channels[self.channel];
+        self.frequency_error = None;
+        self.amplitude_error = None;
+        self.offset_error = None;
+        self.frequency.clear();
+        let _ = write!(&mut self.frequency, "{}", config.frequency);
+        self.amplitude.clear();
+        let _ = write!(&mut self.amplitude, "{}", config.amplitude);
+        self.offset.clear();
+        let _ = write!(&mut self.offset, "{}", config.offset);
+
+        self.mode_index = AWG_MODES
+            .into_iter()
+            .position(|m| m == config.mode)
+            .unwrap();
+    }
+}
+
+/// The pulse trigger settings aren't part of the header, so the last sent ones are kept here.
+#[derive(Default)]
+pub struct TriggerPulseState {
+    pub condition: PulseCondition,
+    pub width: String,
+    pub width_error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct ScpiConsoleState {
+    pub input: String,
+    /// Sent commands and hex dumps of their replies
+    pub log: Vec<String>,
+    /// File the raw bytes of every acquisition are appended to, see `RawDump`
+    pub raw_dump_path: String,
+    pub raw_dump: bool,
 }
 
 #[derive(Default)]
@@ -67,15 +367,52 @@ pub struct OwowonApp {
     ch1_offset_string: String,
     ch2_offset_string: String,
     horizontal_offset_string: String,
+    /// Text boxes of the fine scales, see `fine_scales`
+    ch1_scale_string: String,
+    ch2_scale_string: String,
+    time_scale_string: String,
+    /// Scales in small steps and arbitrary values instead of the 1-2-5 presets
+    fine_scales: bool,
     trigger_level_string: String,
+    trigger_holdoff_string: String,
+    trigger_pulse: TriggerPulseState,
 
     osc_ui_state: OscilloscopeUiState,
 
-    last_device_error: Option<String>,
+    last_device_error: Option<DeviceError>,
+    /// Non-fatal message from the device loop, shown until dismissed
+    notice: Option<String>,
+    /// Devices that were in use by another application when last tried
+    busy_devices: HashSet<String>,
     device_selector: Option<DeviceSelector>,
     device_run: DeviceRunState,
+    /// The reconnection the current run came from, until that run delivers its first frame. A run
+    /// failing before that continues its backoff instead of starting over.
+    reconnected: Option<Reconnect>,
 
     awg_state: AwgState,
+    scpi_console: ScpiConsoleState,
+    measurement_statistics: MeasurementStatistics,
+    measurement_log: MeasurementLogState,
+    capture_state: CaptureState,
+    undo_history: UndoHistory,
+    /// The last header received, unlike `osc_ui_state.head` also while frozen or scrolled back in
+    /// the history. What the device's settings really are, e.g. for undo.
+    live_head: DataHeader,
+    presets_window: PresetsWindowState,
+    health_window: HealthWindowState,
+    trigger_alert: TriggerAlertState,
+    sample_table: SampleTableState,
+    /// Settings snapshot the live ones are compared against
+    golden: Option<DataHeader>,
+    /// Holds the displayed acquisition while the device keeps running
+    frozen: bool,
+    /// Whether clicking the plot drops a marker
+    annotating: bool,
+    /// Channel the vertical scale/offset shortcuts act on
+    active_channel: Channel,
+    /// Hovered plot position, the anchor for zooming the time base with the mouse wheel
+    plot_pointer: Option<PlotPoint>,
 }
 
 impl OwowonApp {
@@ -123,11 +460,7 @@ impl OwowonApp {
 
         let device_selector = DeviceSelector::new(move || egui_ctx.request_repaint()).ok();
 
-        let selected_device_id: Option<HSTRING> = app
-            .persistent_state
-            .selected_device
-            .as_ref()
-            .map(Into::into);
+        let selected_device_id = app.persistent_state.selected_device.clone();
 
         let mut app = OwowonApp {
             device_selector,
@@ -135,7 +468,18 @@ impl OwowonApp {
         };
 
         if let Some(selected_device_id) = selected_device_id {
-            app.try_select_device(selected_device_id, &cc.egui_ctx);
+            app.try_select_device(&selected_device_id, &cc.egui_ctx);
+
+            // e.g. not plugged in yet. Busy ones won't free up by waiting.
+            let wait = Duration::from_secs(app.persistent_state.auto_connect_secs.into());
+            if matches!(app.device_run, DeviceRunState::Stopped)
+                && !wait.is_zero()
+                && selected_device_id != DEMO_DEVICE_ID
+                && !app.busy_devices.contains(&selected_device_id)
+            {
+                app.device_run =
+                    DeviceRunState::Reconnecting(Reconnect::waiting(selected_device_id, wait));
+            }
         }
 
         app
@@ -149,14 +493,14 @@ impl eframe::App for OwowonApp {
             None => return,
         };
 
-        self.update_device_run();
+        self.update_device_run(&device_list, ctx);
         self.persistent_state.measurements_enabled = self.osc_ui_state.measurements.is_some();
         let command_tx = match &mut self.device_run {
             DeviceRunState::Running(run) => Some(run.command_channel().clone()),
             _ => None,
         };
 
-        let command_tx = OptionalSender(command_tx);
+        let command_tx = OptionalSender::new(command_tx);
 
         egui::SidePanel::right("side_panel")
             .resizable(true)
@@ -167,40 +511,112 @@ impl eframe::App for OwowonApp {
             });
 
         egui::TopBottomPanel::top("top_bar").show(ctx, |ui| self.top_panel_ui(ui));
+        if self.device_run.is_running() {
+            egui::TopBottomPanel::top("presets_bar")
+                .show(ctx, |ui| presets::bar_ui(self, ui, &command_tx));
+            presets::window_ui(self, ctx);
+        }
+        health::window_ui(self, ctx);
 
         if let Some(measurements) = &self.osc_ui_state.measurements {
             egui::TopBottomPanel::bottom("bottom_bar").show(ctx, |ui| {
                 ui.set_enabled(self.device_run.is_running());
-                bottom_panel_ui(ui, &self.osc_ui_state.head, measurements);
+                bottom_panel_ui(
+                    ui,
+                    &self.osc_ui_state.head,
+                    measurements,
+                    &self.persistent_state,
+                    &self.measurement_statistics,
+                );
             });
         }
 
         egui::CentralPanel::default().show(ctx, |ui| {
+            if let DeviceRunState::Reconnecting(reconnect) = &self.device_run {
+                if let Some(left) = reconnect.time_left() {
+                    ui.heading("Connecting…");
+                    ui.label(format!(
+                        "Waiting for {}… ({}s left)",
+                        reconnect.device_id(),
+                        left.as_secs_f64().ceil()
+                    ));
+                    // the countdown
+                    ctx.request_repaint_after(Duration::from_millis(250));
+                } else {
+                    ui.heading("Reconnecting…");
+                    ui.label(format!(
+                        "Waiting for {} (attempt {})",
+                        reconnect.device_id(),
+                        reconnect.attempt() + 1
+                    ));
+                }
+                if let Some(error) = &self.last_device_error {
+                    ui.collapsing("Last error", |ui| device_error_ui(ui, error));
+                }
+                if ui.button("Cancel").clicked() {
+                    self.device_run.stop();
+                }
+
+                return;
+            }
+
             if !self.device_run.is_running() {
                 if let Some(error) = &self.last_device_error {
                     ui.heading("Last error");
-                    ui.label(error);
+                    device_error_ui(ui, error);
                 }
                 ui.heading("Select device");
                 ui.group(|ui| {
-                    let devices = device_list.blocking_read();
-                    if devices.is_empty() {
+                    let device_ids = device_list
+                        .blocking_read()
+                        .values()
+                        .map(|device| device.Id().unwrap().to_string())
+                        .collect::<Vec<_>>();
+                    if device_ids.is_empty() {
                         ui.label("No devices found");
-                        return;
                     }
 
-                    for device in device_list.blocking_read().values() {
-                        let device_id = device.Id().unwrap();
-                        if ui
-                            .add(SelectableLabelFullWidth::new(false, device_id.to_string()))
-                            .clicked()
-                        {
-                            self.persistent_state.selected_device = Some(device_id.to_string());
-                            self.try_select_device(device_id, ctx);
+                    for device_id in device_ids {
+                        let busy = self.busy_devices.contains(&device_id);
+                        let mut text = RichText::new(device_id.as_str());
+                        if busy {
+                            text = text.weak();
+                        }
+                        let mut response = ui.add(SelectableLabelFullWidth::new(false, text));
+                        if busy {
+                            response = response.on_hover_text(
+                                "In use by another application when last tried, click to retry",
+                            );
+                        }
+                        if response.clicked() {
+                            self.try_select_device(&device_id, ctx);
                         }
                     }
+
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("On launch, wait for the last device for");
+                        ui.add(
+                            DragValue::new(&mut self.persistent_state.auto_connect_secs)
+                                .clamp_range(0..=600)
+                                .suffix("s"),
+                        );
+                    })
+                    .response
+                    .on_hover_text("0 shows this list right away if it isn't connected");
+                    if ui
+                        .add(SelectableLabelFullWidth::new(false, "Demo device"))
+                        .on_hover_text("A simulated oscilloscope, no hardware needed")
+                        .clicked()
+                    {
+                        self.try_select_device(DEMO_DEVICE_ID, ctx);
+                    }
                 });
 
+                // so the first acquisition after connecting is already logged
+                ui.heading("Measurement log");
+                ui.group(|ui| side_panel::measurement_log_ui(ui, &mut self.measurement_log));
+
                 if ui.button("Quit").clicked() {
                     ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                 }
@@ -214,40 +630,88 @@ impl eframe::App for OwowonApp {
         if self.device_run.is_running() && !ctx.wants_keyboard_input() {
             self.handle_shortcuts(ctx, &command_tx);
         }
+
+        for command in command_tx.take_sent() {
+            self.undo_history.record(command, &self.live_head);
+        }
+        // after recording, so undoing isn't recorded as a change itself
+        if self.device_run.is_running() && !ctx.wants_keyboard_input() {
+            self.handle_undo_shortcuts(ctx, &command_tx);
+        }
     }
 
+    // restoring all of egui's memory messed with the window maximization, the side panel's
+    // sections are remembered in `PersistentState::open_sections` instead
     fn persist_egui_memory(&self) -> bool {
         false
     }
 
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.persistent_state.remember_device_settings();
         eframe::set_value(storage, eframe::APP_KEY, &self.persistent_state);
     }
 
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
         self.device_run.stop();
+        self.measurement_log.stop();
     }
 }
 
 impl OwowonApp {
+    fn handle_undo_shortcuts(
+        &mut self,
+        ctx: &egui::Context,
+        cmd: &OptionalSender<OscilloscopeRunCommand>,
+    ) {
+        let command = ctx.input_mut(|input| {
+            if input.consume_shortcut(&REDO_SHIFT) || input.consume_shortcut(&REDO) {
+                self.undo_history.redo()
+            } else if input.consume_shortcut(&UNDO) {
+                self.undo_history.undo()
+            } else {
+                None
+            }
+        });
+        if let Some(command) = command {
+            let _ = cmd.blocking_send(command);
+        }
+    }
+
     fn handle_shortcuts(
         &mut self,
         ctx: &egui::Context,
         cmd: &OptionalSender<OscilloscopeRunCommand>,
     ) {
         let head = &self.osc_ui_state.head;
+        // the shown header is stale while frozen or scrolled back, toggles go from the device's
+        let live_head = &self.live_head;
         ctx.input_mut(|input| {
             if input.consume_shortcut(&TOGGLE_MEASUREMENT) {
                 cmd.toggle_measurements(&self.osc_ui_state);
             }
+            if input.consume_shortcut(&TOGGLE_FREEZE) {
+                self.frozen = !self.frozen;
+            }
+            if input.consume_shortcut(&SELECT_CH1) {
+                self.active_channel = Channel::Ch1;
+            }
+            if input.consume_shortcut(&SELECT_CH2) {
+                self.active_channel = Channel::Ch2;
+            }
+            if input.consume_shortcut(&TOGGLE_DEPTH) {
+                cmd.set_acquisition_depth(match live_head.sample.depmem {
+                    MemoryDepth::FourK => MemoryDepth::EightK,
+                    MemoryDepth::EightK => MemoryDepth::FourK,
+                });
+            }
+            if input.consume_shortcut(&TOGGLE_PEAK_DETECT) {
+                cmd.set_acquisition_mode(match live_head.sample.sample_type {
+                    SampleType::Sample => SampleType::Peak,
+                    SampleType::Peak => SampleType::Sample,
+                });
+            }
 
-            let ch = if !head.channel_enabled(Channel::Ch2) {
-                Channel::Ch1
-            } else if !head.channel_enabled(Channel::Ch1) || input.modifiers.alt {
-                Channel::Ch2
-            } else {
-                Channel::Ch1
-            };
+            let ch = self.active_channel;
 
             let channel_info = head.channel(ch);
 
@@ -262,31 +726,49 @@ impl OwowonApp {
                 }
             };
 
-            if input.key_pressed(ZOOM_IN) || input.raw_scroll_delta.y > 0.0 {
+            // Ctrl + wheel and trackpad pinches both end up as zoom, which is the vertical scale.
+            // Only handle the wheel when there's no zoom, so one gesture doesn't change both.
+            let zoom_delta = input.zoom_delta();
+            let scroll = if zoom_delta == 1.0 {
+                input.raw_scroll_delta.y
+            } else {
+                0.0
+            };
+            // zooming the time base with the wheel keeps the time under the cursor in place
+            let set_time_scale = |scale: Time| {
+                cmd.set_time_scale(scale);
+                if let Some(pointer) = self.plot_pointer.filter(|_| scroll != 0.0) {
+                    cmd.set_horizontal_offset(calc_anchored_horizontal_offset(
+                        head, scale, pointer.x,
+                    ));
+                }
+            };
+
+            if input.key_pressed(ZOOM_IN) || scroll > 0.0 {
                 if input.modifiers.command {
                     try_zoom_out_vertical();
                 } else if input.modifiers.alt {
                     cmd.set_trigger_level(calc_new_trigger_level(head, input.modifiers, true))
                 } else if let (_, _, Some(smaller), _) = selected_time_base(head) {
-                    cmd.set_time_scale(smaller);
+                    set_time_scale(smaller);
                 }
             }
 
-            if input.zoom_delta() > 1.0 {
+            if zoom_delta > 1.0 {
                 try_zoom_out_vertical();
             }
 
-            if input.key_pressed(ZOOM_OUT) || input.raw_scroll_delta.y < 0.0 {
+            if input.key_pressed(ZOOM_OUT) || scroll < 0.0 {
                 if input.modifiers.command {
                     try_zoom_in_vertical();
                 } else if input.modifiers.alt {
                     cmd.set_trigger_level(calc_new_trigger_level(head, input.modifiers, false))
                 } else if let (_, _, _, Some(larger)) = selected_time_base(head) {
-                    cmd.set_time_scale(larger);
+                    set_time_scale(larger);
                 }
             }
 
-            if input.zoom_delta() < 1.0 {
+            if zoom_delta < 1.0 {
                 try_zoom_in_vertical();
             }
 
@@ -312,10 +794,7 @@ impl OwowonApp {
         });
     }
 
-    fn device_list_or_fail_ui(
-        &mut self,
-        ctx: &egui::Context,
-    ) -> Option<Arc<RwLock<HashMap<String, DeviceInformation>>>> {
+    fn device_list_or_fail_ui(&mut self, ctx: &egui::Context) -> Option<DeviceList> {
         let device_list = if let Some(s) = self.device_selector.as_ref() {
             s.list().clone()
         } else {
@@ -332,47 +811,201 @@ impl OwowonApp {
         Some(device_list)
     }
 
-    fn try_select_device(&mut self, device_id: impl Into<HSTRING>, ctx: &Context) {
-        match Device::blocking_from_device_id(device_id).map(|d| {
-            DeviceRun::new(
-                d,
-                ctx,
-                InitialDeviceRunConfig {
-                    measurements_enabled: self.persistent_state.measurements_enabled,
-                },
-            )
-        }) {
+    fn start_device_run(
+        device_id: &str,
+        ctx: &Context,
+        initial_config: InitialDeviceRunConfig,
+    ) -> Result<DeviceRun, FromUsbDeviceError> {
+        let backend = if device_id == DEMO_DEVICE_ID {
+            DeviceBackend::Demo
+        } else {
+            DeviceBackend::Usb(Device::blocking_from_device_id(HSTRING::from(device_id))?)
+        };
+
+        Ok(DeviceRun::new(backend, ctx, initial_config))
+    }
+
+    fn try_select_device(&mut self, device_id: &str, ctx: &Context) {
+        self.persistent_state.switch_device_settings(device_id);
+        if !self.persistent_state.measurements_enabled {
+            self.osc_ui_state.measurements = None;
+        }
+        match Self::start_device_run(
+            device_id,
+            ctx,
+            self.persistent_state
+                .initial_run_config(&self.health_window.link),
+        ) {
             Ok(run) => {
                 self.last_device_error = None;
+                self.reconnected = None;
+                self.busy_devices.remove(device_id);
+                self.undo_history.clear();
+                self.health_window.link.clear();
+                self.trigger_alert.reset();
+                self.osc_ui_state.clipping = Default::default();
                 self.device_run = DeviceRunState::Running(run)
             }
             Err(e) => {
-                self.last_device_error = Some(snafu::Report::from_error(e).to_string());
+                let error = DeviceError::from(e);
+                if error.class == ErrorClass::DeviceBusy {
+                    self.busy_devices.insert(device_id.to_string());
+                }
+                self.last_device_error = Some(error);
                 self.device_run = DeviceRunState::Stopped;
             }
         }
     }
 
-    fn update_device_run(&mut self) {
+    fn update_device_run(&mut self, device_list: &DeviceList, ctx: &Context) {
         self.device_run.update();
+        // the measurement log stays open across reconnects, only stopped by the user or on exit
+        if !self.device_run.is_running() {
+            // a new run starts out without the dump
+            self.scpi_console.raw_dump = false;
+        }
 
         match &mut self.device_run {
             DeviceRunState::Stopped => {}
             DeviceRunState::Error(e) => {
-                self.last_device_error = Some(snafu::Report::from_error(&*e).to_string());
-                self.device_run = DeviceRunState::Stopped;
+                let transport_error = e.is_transport_error();
+                let error = DeviceError::from(&*e);
+                let busy = error.class == ErrorClass::DeviceBusy;
+                self.last_device_error = Some(error);
+                // transient drops (e.g. a bumped cable) shouldn't force re-picking the device
+                self.device_run = match &self.persistent_state.selected_device {
+                    // never shows up in the device list
+                    Some(device_id) if device_id == DEMO_DEVICE_ID => DeviceRunState::Stopped,
+                    // waiting won't help, the other application has to let go of it first
+                    Some(device_id) if busy => {
+                        self.busy_devices.insert(device_id.clone());
+                        DeviceRunState::Stopped
+                    }
+                    // a new session would fail the same way, e.g. on an unexpected reply
+                    Some(_) if !transport_error => DeviceRunState::Stopped,
+                    Some(device_id) => {
+                        DeviceRunState::Reconnecting(match self.reconnected.take() {
+                            Some(mut reconnect) => {
+                                reconnect.failed();
+                                reconnect
+                            }
+                            None => Reconnect::new(device_id.clone()),
+                        })
+                    }
+                    None => DeviceRunState::Stopped,
+                };
+            }
+            DeviceRunState::Reconnecting(reconnect) => {
+                // back to the picker, with the last error if connecting failed
+                if reconnect.is_expired() {
+                    self.device_run = DeviceRunState::Stopped;
+                    return;
+                }
+                if !device_list
+                    .blocking_read()
+                    .contains_key(reconnect.device_id())
+                {
+                    // the device selector requests a repaint once it shows up again
+                    if let Some(left) = reconnect.time_left() {
+                        ctx.request_repaint_after(left);
+                    }
+                    return;
+                }
+                if !reconnect.is_due() {
+                    ctx.request_repaint_after(reconnect.remaining());
+                    return;
+                }
+
+                match Self::start_device_run(
+                    reconnect.device_id(),
+                    ctx,
+                    self.persistent_state
+                        .initial_run_config(&self.health_window.link),
+                ) {
+                    Ok(run) => {
+                        self.last_device_error = None;
+                        self.trigger_alert.reset();
+                        self.reconnected = Some(reconnect.clone());
+                        self.device_run = DeviceRunState::Running(run);
+                    }
+                    Err(e) => {
+                        self.last_device_error = Some(e.into());
+                        reconnect.failed();
+                        ctx.request_repaint_after(reconnect.remaining());
+                    }
+                }
             }
             DeviceRunState::Running(run) => {
                 let channel = run.message_channel();
 
                 match channel.try_recv() {
                     Ok(OscilloscopeMessage::Data(data)) => {
-                        let state = &mut self.osc_ui_state;
-                        update_osc_ui_state(state, data);
+                        self.reconnected = None;
+                        let alert = self.persistent_state.trigger_alert;
+                        if self
+                            .trigger_alert
+                            .triggered(data.signal_data.header.run_status)
+                        {
+                            if alert.beep {
+                                capture::beep();
+                            }
+                            if alert.flash {
+                                ctx.send_viewport_cmd(ViewportCommand::RequestUserAttention(
+                                    UserAttentionType::Informational,
+                                ));
+                            }
+                        }
+
+                        let mut freeze = false;
+                        if let Some(measurements) = &data.measurements {
+                            self.measurement_statistics
+                                .push(measurements, self.persistent_state.statistics_window);
+                            let actions = self
+                                .capture_state
+                                .evaluate(&self.persistent_state.capture, measurements);
+                            if actions.log {
+                                self.measurement_log.log(measurements);
+                            }
+                            if actions.beep {
+                                capture::beep();
+                            }
+                            freeze = actions.freeze;
+                        }
+                        self.live_head = data.signal_data.header;
+                        self.undo_history.header_updated();
+                        // still received while frozen, so the device thread doesn't block on a
+                        // full channel
+                        if !self.frozen {
+                            let state = &mut self.osc_ui_state;
+                            update_osc_ui_state(state, data, &self.persistent_state);
+                            // the matching frame stays on screen
+                            self.frozen = freeze;
+                        }
+                    }
+                    // the side panel shows the device's settings before the first frame arrives
+                    Ok(OscilloscopeMessage::Settings(head)) => {
+                        self.live_head = head;
+                        self.osc_ui_state.head = head;
+                        self.undo_history.header_updated();
                     }
                     Ok(OscilloscopeMessage::Awg(awg_config)) => {
                         update_awg_state(&mut self.awg_state, awg_config)
                     }
+                    Ok(OscilloscopeMessage::Notice(notice)) => self.notice = Some(notice),
+                    // the controls follow the next header on their own, just say why they jumped
+                    Ok(OscilloscopeMessage::SettingMismatch(mismatch)) => {
+                        self.notice = Some(setting_mismatch_notice(mismatch))
+                    }
+                    Ok(OscilloscopeMessage::RawReply(reply)) => {
+                        self.scpi_console.log.push(if reply.is_empty() {
+                            "(no reply)".to_owned()
+                        } else {
+                            pretty_hex::pretty_hex(&reply)
+                        })
+                    }
+                    Ok(OscilloscopeMessage::Limits(limits)) => {
+                        update_awg_limits(&mut self.awg_state, limits)
+                    }
                     Err(_) => {}
                 }
             }
@@ -391,10 +1024,47 @@ impl OwowonApp {
                 if ui.button("disconnect").clicked() {
                     self.device_run.stop();
                 }
+                ui.toggle_value(&mut self.frozen, "freeze").on_hover_text(
+                    "Holds the display (F), while the device keeps acquiring.\n\
+                    Measurement statistics and logging continue.",
+                );
+                ui.toggle_value(&mut self.health_window.open, "link")
+                    .on_hover_text("USB round-trip latency and errors");
+                if ui
+                    .button("copy as text")
+                    .on_hover_text("Copies the traces as braille sparklines")
+                    .clicked()
+                {
+                    let text = sparkline::traces_as_text(&self.osc_ui_state);
+                    ui.output_mut(|o| o.copied_text = text);
+                }
+                if let Some(measurements) = &self.osc_ui_state.measurements {
+                    if ui
+                        .button("copy measurements")
+                        .on_hover_text("Copies the measurements as a Markdown table")
+                        .clicked()
+                    {
+                        let state = &self.persistent_state;
+                        let channels = measured_channels(head, state)
+                            .map(|channel| (channel, &measurements[channel as usize]))
+                            .collect::<Vec<_>>();
+                        let kinds = state.measurement_kinds.iter().copied().collect::<Vec<_>>();
+                        let text = Measurements::markdown_table(
+                            &channels,
+                            &kinds,
+                            state.number_format,
+                            state.display_precision,
+                            |voltage| state.format_voltage(voltage),
+                        );
+                        ui.output_mut(|o| o.copied_text = text);
+                    }
+                }
             });
 
             columns[1].with_layout(Layout::centered_and_justified(Direction::TopDown), |ui| {
-                ui.label({
+                ui.label(if self.frozen {
+                    RichText::new("Frozen").color(Color32::LIGHT_BLUE)
+                } else {
                     let rt = RichText::new(head.run_status.to_string());
                     match head.run_status {
                         RunStatus::Stopped => rt.color(Color32::RED),
@@ -419,48 +1089,261 @@ impl OwowonApp {
     }
 }
 
-fn bottom_panel_ui(ui: &mut Ui, head: &DataHeader, measurements: &[Measurements; 2]) {
-    if head.channel_enabled(Channel::Ch1) {
-        ui.columns(Measurements::MEASUREMENT_COUNT, |cols| {
-            for (index, measurement) in measurements[0].for_display().into_iter().enumerate() {
-                cols[index].add(Label::new(measurement).wrap(false));
-            }
-        });
+fn device_error_ui(ui: &mut Ui, error: &DeviceError) {
+    ui.label(error.hint());
+    ui.collapsing("Details", |ui| ui.label(&error.report));
+}
+
+/// Measurements shown per channel in the compact bottom panel
+const COMPACT_MEASUREMENTS: usize = 3;
+/// Narrowest column of the bottom panel before the measurements wrap into another row
+const MIN_MEASUREMENT_WIDTH: f32 = 120.0;
+
+/// The channels measurements are queried and shown for.
+fn measured_channels<'a>(
+    head: &'a DataHeader,
+    state: &'a PersistentState,
+) -> impl Iterator<Item = Channel> + 'a {
+    [Channel::Ch1, Channel::Ch2]
+        .into_iter()
+        .enumerate()
+        .filter(|&(index, channel)| {
+            head.channel_enabled(channel) && state.measurement_channels[index]
+        })
+        .map(|(_, channel)| channel)
+}
+
+fn bottom_panel_ui(
+    ui: &mut Ui,
+    head: &DataHeader,
+    measurements: &[Measurements; 2],
+    state: &PersistentState,
+    statistics: &MeasurementStatistics,
+) {
+    let measurement_kinds = &state.measurement_kinds;
+    if measurement_kinds.is_empty() {
+        return;
     }
-    if head.channel_enabled(Channel::Ch2) {
-        ui.columns(Measurements::MEASUREMENT_COUNT, |cols| {
-            for (index, measurement) in measurements[1].for_display().into_iter().enumerate() {
-                cols[index].add(Label::new(measurement).wrap(false));
-            }
-        });
+
+    let fmt_voltage = |voltage| state.format_voltage(voltage);
+
+    let shown = if state.compact_measurements {
+        COMPACT_MEASUREMENTS
+    } else {
+        measurement_kinds.len()
+    };
+    let kinds = measurement_kinds
+        .iter()
+        .copied()
+        .take(shown)
+        .collect::<Vec<_>>();
+    let channels = measured_channels(head, state).collect::<Vec<_>>();
+    let displayed = channels.iter().map(|&channel| {
+        measurements[channel as usize].for_display_with(
+            kinds.iter().copied(),
+            state.number_format,
+            state.display_precision,
+            fmt_voltage,
+        )
+    });
+
+    if state.measurements_by_kind {
+        // the channels' values of a measurement next to each other, for comparing them
+        let displayed = displayed.collect::<Vec<_>>();
+        Grid::new("measurements_by_kind")
+            .striped(true)
+            .num_columns(channels.len() + 1)
+            .show(ui, |ui| {
+                ui.label("");
+                for channel in &channels {
+                    ui.strong(channel.to_string());
+                }
+                ui.end_row();
+
+                for (row, &kind) in kinds.iter().enumerate() {
+                    ui.label(kind.to_string());
+                    for (&channel, displayed) in channels.iter().zip(&displayed) {
+                        let index = channel as usize;
+                        ui.vertical(|ui| {
+                            measurement_ui(
+                                ui,
+                                &measurements[index],
+                                index,
+                                kind,
+                                &displayed[row],
+                                state,
+                                statistics,
+                            )
+                        });
+                    }
+                    ui.end_row();
+                }
+            });
+        return;
+    }
+
+    // as many as fit side by side, the rest wraps into more rows on narrow windows
+    let per_row = ((ui.available_width() / MIN_MEASUREMENT_WIDTH) as usize).clamp(1, kinds.len());
+    for (&channel, displayed) in channels.iter().zip(displayed) {
+        let index = channel as usize;
+        for (row, displayed) in kinds.chunks(per_row).zip(displayed.chunks(per_row)) {
+            // the last row leaves its remaining columns empty, keeping the widths aligned
+            ui.columns(per_row, |cols| {
+                for ((col, displayed), &kind) in cols.iter_mut().zip(displayed).zip(row) {
+                    measurement_ui(
+                        col,
+                        &measurements[index],
+                        index,
+                        kind,
+                        displayed,
+                        state,
+                        statistics,
+                    );
+                }
+            });
+        }
+    }
+}
+
+/// A single measurement of the bottom panel, copied to the clipboard when clicked.
+fn measurement_ui(
+    ui: &mut Ui,
+    measurements: &Measurements,
+    index: usize,
+    kind: MeasurementKind,
+    displayed: &str,
+    state: &PersistentState,
+    statistics: &MeasurementStatistics,
+) {
+    let value = measurements.value(kind);
+    let response = ui
+        .add(Label::new(displayed).wrap(false).sense(Sense::click()))
+        .on_hover_text(format!(
+            "Click to copy the value (in {}), shift+click to copy it as shown",
+            kind.unit()
+        ));
+    // the plain number in the base unit, for pasting into a spreadsheet
+    let plain = value.map(|value| value.display_with(NumberFormat::Plain).to_string());
+    if response.clicked() {
+        let text = if ui.input(|i| i.modifiers.shift) {
+            Some(displayed.to_owned())
+        } else {
+            plain.clone()
+        };
+        if let Some(text) = text {
+            ui.output_mut(|o| o.copied_text = text);
+        }
+    }
+    response.context_menu(|ui| {
+        if ui
+            .add_enabled(plain.is_some(), egui::Button::new("Copy value"))
+            .clicked()
+        {
+            ui.output_mut(|o| o.copied_text = plain.clone().unwrap_or_default());
+            ui.close_menu();
+        }
+        if ui.button(format!("Copy \"{displayed}\"")).clicked() {
+            ui.output_mut(|o| o.copied_text = displayed.to_owned());
+            ui.close_menu();
+        }
+    });
+
+    let stats = statistics
+        .get(index, kind)
+        .filter(|_| state.show_statistics);
+    if let Some(stats) = stats.filter(|stats| stats.count() > 0) {
+        // always in the base unit, dB of a standard deviation isn't meaningful
+        let fmt = |value: f64| {
+            let precision = state.display_precision;
+            format!(
+                "{:.precision$}{}",
+                ScaledNumber(value).display_with(state.number_format),
+                kind.unit()
+            )
+        };
+        ui.add(
+            Label::new(
+                RichText::new(format!(
+                    "min {} max {}\nmean {} σ {}",
+                    fmt(stats.min()),
+                    fmt(stats.max()),
+                    fmt(stats.mean()),
+                    fmt(stats.std_dev())
+                ))
+                .small()
+                .weak(),
+            )
+            .wrap(false),
+        );
     }
 }
 
-fn update_osc_ui_state(state: &mut OscilloscopeUiState, data: owowon::OscilloscopeData) {
+fn update_osc_ui_state(
+    state: &mut OscilloscopeUiState,
+    data: owowon::OscilloscopeData,
+    persistent_state: &PersistentState,
+) {
     state.head = data.signal_data.header;
     state.ch1_data.clear();
-    if let Some(ch) = data.signal_data.ch0_data {
+    if let Some(ch) = data.signal_data.channel_data(Channel::Ch1) {
         state.ch1_data.extend(ch);
     }
     state.ch2_data.clear();
-    if let Some(ch) = data.signal_data.ch1_data {
+    if let Some(ch) = data.signal_data.channel_data(Channel::Ch2) {
         state.ch2_data.extend(ch);
     }
+    // saturated at the rails, usually a too sensitive vertical scale
+    state.clipping = [Channel::Ch1, Channel::Ch2].map(|ch| data.signal_data.channel_clipping(ch));
+    // the history keeps the raw acquisitions
+    state.averaging.apply(
+        persistent_state.average_count,
+        &state.head,
+        [&mut state.ch1_data, &mut state.ch2_data],
+    );
     state.measurements = data.measurements;
-    state.acquisition_duration = data.acquisition_duration
+    state.acquisition_duration = data.acquisition_duration;
+    state.persistence.push(
+        persistent_state.persistence,
+        &state.head,
+        &state.ch1_data,
+        &state.ch2_data,
+    );
+    state
+        .history
+        .push(data.signal_data, persistent_state.history_len);
 }
 
-fn update_awg_state(state: &mut AwgState, config: AwgConfig) {
+fn update_awg_state(state: &mut AwgState, mut config: AwgConfig) {
+    // the arbitrary waveforms, duty cycle and symmetry aren't read back, keep the ones set locally
+    for (new, old) in config.channels.iter_mut().zip(&mut state.config.channels) {
+        new.arbitrary = old.arbitrary.take();
+        new.duty_cycle = old.duty_cycle;
+        new.symmetry = old.symmetry;
+    }
     state.config = config;
-    state.frequency.clear();
-    let _ = write!(&mut state.frequency, "{}", config.frequency);
-    state.amplitude.clear();
-    let _ = write!(&mut state.amplitude, "{}", config.amplitude);
-    state.offset.clear();
-    let _ = write!(&mut state.offset, "{}", config.offset);
-
-    state.mode_index = AWG_MODES
-        .into_iter()
-        .position(|m| m == config.mode)
-        .unwrap();
+    state.channel = state.channel.min(state.config.channels.len() - 1);
+    state.load_inputs();
+}
+
+fn update_awg_limits(state: &mut AwgState, limits: DeviceLimits) {
+    state
+        .config
+        .channels
+        .resize_with(limits.awg_channels, Default::default);
+    state.channel = state.channel.min(state.config.channels.len() - 1);
+    state.limits = limits;
+    state.load_inputs();
+}
+
+fn setting_mismatch_notice(mismatch: SettingMismatch) -> String {
+    match mismatch {
+        SettingMismatch::ChannelVScale {
+            channel,
+            requested,
+            applied,
+        } => format!("{channel} scale: device applied {applied}/div instead of {requested}/div"),
+        SettingMismatch::TimeScale { requested, applied } => {
+            format!("Time base: device applied {applied}/div instead of {requested}/div")
+        }
+    }
 }
@@ -0,0 +1,70 @@
+use super::persistence::{screen_settings, ScreenSettings};
+use owowon::data::head::DataHeader;
+use std::collections::VecDeque;
+
+/// Averages the last acquisitions in software, for less noise on repetitive signals. The device
+/// itself only offers sample and peak detect acquisition.
+#[derive(Default)]
+pub struct AveragingState {
+    settings: Option<ScreenSettings>,
+    /// Samples per channel, newest first
+    frames: VecDeque<[Vec<u8>; 2]>,
+    /// Sum of `frames` per channel and sample
+    sums: [Vec<i32>; 2],
+}
+
+impl AveragingState {
+    /// Adds the newest acquisition and replaces its samples with the average over the last `count`
+    /// ones. Starts over whenever the settings change, `count` of 1 or less turns it off.
+    pub fn apply(&mut self, count: usize, head: &DataHeader, data: [&mut Vec<u8>; 2]) {
+        let settings = screen_settings(head);
+        // e.g. a channel switched on, or peak detect with its min/max pairs
+        let lengths_changed = self.frames.front().is_some_and(|frame| {
+            frame[0].len() != data[0].len() || frame[1].len() != data[1].len()
+        });
+        if count <= 1 || self.settings != Some(settings) || lengths_changed {
+            self.clear();
+        }
+        self.settings = Some(settings);
+        if count <= 1 {
+            return;
+        }
+
+        for (sums, data) in self.sums.iter_mut().zip(&data) {
+            sums.resize(data.len(), 0);
+            for (sum, &sample) in sums.iter_mut().zip(data.iter()) {
+                *sum += sample as i8 as i32;
+            }
+        }
+        self.frames.push_front([data[0].clone(), data[1].clone()]);
+        while self.frames.len() > count {
+            let Some(oldest) = self.frames.pop_back() else {
+                break;
+            };
+            for (sums, data) in self.sums.iter_mut().zip(&oldest) {
+                for (sum, &sample) in sums.iter_mut().zip(data) {
+                    *sum -= sample as i8 as i32;
+                }
+            }
+        }
+
+        let averaged = self.frames.len() as f64;
+        for (sums, data) in self.sums.iter().zip(data) {
+            for (sample, &sum) in data.iter_mut().zip(sums) {
+                *sample = (sum as f64 / averaged).round() as i8 as u8;
+            }
+        }
+    }
+
+    /// Acquisitions in the current average, 0 while off.
+    pub fn averaged(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        for sums in &mut self.sums {
+            sums.clear();
+        }
+    }
+}
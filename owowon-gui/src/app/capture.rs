@@ -0,0 +1,134 @@
+use owowon::data::{
+    head::Channel,
+    measurement::{MeasurementKind, Measurements},
+};
+
+/// How a measurement is compared to the threshold.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Comparator {
+    #[default]
+    Above,
+    Below,
+}
+
+impl Comparator {
+    pub const ALL: [Comparator; 2] = [Comparator::Above, Comparator::Below];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Comparator::Above => ">",
+            Comparator::Below => "<",
+        }
+    }
+}
+
+/// Software trigger on a measurement (e.g. "CH1 peak-to-peak > 3V"), for catching intermittent
+/// events.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct CaptureCondition {
+    pub enabled: bool,
+    pub channel: Channel,
+    pub kind: MeasurementKind,
+    pub comparator: Comparator,
+    /// In the base unit of the measurement (V, s, Hz)
+    pub threshold: f64,
+    /// Actions on a match
+    pub freeze: bool,
+    pub beep: bool,
+    /// Only matching frames are written to the CSV log while logging
+    pub log: bool,
+}
+
+impl Default for CaptureCondition {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            channel: Channel::Ch1,
+            kind: MeasurementKind::PeakToPeak,
+            comparator: Comparator::Above,
+            threshold: 1.0,
+            freeze: true,
+            beep: false,
+            log: false,
+        }
+    }
+}
+
+impl CaptureCondition {
+    /// Unavailable measurements never match, nor do channels that aren't measured (e.g. CH3/CH4
+    /// from an edited state file).
+    pub fn matches(&self, measurements: &[Measurements; 2]) -> bool {
+        let Some(value) = measurements
+            .get(self.channel as usize)
+            .and_then(|measurements| measurements.value(self.kind))
+        else {
+            return false;
+        };
+
+        match self.comparator {
+            Comparator::Above => value.0 > self.threshold,
+            Comparator::Below => value.0 < self.threshold,
+        }
+    }
+}
+
+/// Matches of the [`CaptureCondition`] since it was last changed.
+#[derive(Debug, Default)]
+pub struct CaptureState {
+    pub matches: usize,
+    /// Whether the previous frame matched, so a lasting condition only beeps once
+    matched: bool,
+}
+
+/// What to do with the current frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureActions {
+    pub freeze: bool,
+    pub beep: bool,
+    pub log: bool,
+}
+
+impl CaptureState {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Checks a new frame's measurements. Without an enabled condition every frame is logged.
+    pub fn evaluate(
+        &mut self,
+        condition: &CaptureCondition,
+        measurements: &[Measurements; 2],
+    ) -> CaptureActions {
+        if !condition.enabled {
+            self.matched = false;
+            return CaptureActions {
+                log: true,
+                ..Default::default()
+            };
+        }
+
+        let matched = condition.matches(measurements);
+        let first = matched && !self.matched;
+        self.matched = matched;
+        if matched {
+            self.matches += 1;
+        }
+
+        CaptureActions {
+            freeze: matched && condition.freeze,
+            beep: first && condition.beep,
+            log: !condition.log || matched,
+        }
+    }
+}
+
+/// The default system sound, for getting attention while looking elsewhere.
+pub fn beep() {
+    use windows::Win32::{
+        System::Diagnostics::Debug::MessageBeep, UI::WindowsAndMessaging::MB_ICONWARNING,
+    };
+
+    // SAFETY: no pointers involved, failing to play a sound isn't worth reporting
+    let _ = unsafe { MessageBeep(MB_ICONWARNING) };
+}
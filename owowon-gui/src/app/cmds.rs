@@ -1,16 +1,35 @@
-use super::OscilloscopeUiState;
-use crate::optional_sender::OptionalSender;
+use super::{
+    presets::Preset,
+    utils::{calc_channel_fit, nearest_vertical_scale},
+    OscilloscopeUiState,
+};
+use crate::optional_sender::{OptionalSender, Recorded};
 use owowon::{
+    consts::VERTICAL_SCALES,
     data::{
         awg::AwgConfig,
         head::{
-            Channel, ChannelCoupling, MemoryDepth, SampleType, TriggerCoupling, TriggerEdge,
-            TriggerSweep,
+            Channel, ChannelCoupling, ChannelInfo, MemoryDepth, PulseCondition, SampleType,
+            TriggerCoupling, TriggerEdge, TriggerSweep, TriggerType,
         },
+        measurement::{MeasurementKind, Measurements},
         units::{ProbeAttenuation, Time, Voltage},
     },
     OscilloscopeCommand, OscilloscopeRunCommand, OscilloscopeRunSetting,
 };
+use std::{collections::BTreeSet, path::PathBuf, time::Duration};
+
+// only the device commands are needed for undo, not e.g. arbitrary waveform payloads
+impl Recorded for OscilloscopeRunCommand {
+    type Record = OscilloscopeCommand;
+
+    fn record(&self) -> Option<OscilloscopeCommand> {
+        match self {
+            OscilloscopeRunCommand::Command(command) => Some(*command),
+            _ => None,
+        }
+    }
+}
 
 impl OptionalSender<OscilloscopeRunCommand> {
     pub fn run_auto(&self) {
@@ -26,6 +45,32 @@ impl OptionalSender<OscilloscopeRunCommand> {
         let _ = self.blocking_send(OscilloscopeRunSetting::SetMeasurementsEnabled(enabled));
     }
 
+    pub fn set_measurement_channels(&self, channels: [bool; 2]) {
+        let _ = self.blocking_send(OscilloscopeRunSetting::SetMeasurementChannels(channels));
+    }
+
+    pub fn set_measurement_kinds(&self, kinds: BTreeSet<MeasurementKind>) {
+        let _ = self.blocking_send(OscilloscopeRunSetting::SetMeasurementKinds(kinds));
+    }
+
+    pub fn set_min_frame_interval(&self, interval: Duration) {
+        let _ = self.blocking_send(OscilloscopeRunSetting::SetMinFrameInterval(interval));
+    }
+
+    /// The reply to queries (ending with `?`) comes back as `OscilloscopeMessage::RawReply`.
+    pub fn send_raw_command(&self, cmd: String) {
+        let expect_reply = cmd.ends_with('?');
+        let _ = self.blocking_send(OscilloscopeRunSetting::RawCommand { cmd, expect_reply });
+    }
+
+    pub fn set_raw_dump(&self, path: Option<PathBuf>) {
+        let _ = self.blocking_send(OscilloscopeRunSetting::SetRawDump(path));
+    }
+
+    pub fn set_trigger_holdoff(&self, holdoff: Time) {
+        let _ = self.blocking_send(OscilloscopeCommand::SetTriggerHoldoff(holdoff));
+    }
+
     pub fn set_channel_display(&self, channel: Channel, display: bool) {
         let _ = self.blocking_send(OscilloscopeCommand::SetChannelDisplay(
             channel,
@@ -51,8 +96,49 @@ impl OptionalSender<OscilloscopeRunCommand> {
     ) {
         let _ = self.blocking_send(OscilloscopeCommand::SetChannelVScale(
             channel,
-            Voltage(scale.0 * (attenuation.0 as f64)),
+            scale * attenuation.0 as f64,
+        ));
+    }
+
+    /// Also re-sends the vertical scale, so the real volts/div stay the same (as far as
+    /// [`VERTICAL_SCALES`] allows) instead of being rescaled by the new attenuation.
+    pub fn set_channel_attenuation(
+        &self,
+        channel_info: &ChannelInfo,
+        attenuation: ProbeAttenuation,
+    ) {
+        let _ = self.blocking_send(OscilloscopeCommand::SetChannelAttenuation(
+            channel_info.channel,
+            attenuation,
         ));
+
+        let real_scale = channel_info.scale_attenuated();
+        let scale = VERTICAL_SCALES[nearest_vertical_scale(real_scale / attenuation.0 as f64)];
+        self.set_vertical_scale(channel_info.channel, scale, attenuation);
+    }
+
+    /// For correcting a wrongly configured probe: unlike [`Self::set_channel_attenuation`], keeps
+    /// the trace where it is on screen and only changes the volts it stands for.
+    pub fn correct_channel_attenuation(
+        &self,
+        channel_info: &ChannelInfo,
+        attenuation: ProbeAttenuation,
+    ) {
+        let _ = self.blocking_send(OscilloscopeCommand::SetChannelAttenuation(
+            channel_info.channel,
+            attenuation,
+        ));
+
+        let scale = VERTICAL_SCALES[nearest_vertical_scale(channel_info.scale)];
+        self.set_vertical_scale(channel_info.channel, scale, attenuation);
+    }
+
+    /// Does nothing if the needed measurements aren't available, see [`calc_channel_fit`].
+    pub fn fit_channel(&self, channel_info: &ChannelInfo, measurements: &Measurements) {
+        if let Some((scale, offset)) = calc_channel_fit(channel_info, measurements) {
+            self.set_vertical_scale(channel_info.channel, scale, channel_info.probe);
+            self.set_vertical_offset(channel_info.channel, offset);
+        }
     }
 
     pub fn set_vertical_offset(&self, channel: Channel, offset_in_grid_units: f64) {
@@ -66,6 +152,12 @@ impl OptionalSender<OscilloscopeRunCommand> {
         let _ = self.blocking_send(OscilloscopeCommand::SetChannelCoupling(channel, coupling));
     }
 
+    pub fn set_channel_bandwidth_limit(&self, channel: Channel, limit: bool) {
+        let _ = self.blocking_send(OscilloscopeCommand::SetChannelBandwidthLimit(
+            channel, limit,
+        ));
+    }
+
     pub fn set_acquisition_depth(&self, depth: MemoryDepth) {
         let _ = self.blocking_send(OscilloscopeCommand::SetAcquisitionDepth(depth));
     }
@@ -86,6 +178,18 @@ impl OptionalSender<OscilloscopeRunCommand> {
         let _ = self.blocking_send(OscilloscopeCommand::SetTriggerEdge(trigger_edge));
     }
 
+    pub fn set_trigger_type(&self, trigger_type: TriggerType) {
+        let _ = self.blocking_send(OscilloscopeCommand::SetTriggerType(trigger_type));
+    }
+
+    pub fn set_trigger_pulse_condition(&self, condition: PulseCondition) {
+        let _ = self.blocking_send(OscilloscopeCommand::SetTriggerPulseCondition(condition));
+    }
+
+    pub fn set_trigger_pulse_width(&self, width: Time) {
+        let _ = self.blocking_send(OscilloscopeCommand::SetTriggerPulseWidth(width));
+    }
+
     pub fn set_trigger_sweep(&self, trigger_sweep: TriggerSweep) {
         let _ = self.blocking_send(OscilloscopeCommand::SetTriggerSweep(trigger_sweep));
     }
@@ -94,6 +198,12 @@ impl OptionalSender<OscilloscopeRunCommand> {
         let _ = self.blocking_send(OscilloscopeCommand::SetTriggerSource(channel));
     }
 
+    pub fn apply_preset(&self, preset: &Preset) {
+        for command in preset.commands() {
+            let _ = self.blocking_send(command);
+        }
+    }
+
     pub fn read_awg_config(&self) {
         let _ = self.blocking_send(OscilloscopeRunSetting::ReadAwgConfig);
     }
@@ -0,0 +1,193 @@
+use owowon::data::{
+    decode::{
+        i2c::{self, I2cConfig, I2cEvent},
+        uart::{self, UartConfig},
+    },
+    head::{Channel, DataHeader},
+    units::{Time, Voltage},
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum DecodeMode {
+    #[default]
+    Off,
+    Uart,
+    I2c,
+}
+
+impl DecodeMode {
+    pub const ALL: [DecodeMode; 3] = [DecodeMode::Off, DecodeMode::Uart, DecodeMode::I2c];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DecodeMode::Off => "Off",
+            DecodeMode::Uart => "UART",
+            DecodeMode::I2c => "I2C",
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct DecodeSettings {
+    pub mode: DecodeMode,
+    pub uart: UartSettings,
+    pub i2c: I2cSettings,
+}
+
+impl Default for DecodeSettings {
+    fn default() -> Self {
+        Self {
+            mode: Default::default(),
+            uart: UartSettings {
+                channel: Channel::Ch1,
+                baud_rate: 9600,
+                auto_threshold: true,
+                threshold: 1.5,
+                inverted: false,
+            },
+            i2c: I2cSettings {
+                scl: Channel::Ch1,
+                auto_threshold: true,
+                threshold: 1.5,
+            },
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct UartSettings {
+    pub channel: Channel,
+    pub baud_rate: u32,
+    /// Halfway between the lowest and highest voltage instead of `threshold`
+    pub auto_threshold: bool,
+    /// In volts
+    pub threshold: f64,
+    pub inverted: bool,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct I2cSettings {
+    /// SDA is on the other channel
+    pub scl: Channel,
+    /// Halfway between the lowest and highest voltage of each line instead of `threshold`
+    pub auto_threshold: bool,
+    /// In volts
+    pub threshold: f64,
+}
+
+impl I2cSettings {
+    pub fn sda(&self) -> Channel {
+        match self.scl {
+            Channel::Ch1 => Channel::Ch2,
+            Channel::Ch2 => Channel::Ch1,
+            Channel::Ch3 => Channel::Ch4,
+            Channel::Ch4 => Channel::Ch3,
+        }
+    }
+}
+
+/// A decoded symbol, drawn over the time span it was decoded from.
+pub struct Annotation {
+    pub start: Time,
+    pub end: Time,
+    pub text: String,
+    /// Malformed, e.g. a framing error
+    pub error: bool,
+}
+
+/// Decodes the displayed data. `Err` is a message about why the data can't be decoded.
+pub fn decode(
+    settings: &DecodeSettings,
+    head: &DataHeader,
+    ch1_data: Option<&[u8]>,
+    ch2_data: Option<&[u8]>,
+) -> Result<Vec<Annotation>, String> {
+    let voltages = |channel: Channel| {
+        let data = match channel {
+            Channel::Ch1 => ch1_data,
+            Channel::Ch2 => ch2_data,
+            // only two channels are shown so far
+            Channel::Ch3 | Channel::Ch4 => None,
+        }
+        .ok_or_else(|| format!("{channel} is not displayed"))?;
+
+        Ok::<_, String>(
+            head.channel(channel)
+                .voltages(data, head.screen_samples())
+                .collect::<Vec<_>>(),
+        )
+    };
+
+    match settings.mode {
+        DecodeMode::Off => Ok(Vec::new()),
+        DecodeMode::Uart => {
+            let uart = &settings.uart;
+            let voltages = voltages(uart.channel)?;
+            let times = head.sample_times(voltages.len()).collect::<Vec<_>>();
+            let config = UartConfig {
+                baud_rate: uart.baud_rate,
+                threshold: (!uart.auto_threshold).then_some(Voltage(uart.threshold)),
+                inverted: uart.inverted,
+            };
+
+            let frames = uart::decode(&config, &times, &voltages).map_err(|e| e.to_string())?;
+            Ok(frames
+                .into_iter()
+                .map(|frame| Annotation {
+                    start: frame.start,
+                    end: frame.end,
+                    text: byte_label(frame.value),
+                    error: frame.framing_error,
+                })
+                .collect())
+        }
+        DecodeMode::I2c => {
+            let i2c = &settings.i2c;
+            let (scl, sda) = (voltages(i2c.scl)?, voltages(i2c.sda())?);
+            let times = head.sample_times(scl.len()).collect::<Vec<_>>();
+            let config = I2cConfig {
+                threshold: (!i2c.auto_threshold).then_some(Voltage(i2c.threshold)),
+            };
+
+            Ok(i2c::decode(&config, &times, &scl, &sda)
+                .into_iter()
+                .map(|symbol| Annotation {
+                    start: symbol.start,
+                    end: symbol.end,
+                    text: i2c_label(symbol.event),
+                    error: symbol.is_error(),
+                })
+                .collect())
+        }
+    }
+}
+
+fn i2c_label(event: I2cEvent) -> String {
+    let ack = |ack| if ack { "ACK" } else { "NAK" };
+    match event {
+        I2cEvent::Start => "S".to_owned(),
+        I2cEvent::Stop => "P".to_owned(),
+        I2cEvent::Address {
+            address,
+            read,
+            ack: acked,
+        } => format!(
+            "{} {address:02X} {}",
+            if read { "R" } else { "W" },
+            ack(acked)
+        ),
+        I2cEvent::Data {
+            value, ack: acked, ..
+        } => format!("{value:02X} {}", ack(acked)),
+        I2cEvent::Incomplete { bits } => format!("? ({bits} bits)"),
+    }
+}
+
+fn byte_label(value: u8) -> String {
+    if value.is_ascii_graphic() {
+        format!("{value:02X} '{}'", value as char)
+    } else {
+        format!("{value:02X}")
+    }
+}
@@ -0,0 +1,80 @@
+use super::OwowonApp;
+use egui::{Color32, Context};
+use egui_plot::{Line, Plot, PlotPoints};
+use owowon::device::LinkHealth;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// The connection health window: USB round-trip latencies over time and error counts.
+#[derive(Debug, Default)]
+pub struct HealthWindowState {
+    pub open: bool,
+    /// Shared with the device loop of each run, see [`LinkHealth`]
+    pub link: Arc<LinkHealth>,
+}
+
+pub fn window_ui(app: &mut OwowonApp, ctx: &Context) {
+    let state = &mut app.health_window;
+    // only record while someone is looking
+    state.link.set_enabled(state.open);
+    if !state.open {
+        return;
+    }
+
+    egui::Window::new("Connection health")
+        .open(&mut state.open)
+        .default_size([400.0, 250.0])
+        .show(ctx, |ui| {
+            let stats = state.link.stats().clone();
+            let now = Instant::now();
+            let latencies_ms = stats
+                .latencies
+                .iter()
+                .map(|(time, latency)| {
+                    [-(now - *time).as_secs_f64(), latency.as_secs_f64() * 1000.0]
+                })
+                .collect::<Vec<_>>();
+
+            ui.horizontal(|ui| match stats.latencies.back() {
+                Some((_, last)) => {
+                    let max = stats.latencies.iter().map(|(_, l)| *l).max().unwrap();
+                    let mean = stats.latencies.iter().map(|(_, l)| *l).sum::<Duration>()
+                        / stats.latencies.len() as u32;
+                    ui.label(format!(
+                        "Last {:.1} ms, mean {:.1} ms, max {:.1} ms",
+                        last.as_secs_f64() * 1000.0,
+                        mean.as_secs_f64() * 1000.0,
+                        max.as_secs_f64() * 1000.0
+                    ));
+                }
+                None => {
+                    ui.weak("No round trips recorded yet (the demo device has no USB link)");
+                }
+            });
+            ui.horizontal(|ui| {
+                let counts = format!("Timeouts: {}, errors: {}", stats.timeouts, stats.errors);
+                if stats.timeouts + stats.errors > 0 {
+                    ui.colored_label(Color32::YELLOW, counts);
+                } else {
+                    ui.label(counts);
+                }
+                if ui.button("Reset").clicked() {
+                    state.link.clear();
+                }
+            });
+
+            Plot::new("latency_plot")
+                .height(150.0)
+                .include_y(0.0)
+                .x_axis_label("s")
+                .y_axis_label("ms")
+                .allow_drag(false)
+                .allow_zoom(false)
+                .allow_scroll(false)
+                .show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(PlotPoints::new(latencies_ms)).name("round trip"))
+                });
+        });
+}
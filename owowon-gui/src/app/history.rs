@@ -0,0 +1,41 @@
+use owowon::SignalData;
+use std::collections::VecDeque;
+
+/// Rolling history of the last acquisitions, for scrolling back while acquisition continues.
+#[derive(Default)]
+pub struct History {
+    frames: VecDeque<SignalData>,
+    /// Frame shown instead of the live data, `None` while following the live data
+    position: Option<usize>,
+}
+
+impl History {
+    /// Adds the newest frame, dropping the oldest ones beyond `capacity`.
+    pub fn push(&mut self, frame: SignalData, capacity: usize) {
+        self.frames.push_back(frame);
+        while self.frames.len() > capacity.max(1) {
+            self.frames.pop_front();
+            // keep showing the same frame while it's still around
+            if let Some(position) = &mut self.position {
+                *position = position.saturating_sub(1);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn position(&self) -> Option<usize> {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: Option<usize>) {
+        self.position = position.filter(|&p| p < self.frames.len());
+    }
+
+    /// The frozen historical frame, if not following the live data.
+    pub fn selected(&self) -> Option<&SignalData> {
+        self.position.and_then(|p| self.frames.get(p))
+    }
+}
@@ -0,0 +1,119 @@
+use owowon::data::measurement::{MeasurementKind, Measurements};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Interval the buffered rows are written out in, so a slow drive doesn't stall the UI.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Continuous logging of the measurements to a CSV file, one row per acquisition.
+#[derive(Default)]
+pub struct MeasurementLogState {
+    pub path: String,
+    pub error: Option<String>,
+    logger: Option<MeasurementLogger>,
+}
+
+struct MeasurementLogger {
+    writer: BufWriter<File>,
+    last_flush: Instant,
+    rows: usize,
+}
+
+impl MeasurementLogState {
+    pub fn is_logging(&self) -> bool {
+        self.logger.is_some()
+    }
+
+    /// Rows written since logging started
+    pub fn rows(&self) -> usize {
+        self.logger.as_ref().map_or(0, |logger| logger.rows)
+    }
+
+    /// Starts appending to the file at `path`, writing the header first if it's a new file.
+    pub fn start(&mut self) {
+        self.stop();
+        match MeasurementLogger::open(self.path.trim()) {
+            Ok(logger) => {
+                self.error = None;
+                self.logger = Some(logger);
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    /// Flushes and closes the file.
+    pub fn stop(&mut self) {
+        if let Some(mut logger) = self.logger.take() {
+            if let Err(e) = logger.writer.flush() {
+                self.error = Some(e.to_string());
+            }
+        }
+    }
+
+    pub fn log(&mut self, measurements: &[Measurements; 2]) {
+        let Some(logger) = &mut self.logger else {
+            return;
+        };
+
+        if let Err(e) = logger.log(measurements) {
+            self.error = Some(e.to_string());
+            // the file is unusable from now on, don't try again every frame
+            self.logger = None;
+        }
+    }
+}
+
+impl MeasurementLogger {
+    fn open(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let is_new = file.metadata()?.len() == 0;
+        let mut writer = BufWriter::new(file);
+
+        if is_new {
+            write!(writer, "timestamp")?;
+            for channel in ["CH1", "CH2"] {
+                for kind in MeasurementKind::all() {
+                    write!(writer, ",{channel} {kind} ({})", kind.unit())?;
+                }
+            }
+            writeln!(writer)?;
+        }
+
+        Ok(Self {
+            writer,
+            last_flush: Instant::now(),
+            rows: 0,
+        })
+    }
+
+    fn log(&mut self, measurements: &[Measurements; 2]) -> io::Result<()> {
+        // seconds since the unix epoch, easy to convert in any spreadsheet or script
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        write!(self.writer, "{timestamp:.3}")?;
+
+        for measurements in measurements {
+            for kind in MeasurementKind::all() {
+                // unavailable measurements are left empty
+                match measurements.value(kind) {
+                    Some(value) => write!(self.writer, ",{}", value.0)?,
+                    None => write!(self.writer, ",")?,
+                }
+            }
+        }
+        writeln!(self.writer)?;
+        self.rows += 1;
+
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.writer.flush()?;
+            self.last_flush = Instant::now();
+        }
+
+        Ok(())
+    }
+}
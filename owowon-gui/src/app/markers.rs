@@ -0,0 +1,77 @@
+use super::plot::{time_to_x, x_to_time};
+use egui::{Button, TextEdit, Ui};
+use egui_plot::PlotPoint;
+use owowon::data::{
+    head::{Channel, DataHeader},
+    units::{Time, Voltage},
+};
+
+/// A labeled point dropped on the plot in annotate mode. Anchored to a time and voltage, so it
+/// stays put while the traces move underneath.
+#[derive(Debug, Clone)]
+pub struct Marker {
+    pub label: String,
+    /// Relative to the trigger point
+    time: Time,
+    /// Of `channel`, which decides the vertical scale/offset the marker follows
+    channel: Channel,
+    voltage: Voltage,
+}
+
+impl Marker {
+    /// Where the marker is drawn with the settings in `head`.
+    pub fn position(&self, head: &DataHeader) -> PlotPoint {
+        PlotPoint::new(
+            time_to_x(head, self.time),
+            head.channel(self.channel).voltage_to_units(self.voltage),
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Markers {
+    pub items: Vec<Marker>,
+    /// Number of the next default label
+    next: usize,
+}
+
+impl Markers {
+    /// Adds a marker at the plot position `point`, in the time base of `head` and the vertical
+    /// scale of `channel`.
+    pub fn add(&mut self, head: &DataHeader, channel: Channel, point: PlotPoint) {
+        self.next += 1;
+        self.items.push(Marker {
+            label: format!("M{}", self.next),
+            time: x_to_time(head, point.x),
+            channel,
+            voltage: head.channel(channel).units_to_voltage(point.y),
+        });
+    }
+}
+
+/// Editable labels and deletion.
+pub fn markers_ui(ui: &mut Ui, markers: &mut Markers, annotating: &mut bool) {
+    ui.checkbox(annotating, "Annotate")
+        .on_hover_text("Click the plot to drop a marker (at the active channel's scale)");
+
+    let mut remove = None;
+    for (i, marker) in markers.items.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.add(TextEdit::singleline(&mut marker.label).desired_width(120.0));
+            ui.weak(format!("{} {}", marker.time, marker.voltage));
+            if ui.button("Delete").clicked() {
+                remove = Some(i);
+            }
+        });
+    }
+    if let Some(i) = remove {
+        markers.items.remove(i);
+    }
+
+    if ui
+        .add_enabled(!markers.items.is_empty(), Button::new("Clear all"))
+        .clicked()
+    {
+        markers.items.clear();
+    }
+}
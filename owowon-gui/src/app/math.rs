@@ -0,0 +1,85 @@
+use owowon::data::head::{Channel, DataHeader};
+
+/// Trace derived from both channels, e.g. for differential measurements.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum MathMode {
+    #[default]
+    Off,
+    Add,
+    Subtract,
+    Multiply,
+}
+
+impl MathMode {
+    pub const ALL: [MathMode; 4] = [
+        MathMode::Off,
+        MathMode::Add,
+        MathMode::Subtract,
+        MathMode::Multiply,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MathMode::Off => "Off",
+            MathMode::Add => "CH1+CH2",
+            MathMode::Subtract => "CH1-CH2",
+            MathMode::Multiply => "CH1*CH2",
+        }
+    }
+
+    fn apply(self, ch1: f64, ch2: f64) -> f64 {
+        match self {
+            MathMode::Off => 0.0,
+            MathMode::Add => ch1 + ch2,
+            MathMode::Subtract => ch1 - ch2,
+            MathMode::Multiply => ch1 * ch2,
+        }
+    }
+
+    /// Value per plot unit of the math trace. Uses the CH1 scale (squared-ish for products), so
+    /// e.g. a difference of two equally scaled channels lines up with the grid.
+    pub fn scale_per_unit(self, head: &DataHeader) -> f64 {
+        let ch1 = head.channel(Channel::Ch1).scale_per_unit();
+        match self {
+            MathMode::Multiply => ch1 * head.channel(Channel::Ch2).scale_per_unit(),
+            _ => ch1,
+        }
+    }
+
+    /// Unit of the math trace values.
+    pub fn unit(self) -> &'static str {
+        match self {
+            MathMode::Multiply => "V²",
+            _ => "V",
+        }
+    }
+}
+
+/// Computes the math trace in plot units, one value per screen column. `None` if math is off or
+/// one of the channels has no data.
+pub fn math_trace(
+    mode: MathMode,
+    head: &DataHeader,
+    ch1_data: Option<&[u8]>,
+    ch2_data: Option<&[u8]>,
+) -> Option<Vec<f64>> {
+    if mode == MathMode::Off {
+        return None;
+    }
+
+    let screen_samples = head.screen_samples();
+    let ch1 = head
+        .channel(Channel::Ch1)
+        .voltages(ch1_data?, screen_samples);
+    let ch2 = head
+        .channel(Channel::Ch2)
+        .voltages(ch2_data?, screen_samples);
+    let scale_per_unit = mode.scale_per_unit(head);
+
+    // zip: the channels might (briefly, while switching modes) differ in length
+    Some(
+        ch1.zip(ch2)
+            .map(|(ch1, ch2)| mode.apply(ch1.0, ch2.0) / scale_per_unit)
+            .collect(),
+    )
+}
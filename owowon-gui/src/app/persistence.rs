@@ -0,0 +1,198 @@
+use super::plot::prep_channel_data;
+use egui::{Color32, ColorImage, Context, TextureHandle, TextureId, TextureOptions};
+use egui_plot::PlotPoint;
+use owowon::data::{
+    head::{Channel, DataHeader},
+    screen::ScreenGeometry,
+    units::{ProbeAttenuation, Time, Voltage},
+};
+use std::{collections::VecDeque, mem::discriminant};
+
+/// Height of the accumulated intensity map, one row per `i8` value. The width is the screen's, in
+/// samples.
+const HEIGHT: usize = 256;
+
+/// Keeping previous traces visible, like the phosphor of an analog scope.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Persistence {
+    #[default]
+    Off,
+    /// The given number of previous traces, fading out with age
+    Traces(usize),
+    /// Every trace since the settings last changed, accumulated into an intensity map
+    Infinite,
+}
+
+/// Settings that change where a signal ends up on screen. Persisted traces are dropped when they
+/// change.
+pub(super) type ScreenSettings = (
+    ScreenGeometry,
+    Time,
+    i64,
+    [(Voltage, i64, ProbeAttenuation); 2],
+);
+
+#[derive(Default)]
+pub struct PersistenceState {
+    mode: Persistence,
+    settings: Option<ScreenSettings>,
+    /// Traces per channel, newest (the live one) first
+    traces: VecDeque<[Option<Vec<PlotPoint>>; 2]>,
+    /// Hit counts per channel, row-major, see [`PersistenceState::size`]. Empty while not
+    /// accumulating.
+    hits: [Vec<u32>; 2],
+    /// Whether `hits` changed since the textures were last updated
+    dirty: bool,
+    textures: [Option<TextureHandle>; 2],
+}
+
+impl PersistenceState {
+    /// Adds the newest acquisition.
+    pub fn push(&mut self, mode: Persistence, head: &DataHeader, ch1_data: &[u8], ch2_data: &[u8]) {
+        let settings = screen_settings(head);
+        if discriminant(&mode) != discriminant(&self.mode) || self.settings != Some(settings) {
+            self.clear();
+        }
+        self.mode = mode;
+        self.settings = Some(settings);
+        if mode == Persistence::Off {
+            return;
+        }
+
+        let mut traces = [ch1_data, ch2_data]
+            .into_iter()
+            .zip([Channel::Ch1, Channel::Ch2])
+            .map(|(data, channel)| {
+                (!data.is_empty()).then(|| prep_channel_data(data, head, channel))
+            });
+        let traces = [traces.next().flatten(), traces.next().flatten()];
+
+        match mode {
+            Persistence::Off => unreachable!(),
+            Persistence::Traces(depth) => {
+                self.traces.push_front(traces);
+                self.traces.truncate(depth + 1);
+            }
+            Persistence::Infinite => {
+                let size = self.size();
+                let center = head.time_base.screen.center();
+                for (hits, trace) in self.hits.iter_mut().zip(&traces) {
+                    if let Some(trace) = trace {
+                        accumulate(hits, trace, size, center);
+                    }
+                }
+                self.dirty = true;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.traces.clear();
+        for hits in &mut self.hits {
+            hits.clear();
+        }
+        self.dirty = true;
+    }
+
+    /// The previous traces with their fade factor, oldest (most faded) first so newer ones are
+    /// drawn on top.
+    pub fn traces(&self) -> impl Iterator<Item = (f32, &[Option<Vec<PlotPoint>>; 2])> {
+        let depth = self.traces.len();
+        self.traces
+            .iter()
+            .enumerate()
+            // the newest one is the live trace, drawn anyway
+            .skip(1)
+            .rev()
+            .map(move |(age, traces)| (0.6 * (1.0 - age as f32 / depth as f32), traces))
+    }
+
+    /// Uploads the accumulated intensity maps, if they changed.
+    pub fn update_textures(&mut self, ctx: &Context, colors: [Color32; 2]) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+
+        for (i, (hits, color)) in self.hits.iter().zip(colors).enumerate() {
+            if hits.is_empty() {
+                self.textures[i] = None;
+                continue;
+            }
+
+            // log scale, so rare glitches stay visible next to the bulk of the traces
+            let max = (*hits.iter().max().unwrap_or(&0) as f32)
+                .ln_1p()
+                .max(f32::EPSILON);
+            let pixels = hits
+                .iter()
+                .map(|&hits| color.gamma_multiply((hits as f32).ln_1p() / max))
+                .collect();
+            let image = ColorImage {
+                size: self.size(),
+                pixels,
+            };
+
+            match &mut self.textures[i] {
+                Some(texture) => texture.set(image, TextureOptions::NEAREST),
+                texture @ None => {
+                    *texture = Some(ctx.load_texture(
+                        format!("persistence_ch{}", i + 1),
+                        image,
+                        TextureOptions::NEAREST,
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Width and height of the accumulated intensity maps.
+    pub fn size(&self) -> [usize; 2] {
+        let width = self.settings.map_or(0.0, |(screen, ..)| screen.width());
+        [width.round() as usize, HEIGHT]
+    }
+
+    /// The accumulated intensity maps, see [`Self::size`].
+    pub fn textures(&self) -> impl Iterator<Item = TextureId> + '_ {
+        self.textures.iter().flatten().map(TextureHandle::id)
+    }
+}
+
+pub(super) fn screen_settings(head: &DataHeader) -> ScreenSettings {
+    let channel = |channel| {
+        let info = head.channel(channel);
+        (info.scale, info.offset, info.probe)
+    };
+    (
+        head.time_base.screen,
+        head.time_base.scale,
+        head.time_base.h_offset,
+        [channel(Channel::Ch1), channel(Channel::Ch2)],
+    )
+}
+
+fn accumulate(hits: &mut Vec<u32>, trace: &[PlotPoint], size: [usize; 2], center: f64) {
+    let [width, height] = size;
+    hits.resize(width * height, 0);
+
+    let column = |point: &PlotPoint| {
+        let column = (point.x + center).round();
+        (0.0..width as f64)
+            .contains(&column)
+            .then_some(column as usize)
+    };
+    let row = |point: &PlotPoint| {
+        (i8::MAX as f64 - point.y.round()).clamp(0.0, height as f64 - 1.0) as usize
+    };
+
+    // fill the rows up to the next point, so steep edges show up as well
+    for (point, next) in trace.iter().zip(trace.iter().skip(1).chain(trace.last())) {
+        let Some(column) = column(point) else {
+            continue;
+        };
+        let (from, to) = (row(point), row(next));
+        for row in from.min(to)..=from.max(to) {
+            hits[row * width + column] = hits[row * width + column].saturating_add(1);
+        }
+    }
+}
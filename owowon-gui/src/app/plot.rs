@@ -1,38 +1,219 @@
-use super::{OscilloscopeUiState, OwowonApp};
-use egui::{Color32, Ui, Vec2};
-use egui_plot::{GridInput, GridMark, HLine, Line, LineStyle, Plot, PlotPoints, VLine};
+use super::{
+    decode::{decode, Annotation},
+    math::{math_trace, MathMode},
+    OscilloscopeUiState, OwowonApp,
+};
+use egui::{Align2, Color32, Rect, RichText, Ui, Vec2};
+use egui_plot::{
+    GridInput, GridMark, HLine, Line, LineStyle, MarkerShape, Plot, PlotImage, PlotPoint,
+    PlotPoints, PlotUi, Points, Text, VLine,
+};
+use owowon::scaled_number::ScaledNumber;
 use owowon::{
-    consts::{GRID_DIV_COUNT_HORIZONTAL, GRID_DIV_SIZE, SAMPLES},
-    data::{head::Channel, units::Voltage},
+    consts::GRID_DIV_SIZE,
+    data::{
+        head::{Channel, DataHeader, SampleType},
+        screen::ScreenGeometry,
+        units::{Time, Voltage},
+    },
 };
-use std::ops::Deref;
+use std::{fmt::Write, ops::Deref};
+
+const CHANNEL_COLORS: [Color32; 2] = [Color32::YELLOW, Color32::LIGHT_BLUE];
+/// Vertical extent of the plot: the sample range, plus room for the lines at its edges
+const Y_BOUNDS: [f64; 2] = [-128.25, 127.25];
+
+/// How a channel's trace is drawn, e.g. to tell overlapping traces apart or for printing.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct TraceStyle {
+    /// In points
+    pub width: f32,
+    pub dash: TraceDash,
+    /// Only hides the trace in the plot, the device keeps showing (and measuring) the channel
+    pub hidden: bool,
+}
+
+impl Default for TraceStyle {
+    fn default() -> Self {
+        Self {
+            // egui_plot's default
+            width: 1.5,
+            dash: TraceDash::Solid,
+            hidden: false,
+        }
+    }
+}
+
+impl TraceStyle {
+    fn apply(self, line: Line) -> Line {
+        let style = match self.dash {
+            TraceDash::Solid => LineStyle::Solid,
+            TraceDash::Dashed => LineStyle::dashed_loose(),
+            TraceDash::Dotted => LineStyle::dotted_dense(),
+        };
+        line.width(self.width).style(style)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum TraceDash {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl TraceDash {
+    pub const ALL: [TraceDash; 3] = [TraceDash::Solid, TraceDash::Dashed, TraceDash::Dotted];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TraceDash::Solid => "Solid",
+            TraceDash::Dashed => "Dashed",
+            TraceDash::Dotted => "Dotted",
+        }
+    }
+}
+
+pub(crate) fn ui(app: &mut OwowonApp, ui: &mut Ui) {
+    app.osc_ui_state
+        .persistence
+        .update_textures(ui.ctx(), CHANNEL_COLORS);
+    let (pointer, clicked) = if app.persistent_state.square_divisions {
+        let screen = app.osc_ui_state.head.time_base.screen;
+        let rect = square_divisions_rect(ui.available_rect_before_wrap(), &screen);
+        ui.allocate_ui_at_rect(rect, |ui| plot(app, ui)).inner
+    } else {
+        plot(app, ui)
+    };
+    app.plot_pointer = pointer;
+
+    if let (true, Some(point)) = (app.annotating && clicked, pointer) {
+        let head = match app.osc_ui_state.history.selected() {
+            Some(frame) => &frame.header,
+            None => &app.osc_ui_state.head,
+        };
+        app.osc_ui_state
+            .markers
+            .add(head, app.active_channel, point);
+    }
+}
 
-pub(crate) fn ui(app: &OwowonApp, ui: &mut Ui) {
+/// The largest rect centered in `available` whose divisions are square, like on the scope's screen.
+fn square_divisions_rect(available: Rect, screen: &ScreenGeometry) -> Rect {
+    let [bottom, top] = Y_BOUNDS;
+    let vertical_divs = (top - bottom) / GRID_DIV_SIZE;
+    let aspect = (screen.horizontal_divs / vertical_divs) as f32;
+    let size = if available.width() > available.height() * aspect {
+        Vec2::new(available.height() * aspect, available.height())
+    } else {
+        Vec2::new(available.width(), available.width() / aspect)
+    };
+    Rect::from_center_size(available.center(), size)
+}
+
+/// Draws the plot, returning the hovered plot position (if any) and whether it was clicked.
+fn plot(app: &OwowonApp, ui: &mut Ui) -> (Option<PlotPoint>, bool) {
     let OscilloscopeUiState {
         head,
         ch1_data,
         ch2_data,
         ..
     } = &app.osc_ui_state;
+    let voltage_display = app.persistent_state.voltage_display;
+    let math_mode = app.persistent_state.math_mode;
+    let dbm_impedance = app.persistent_state.dbm_impedance;
+    let precision = app.persistent_state.display_precision;
+    let number_format = app.persistent_state.number_format;
+    let show_overlay = app.persistent_state.show_overlay;
+    let trace_styles = app.persistent_state.trace_styles;
 
     let ch1_data = (!ch1_data.is_empty()).then_some(ch1_data.deref());
     let ch2_data = (!ch2_data.is_empty()).then_some(ch2_data.deref());
 
+    // the persistence is of the live traces, hide it while scrolled back
+    let persistence = app
+        .osc_ui_state
+        .history
+        .selected()
+        .is_none()
+        .then_some(&app.osc_ui_state.persistence);
+
+    // scrolled back: freeze on the historical frame instead
+    let (head, ch1_data, ch2_data, clipping, averaged) = match app.osc_ui_state.history.selected() {
+        Some(frame) => (
+            &frame.header,
+            frame.channel_data(Channel::Ch1),
+            frame.channel_data(Channel::Ch2),
+            [Channel::Ch1, Channel::Ch2].map(|ch| frame.channel_clipping(ch)),
+            0,
+        ),
+        None => (
+            head,
+            ch1_data,
+            ch2_data,
+            app.osc_ui_state.clipping,
+            app.osc_ui_state.averaging.averaged(),
+        ),
+    };
+    let average_count = app.persistent_state.average_count;
+
+    // physical pixel columns the plot spans, there's no point in drawing more than that
+    let columns = (ui.available_width() * ui.ctx().pixels_per_point()) as usize;
+
     let (line1, line2) = {
-        let line1 = ch1_data.map(prep_channel_data);
-        let line2 = ch2_data.map(prep_channel_data);
+        let line1 = ch1_data.map(|data| {
+            PlotPoints::Owned(decimate(
+                prep_channel_data(data, head, Channel::Ch1),
+                columns,
+            ))
+        });
+        let line2 = ch2_data.map(|data| {
+            PlotPoints::Owned(decimate(
+                prep_channel_data(data, head, Channel::Ch2),
+                columns,
+            ))
+        });
         (line1, line2)
     };
+    let screen = head.time_base.screen;
+    let center = screen.center();
+    let line_math = math_trace(math_mode, head, ch1_data, ch2_data).map(|values| {
+        PlotPoints::Owned(decimate(
+            values
+                .into_iter()
+                .enumerate()
+                .map(|(i, val)| [i as f64 - center, val].into())
+                .collect(),
+            columns,
+        ))
+    });
+
+    let decoded = decode(&app.persistent_state.decode, head, ch1_data, ch2_data);
+
+    let markers = &app.osc_ui_state.markers.items;
+
+    let references = app
+        .osc_ui_state
+        .references
+        .each_ref()
+        .map(|reference| reference.as_ref().map(|reference| reference.points(head)));
 
     let head = *head;
+    // the label formatter has to own what it reads
+    let samples = [ch1_data.map(<[u8]>::to_vec), ch2_data.map(<[u8]>::to_vec)];
     Plot::new("osc")
-        .include_y(-128.25)
-        .include_y(127.25)
-        .include_x(-150.0f32)
-        .include_x(150.0f32)
+        .include_y(Y_BOUNDS[0])
+        .include_y(Y_BOUNDS[1])
+        .include_x(-screen.width() / 2.0)
+        .include_x(screen.width() / 2.0)
         .set_margin_fraction(Vec2::ZERO)
-        .x_grid_spacer(const_grid_lines)
-        .y_grid_spacer(const_grid_lines)
+        .x_grid_spacer(grid_lines(
+            screen.samples_per_div,
+            (screen.horizontal_divs / 2.0).ceil() as i32 - 1,
+        ))
+        .y_grid_spacer(grid_lines(GRID_DIV_SIZE, 5))
         .allow_boxed_zoom(false)
         .allow_drag(false)
         .allow_scroll(false)
@@ -42,34 +223,115 @@ pub(crate) fn ui(app: &OwowonApp, ui: &mut Ui) {
         .show_x(true)
         .show_y(true)
         .label_formatter(move |name, point| {
-            let channel = match name {
-                "CH1" => Channel::Ch1,
-                "CH2" => Channel::Ch2,
+            let format_voltage =
+                |voltage| voltage_display.format(voltage, dbm_impedance, number_format, precision);
+            let mut label = match name {
+                "CH1" | "CH2" => {
+                    let channel = head.channel(if name == "CH1" {
+                        Channel::Ch1
+                    } else {
+                        Channel::Ch2
+                    });
+                    format!(
+                        "{}: {}",
+                        channel.channel,
+                        format_voltage(channel.units_to_voltage(point.y))
+                    )
+                }
+                "MATH" => {
+                    let val = point.y * math_mode.scale_per_unit(&head);
+                    match math_mode {
+                        MathMode::Multiply => {
+                            format!(
+                                "MATH: {:.precision$}{}",
+                                ScaledNumber::from(val).display_with(number_format),
+                                math_mode.unit()
+                            )
+                        }
+                        _ => format!("MATH: {}", format_voltage(Voltage(val))),
+                    }
+                }
+                // not near a trace
+                "" => String::new(),
                 _ => return String::new(),
             };
 
-            let channel = head.channel(channel);
-            let vscale_per_unit = channel.scale_per_unit();
-            let offset_scaled = channel.offset as f64 * vscale_per_unit;
-            format!(
-                "{}: {}",
-                channel.channel,
-                Voltage(point.y * vscale_per_unit - offset_scaled)
-            )
+            // the actual samples under the pointer, not the line between them
+            for (channel, data) in [Channel::Ch1, Channel::Ch2].into_iter().zip(&samples) {
+                let Some((time, min, max)) = data
+                    .as_deref()
+                    .and_then(|data| samples_at(&head, channel, data, point.x))
+                else {
+                    continue;
+                };
+                let value = if min == max {
+                    format_voltage(min).to_string()
+                } else {
+                    format!("{} to {}", format_voltage(min), format_voltage(max))
+                };
+                if !label.is_empty() {
+                    label.push('\n');
+                }
+                let _ = write!(label, "{channel} sample @ {time}: {value}");
+            }
+            label
         })
         .show(ui, |plot_ui| {
-            if let Some(line) = line1 {
-                plot_ui.line(Line::new(line).name("CH1").color(Color32::YELLOW));
+            if let Some(persistence) = persistence {
+                for texture in persistence.textures() {
+                    // pixel (0, 0) is the first sample at the top of the screen
+                    let [width, height] = persistence.size().map(|size| size as f64);
+                    let center = PlotPoint::new(
+                        (width - 1.0) / 2.0 - center,
+                        i8::MAX as f64 - (height - 1.0) / 2.0,
+                    );
+                    plot_ui.image(PlotImage::new(
+                        texture,
+                        center,
+                        Vec2::new(width as f32, height as f32),
+                    ));
+                }
+
+                for (fade, traces) in persistence.traces() {
+                    for (trace, color) in traces.iter().zip(CHANNEL_COLORS) {
+                        if let Some(trace) = trace {
+                            plot_ui.line(
+                                Line::new(PlotPoints::Owned(decimate(trace.clone(), columns)))
+                                    .color(color.gamma_multiply(fade)),
+                            );
+                        }
+                    }
+                }
             }
-            if let Some(line) = line2 {
-                plot_ui.line(Line::new(line).name("CH2").color(Color32::LIGHT_BLUE));
+
+            for (points, color) in references.into_iter().zip(CHANNEL_COLORS) {
+                if let Some(points) = points {
+                    plot_ui.line(
+                        Line::new(PlotPoints::Owned(decimate(points, columns)))
+                            .color(color.gamma_multiply(0.35)),
+                    );
+                }
             }
 
-            let trigger_channel = head.channel(head.trigger.items.channel);
-            let voltage_per_unit = trigger_channel.scale_per_unit();
-            let offset = trigger_channel.offset;
+            if let Some(line) = line1.filter(|_| !trace_styles[0].hidden) {
+                let line = Line::new(line).name("CH1").color(CHANNEL_COLORS[0]);
+                plot_ui.line(trace_styles[0].apply(line));
+            }
+            if let Some(line) = line2.filter(|_| !trace_styles[1].hidden) {
+                let line = Line::new(line).name("CH2").color(CHANNEL_COLORS[1]);
+                plot_ui.line(trace_styles[1].apply(line));
+            }
+            if let Some(line) = line_math {
+                plot_ui.line(
+                    Line::new(line)
+                        .name("MATH")
+                        .color(Color32::from_rgb(220, 80, 220)),
+                );
+            }
 
-            let trigger_level = head.trigger.items.level.0 / voltage_per_unit + offset as f64;
+            let trigger_level = head
+                .channel(head.trigger.items.channel)
+                .voltage_to_units(head.trigger.items.level);
 
             plot_ui.hline(
                 HLine::new(trigger_level)
@@ -84,65 +346,286 @@ pub(crate) fn ui(app: &OwowonApp, ui: &mut Ui) {
                     .color(Color32::from_rgb(160, 80, 80))
                     .style(LineStyle::Solid),
             );
-        });
+
+            if show_overlay {
+                overlay(plot_ui, &head, clipping, (averaged, average_count));
+            }
+
+            match &decoded {
+                Ok(annotations) => decode_annotations(plot_ui, &head, annotations),
+                Err(e) => plot_ui.text(
+                    Text::new(
+                        PlotPoint::new(-148.0, 126.0),
+                        RichText::new(format!("Decode: {e}"))
+                            .color(Color32::LIGHT_RED)
+                            .monospace(),
+                    )
+                    .anchor(Align2::LEFT_TOP),
+                ),
+            }
+
+            for marker in markers {
+                let position = marker.position(&head);
+                plot_ui.points(
+                    Points::new(PlotPoints::Owned(vec![position]))
+                        .shape(MarkerShape::Diamond)
+                        .radius(5.0)
+                        .color(Color32::WHITE),
+                );
+                plot_ui.text(
+                    Text::new(
+                        PlotPoint::new(position.x + 2.0, position.y + 2.0),
+                        RichText::new(&marker.label).color(Color32::WHITE),
+                    )
+                    .anchor(Align2::LEFT_BOTTOM),
+                );
+            }
+
+            (plot_ui.pointer_coordinate(), plot_ui.response().clicked())
+        })
+        .inner
+}
+
+/// Scope-like info in the plot corners, where the traces are the least likely to be.
+/// `averaged` is the number of acquisitions averaged so far and the number to average, see
+/// [`AveragingState`](super::averaging::AveragingState).
+fn overlay(plot_ui: &mut PlotUi, head: &DataHeader, clipping: [bool; 2], averaged: (usize, usize)) {
+    // the vertical grid is the same on every model, see `ScreenGeometry`
+    const TOP: f64 = Y_BOUNDS[1] - 1.25;
+    const BOTTOM: f64 = Y_BOUNDS[0] + 1.25;
+    // samples between the text and the screen edge
+    const INSET: f64 = 2.0;
+
+    let half_width = head.time_base.screen.width() / 2.0;
+    let (left, right) = (-half_width + INSET, half_width - INSET);
+
+    let mut x = left;
+    for ((channel, color), clipping) in [Channel::Ch1, Channel::Ch2]
+        .into_iter()
+        .zip(CHANNEL_COLORS)
+        .zip(clipping)
+    {
+        if !head.channel_enabled(channel) {
+            continue;
+        }
+        let info = head.channel(channel);
+        // in red while the trace is cut off, a flat top alone doesn't explain itself
+        let (over, color) = if clipping {
+            (" OVER", Color32::RED)
+        } else {
+            ("", color)
+        };
+        plot_ui.text(
+            Text::new(
+                PlotPoint::new(x, BOTTOM),
+                RichText::new(format!("{channel} {}/div{over}", info.scale_attenuated()))
+                    .color(color)
+                    .monospace(),
+            )
+            .anchor(Align2::LEFT_BOTTOM),
+        );
+        x += head.time_base.screen.width() / 5.0;
+    }
+
+    plot_ui.text(
+        Text::new(
+            PlotPoint::new(right, BOTTOM),
+            RichText::new(format!(
+                "{}/div  {}",
+                head.time_base.scale, head.sample.sampling_rate
+            ))
+            .monospace(),
+        )
+        .anchor(Align2::RIGHT_BOTTOM),
+    );
+
+    let (averaged, average_count) = averaged;
+    if averaged > 0 {
+        plot_ui.text(
+            Text::new(
+                // the top left is taken by decoder errors
+                PlotPoint::new(0.0, TOP),
+                RichText::new(format!("Avg {averaged}/{average_count}")).monospace(),
+            )
+            .anchor(Align2::CENTER_TOP),
+        );
+    }
+
+    let trigger = &head.trigger.items;
+    plot_ui.text(
+        Text::new(
+            PlotPoint::new(right, TOP),
+            RichText::new(format!(
+                "T {} {} {}",
+                trigger.channel, trigger.edge, trigger.level
+            ))
+            .color(Color32::from_rgb(160, 80, 80))
+            .monospace(),
+        )
+        .anchor(Align2::RIGHT_TOP),
+    );
+}
+
+/// Decoded symbols in a row near the top, each spanning the time it was decoded from.
+fn decode_annotations(plot_ui: &mut PlotUi, head: &DataHeader, annotations: &[Annotation]) {
+    const ROW: f64 = 105.0;
+    const TICK: f64 = 4.0;
+
+    for annotation in annotations {
+        let color = if annotation.error {
+            Color32::LIGHT_RED
+        } else {
+            Color32::WHITE
+        };
+        let (start, end) = (
+            time_to_x(head, annotation.start),
+            time_to_x(head, annotation.end),
+        );
+
+        plot_ui.line(
+            Line::new(PlotPoints::new(vec![
+                [start, ROW - TICK],
+                [start, ROW],
+                [end, ROW],
+                [end, ROW - TICK],
+            ]))
+            .color(color),
+        );
+        plot_ui.text(
+            Text::new(
+                PlotPoint::new((start + end) / 2.0, ROW + 1.0),
+                RichText::new(&annotation.text).color(color).monospace(),
+            )
+            .anchor(Align2::CENTER_BOTTOM),
+        );
+    }
+}
+
+/// The plot x position of `time` (relative to the trigger).
+// keep in line with `TimeBase::sample_times`
+pub(super) fn time_to_x(head: &DataHeader, time: Time) -> f64 {
+    let time_base = &head.time_base;
+    time / time_base.scale * time_base.screen.samples_per_div - time_base.h_offset as f64
 }
 
-fn prep_channel_data(data: &[u8]) -> PlotPoints {
-    const WEIRD_OFFSET: f64 = GRID_DIV_SIZE * GRID_DIV_COUNT_HORIZONTAL / 2.0 - 1.0;
+/// The inverse of [`time_to_x`].
+pub(super) fn x_to_time(head: &DataHeader, x: f64) -> Time {
+    let time_base = &head.time_base;
+    Time((x + time_base.h_offset as f64) / time_base.screen.samples_per_div * time_base.scale.0)
+}
+
+/// Whether `data` holds a min/max pair per screen column, which peak detect packs into data
+/// longer than the screen. Other packed data is reduced to the midpoints, see
+/// [`ChannelInfo::voltages`](owowon::data::head::ChannelInfo::voltages).
+fn has_min_max_pairs(head: &DataHeader, data: &[u8]) -> bool {
+    data.len() > head.screen_samples() && head.sample.sample_type == SampleType::Peak
+}
 
-    fn two((i, [val1, val2]): (usize, [u8; 2])) -> [f64; 2] {
-        [
-            i as f64 - WEIRD_OFFSET,
-            ((val1 as i8 as f64) + (val2 as i8 as f64)) / 2.0,
-        ]
+/// The sample in the screen column nearest to the plot position `x`, as min/max (the same unless
+/// the column holds a min/max pair, see [`has_min_max_pairs`]), and the column's time relative to
+/// the trigger.
+fn samples_at(
+    head: &DataHeader,
+    channel: Channel,
+    data: &[u8],
+    x: f64,
+) -> Option<(Time, Voltage, Voltage)> {
+    let center = head.time_base.screen.center();
+    let column = (x + center).round();
+    if column < 0.0 {
+        return None;
     }
+    let time = x_to_time(head, column - center);
+    let column = column as usize;
 
-    fn one((i, val): (usize, u8)) -> [f64; 2] {
-        [i as f64 - WEIRD_OFFSET, val as i8 as f64]
+    let info = head.channel(channel);
+    if has_min_max_pairs(head, data) {
+        let pair = data.chunks_exact(2).nth(column)?;
+        let (a, b) = (pair[0] as i8, pair[1] as i8);
+        let [min, max] = [a.min(b), a.max(b)].map(|sample| info.sample_to_voltage(sample));
+        Some((time, min, max))
+    } else {
+        let voltage = info.voltages(data, head.screen_samples()).nth(column)?;
+        Some((time, voltage, voltage))
     }
+}
+
+/// The plot points of a channel's screen data.
+pub(super) fn prep_channel_data(
+    data: &[u8],
+    head: &DataHeader,
+    channel: Channel,
+) -> Vec<PlotPoint> {
+    let screen_samples = head.screen_samples();
+    let center = head.time_base.screen.center();
+    let channel = head.channel(channel);
 
-    let vec = if data.len() != SAMPLES {
-        data.array_chunks::<2>()
-            .copied()
+    // the plot is in screen units, but going through the voltage keeps the traces in line with
+    // everything else decoding the data (measurements, math, exports)
+    if has_min_max_pairs(head, data) {
+        // In peak detect mode, each pair is the min/max of its interval. Draw a vertical bar from
+        // one to the other, alternating the direction so the bars connect into a filled envelope.
+        data.chunks_exact(2)
             .enumerate()
-            .map(two)
+            .flat_map(|(i, pair)| {
+                let x = i as f64 - center;
+                let [val1, val2] = [pair[0], pair[1]].map(|sample| {
+                    channel.voltage_to_units(channel.sample_to_voltage(sample as i8))
+                });
+                let (min, max) = (val1.min(val2), val1.max(val2));
+
+                if i % 2 == 0 {
+                    [[x, min], [x, max]]
+                } else {
+                    [[x, max], [x, min]]
+                }
+            })
             .map(Into::into)
             .collect()
     } else {
-        data.iter()
-            .copied()
+        channel
+            .voltages(data, screen_samples)
             .enumerate()
-            .map(one)
-            .map(Into::into)
+            .map(|(i, voltage)| [i as f64 - center, channel.voltage_to_units(voltage)].into())
             .collect()
-    };
-
-    PlotPoints::Owned(vec)
+    }
 }
 
-fn const_grid_lines(grid_input: GridInput) -> Vec<GridMark> {
-    const GRID_LINES: [f64; 11] = [
-        GRID_DIV_SIZE * -5.0,
-        GRID_DIV_SIZE * -4.0,
-        GRID_DIV_SIZE * -3.0,
-        GRID_DIV_SIZE * -2.0,
-        GRID_DIV_SIZE * -1.0,
-        GRID_DIV_SIZE * 0.0,
-        GRID_DIV_SIZE * 1.0,
-        GRID_DIV_SIZE * 2.0,
-        GRID_DIV_SIZE * 3.0,
-        GRID_DIV_SIZE * 4.0,
-        GRID_DIV_SIZE * 5.0,
-    ];
-
-    let step_size = grid_input.base_step_size * GRID_DIV_SIZE;
+/// Min/max decimation: reduces `points` to two per pixel column, keeping the extremes so glitches
+/// stay visible. A no-op if there aren't more points than that anyway (e.g. the 300 sample screen).
+fn decimate(points: Vec<PlotPoint>, columns: usize) -> Vec<PlotPoint> {
+    let columns = columns.max(1);
+    if points.len() <= columns * 2 {
+        return points;
+    }
 
-    GRID_LINES
-        .into_iter()
-        .filter(|&p| grid_input.bounds.0 <= p && p <= grid_input.bounds.1)
-        .map(|p| GridMark {
-            value: p,
-            step_size,
+    points
+        .chunks(points.len().div_ceil(columns))
+        .flat_map(|chunk| {
+            let min = chunk.iter().min_by(|a, b| a.y.total_cmp(&b.y)).unwrap();
+            let max = chunk.iter().max_by(|a, b| a.y.total_cmp(&b.y)).unwrap();
+            // in sample order, so the line doesn't jump back and forth
+            if min.x <= max.x {
+                [*min, *max]
+            } else {
+                [*max, *min]
+            }
         })
         .collect()
 }
+
+/// Grid lines every `div_size` units, `count` on each side of the center line.
+fn grid_lines(div_size: f64, count: i32) -> impl Fn(GridInput) -> Vec<GridMark> {
+    move |grid_input| {
+        let step_size = grid_input.base_step_size * div_size;
+
+        (-count..=count)
+            .map(|i| i as f64 * div_size)
+            .filter(|&p| grid_input.bounds.0 <= p && p <= grid_input.bounds.1)
+            .map(|p| GridMark {
+                value: p,
+                step_size,
+            })
+            .collect()
+    }
+}
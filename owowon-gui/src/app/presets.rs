@@ -0,0 +1,223 @@
+use super::OwowonApp;
+use crate::optional_sender::OptionalSender;
+use egui::{Context, TextEdit, Ui};
+use owowon::{
+    data::{
+        head::{Channel, ChannelCoupling, DataHeader, TriggerCoupling, TriggerEdge, TriggerSweep},
+        units::{ProbeAttenuation, Time, Voltage},
+    },
+    OscilloscopeCommand, OscilloscopeRunCommand,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// A named bundle of settings (time base, channels, trigger), applied with one click from the
+/// presets bar.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Preset {
+    pub name: String,
+    /// Seconds per div
+    time_scale: f64,
+    channels: [PresetChannel; 2],
+    trigger: PresetTrigger,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+struct PresetChannel {
+    enabled: bool,
+    probe: u32,
+    /// Volts per div, attenuated
+    scale: f64,
+    /// In grid divs
+    offset: f64,
+    coupling: ChannelCoupling,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+struct PresetTrigger {
+    source: Channel,
+    edge: TriggerEdge,
+    /// In volts
+    level: f64,
+    coupling: TriggerCoupling,
+    sweep: TriggerSweep,
+}
+
+impl Preset {
+    /// The current settings shown by `head`.
+    pub fn capture(name: String, head: &DataHeader) -> Self {
+        let channel = |ch: Channel| {
+            let info = head.channel(ch);
+            PresetChannel {
+                enabled: head.channel_enabled(ch),
+                probe: info.probe.0,
+                scale: info.scale_attenuated().0,
+                offset: info.offset_grid_divs(),
+                coupling: info.coupling,
+            }
+        };
+        let items = &head.trigger.items;
+
+        Self {
+            name,
+            time_scale: head.time_base.scale.0,
+            channels: [channel(Channel::Ch1), channel(Channel::Ch2)],
+            trigger: PresetTrigger {
+                source: items.channel,
+                edge: items.edge,
+                level: items.level.0,
+                coupling: items.coupling,
+                sweep: items.sweep,
+            },
+        }
+    }
+
+    /// The commands restoring this preset, in the order they have to be sent (attenuation before
+    /// scale, trigger source before level).
+    pub fn commands(&self) -> Vec<OscilloscopeCommand> {
+        let mut commands = vec![OscilloscopeCommand::SetTimeScale(Time(self.time_scale))];
+
+        for (ch, settings) in [Channel::Ch1, Channel::Ch2].into_iter().zip(&self.channels) {
+            commands.extend([
+                OscilloscopeCommand::SetChannelDisplay(ch, settings.enabled.into()),
+                OscilloscopeCommand::SetChannelAttenuation(ch, ProbeAttenuation(settings.probe)),
+                OscilloscopeCommand::SetChannelVScale(ch, Voltage(settings.scale)),
+                OscilloscopeCommand::SetChannelVOffset(ch, settings.offset),
+                OscilloscopeCommand::SetChannelCoupling(ch, settings.coupling),
+            ]);
+        }
+
+        let trigger = &self.trigger;
+        commands.extend([
+            OscilloscopeCommand::SetTriggerSource(trigger.source),
+            OscilloscopeCommand::SetTriggerEdge(trigger.edge),
+            OscilloscopeCommand::SetTriggerLevel(Voltage(trigger.level)),
+            OscilloscopeCommand::SetTriggerCoupling(trigger.coupling),
+            OscilloscopeCommand::SetTriggerSweep(trigger.sweep),
+        ]);
+
+        commands
+    }
+
+    /// One line per part, for the hover text of the preset's button.
+    pub fn summary(&self) -> String {
+        let mut summary = format!("{}/div", Time(self.time_scale));
+        for (ch, settings) in [Channel::Ch1, Channel::Ch2].into_iter().zip(&self.channels) {
+            if settings.enabled {
+                let _ = write!(
+                    summary,
+                    "\n{ch}: {}/div {} (x{} probe)",
+                    Voltage(settings.scale),
+                    settings.coupling,
+                    settings.probe
+                );
+            } else {
+                let _ = write!(summary, "\n{ch}: off");
+            }
+        }
+        let trigger = &self.trigger;
+        let _ = write!(
+            summary,
+            "\nTrigger: {} {} at {}, {} coupling, {}",
+            trigger.source,
+            trigger.edge,
+            Voltage(trigger.level),
+            trigger.coupling,
+            trigger.sweep
+        );
+
+        summary
+    }
+}
+
+/// The presets editor window.
+#[derive(Debug, Default)]
+pub struct PresetsWindowState {
+    pub open: bool,
+    /// Name for the next preset saved from the current settings
+    pub new_name: String,
+}
+
+/// One button per preset, plus opening the editor.
+pub fn bar_ui(
+    app: &mut OwowonApp,
+    ui: &mut Ui,
+    command_tx: &OptionalSender<OscilloscopeRunCommand>,
+) {
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Presets");
+        for preset in &app.persistent_state.presets {
+            if ui
+                .button(&preset.name)
+                .on_hover_text(preset.summary())
+                .clicked()
+            {
+                command_tx.apply_preset(preset);
+            }
+        }
+        if app.persistent_state.presets.is_empty() {
+            ui.weak("none yet");
+        }
+        ui.toggle_value(&mut app.presets_window.open, "edit…");
+    });
+}
+
+pub fn window_ui(app: &mut OwowonApp, ctx: &Context) {
+    let head = &app.osc_ui_state.head;
+    let presets = &mut app.persistent_state.presets;
+    let new_name = &mut app.presets_window.new_name;
+
+    egui::Window::new("Presets")
+        .open(&mut app.presets_window.open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.add(
+                    TextEdit::singleline(new_name)
+                        .hint_text("name")
+                        .desired_width(150.0),
+                );
+                if ui
+                    .add_enabled(
+                        !new_name.trim().is_empty(),
+                        egui::Button::new("Save current settings"),
+                    )
+                    .clicked()
+                {
+                    presets.push(Preset::capture(new_name.trim().to_owned(), head));
+                    new_name.clear();
+                }
+            });
+
+            if presets.is_empty() {
+                return;
+            }
+            ui.separator();
+
+            let mut update = None;
+            let mut remove = None;
+            for (i, preset) in presets.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(TextEdit::singleline(&mut preset.name).desired_width(150.0));
+                    if ui
+                        .button("Update")
+                        .on_hover_text("Replace with the current settings")
+                        .clicked()
+                    {
+                        update = Some(i);
+                    }
+                    if ui.button("Delete").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+
+            if let Some(i) = update {
+                let name = std::mem::take(&mut presets[i].name);
+                presets[i] = Preset::capture(name, head);
+            }
+            if let Some(i) = remove {
+                presets.remove(i);
+            }
+        });
+}
@@ -0,0 +1,44 @@
+use super::plot::time_to_x;
+use egui_plot::PlotPoint;
+use owowon::data::head::{Channel, DataHeader};
+
+/// A saved trace, drawn dimmed behind the live one for before/after comparisons.
+#[derive(Debug, Clone)]
+pub struct ReferenceTrace {
+    /// The settings the trace was captured with
+    head: DataHeader,
+    channel: Channel,
+    data: Vec<u8>,
+}
+
+impl ReferenceTrace {
+    /// `None` without data to save.
+    pub fn new(head: DataHeader, channel: Channel, data: &[u8]) -> Option<Self> {
+        (!data.is_empty()).then(|| Self {
+            head,
+            channel,
+            data: data.to_vec(),
+        })
+    }
+
+    /// The trace in the plot coordinates of `live`. Goes through time and voltage with the
+    /// captured settings, so it stays comparable after the live scales or offsets change.
+    pub fn points(&self, live: &DataHeader) -> Vec<PlotPoint> {
+        let captured = self.head.channel(self.channel);
+        let live_channel = live.channel(self.channel);
+        let voltages = captured
+            .voltages(&self.data, self.head.screen_samples())
+            .collect::<Vec<_>>();
+
+        self.head
+            .sample_times(voltages.len())
+            .zip(voltages)
+            .map(|(time, voltage)| {
+                PlotPoint::new(
+                    time_to_x(live, time),
+                    live_channel.voltage_to_units(voltage),
+                )
+            })
+            .collect()
+    }
+}
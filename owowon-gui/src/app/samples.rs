@@ -0,0 +1,111 @@
+use super::{plot::time_to_x, OwowonApp};
+use egui::{Color32, ScrollArea, TextEdit, TextStyle, Ui};
+use egui_plot::PlotPoint;
+use owowon::{
+    data::{
+        head::Channel,
+        units::{Time, Voltage},
+    },
+    scaled_number::ScaledNumber,
+};
+
+/// The jump-to-index box of the sample table.
+#[derive(Debug, Default)]
+pub struct SampleTableState {
+    go_to: String,
+    go_to_error: Option<String>,
+    /// Row to scroll to on the next frame
+    scroll_to: Option<usize>,
+}
+
+/// Index, time and voltages of every screen column of the shown acquisition. Only the visible rows
+/// are laid out, so deep captures stay fast.
+pub fn sample_table_ui(ui: &mut Ui, app: &mut OwowonApp) {
+    // same frame as on screen: the historical one while scrolled back
+    let state = &app.osc_ui_state;
+    let (head, ch1_data, ch2_data) = match state.history.selected() {
+        Some(frame) => (
+            frame.header,
+            frame.channel_data(Channel::Ch1).unwrap_or_default(),
+            frame.channel_data(Channel::Ch2).unwrap_or_default(),
+        ),
+        None => (state.head, &state.ch1_data[..], &state.ch2_data[..]),
+    };
+    let voltages = |channel: Channel, data: &[u8]| {
+        head.channel(channel)
+            .voltages(data, head.screen_samples())
+            .collect::<Vec<_>>()
+    };
+    let voltages = [
+        voltages(Channel::Ch1, ch1_data),
+        voltages(Channel::Ch2, ch2_data),
+    ];
+    let rows = voltages[0].len().max(voltages[1].len());
+    let times = head.sample_times(rows).collect::<Vec<_>>();
+
+    let table = &mut app.sample_table;
+    ui.horizontal(|ui| {
+        ui.label("Go to sample");
+        let response = ui.add(TextEdit::singleline(&mut table.go_to).desired_width(80.0));
+        if response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+            table.go_to_error = None;
+            match table.go_to.trim().parse::<usize>() {
+                Ok(index) if index < rows => {
+                    table.scroll_to = Some(index);
+                    // on the active channel's trace, if it has one
+                    let channel = app.active_channel;
+                    let y = voltages
+                        .get(channel as usize)
+                        .and_then(|voltages| voltages.get(index))
+                        .map(|&voltage| head.channel(channel).voltage_to_units(voltage))
+                        .unwrap_or_default();
+                    app.osc_ui_state.markers.add(
+                        &head,
+                        channel,
+                        PlotPoint::new(time_to_x(&head, times[index]), y),
+                    );
+                }
+                _ => table.go_to_error = Some(format!("0 to {} expected", rows.saturating_sub(1))),
+            }
+        }
+    });
+    if let Some(error) = &table.go_to_error {
+        ui.colored_label(Color32::RED, error);
+    }
+
+    ui.monospace(format!(
+        "{:>5} {:>10} {:>10} {:>10}",
+        "#", "time", "CH1", "CH2"
+    ));
+    let row_height = ui.text_style_height(&TextStyle::Monospace);
+    let mut scroll_area = ScrollArea::vertical()
+        .id_source("sample_table")
+        .max_height(250.0)
+        .auto_shrink(false);
+    if let Some(index) = table.scroll_to.take() {
+        let spacing = ui.spacing().item_spacing.y;
+        scroll_area = scroll_area.vertical_scroll_offset(index as f32 * (row_height + spacing));
+    }
+    scroll_area.show_rows(ui, row_height, rows, |ui, visible| {
+        for i in visible {
+            let [ch1, ch2] = voltages.each_ref().map(|voltages| {
+                voltages
+                    .get(i)
+                    .map_or_else(|| "-".to_owned(), |&voltage| format_voltage(voltage))
+            });
+            ui.monospace(format!(
+                "{i:>5} {:>10} {ch1:>10} {ch2:>10}",
+                format_time(times[i])
+            ));
+        }
+    });
+}
+
+// `Time`'s single decimal can't tell neighbouring samples apart
+fn format_time(time: Time) -> String {
+    format!("{}s", ScaledNumber(time.0))
+}
+
+fn format_voltage(voltage: Voltage) -> String {
+    format!("{}V", ScaledNumber(voltage.0))
+}
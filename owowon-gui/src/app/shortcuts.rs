@@ -1,6 +1,19 @@
 use egui::{Key, KeyboardShortcut, Modifiers};
 
 pub const TOGGLE_MEASUREMENT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::M);
+pub const TOGGLE_FREEZE: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::F);
+
+pub const UNDO: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+pub const REDO: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Y);
+// checked before `UNDO`, which also matches with shift held
+pub const REDO_SHIFT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Z);
+
+pub const SELECT_CH1: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::Num1);
+pub const SELECT_CH2: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::Num2);
+
+pub const TOGGLE_DEPTH: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::D);
+pub const TOGGLE_PEAK_DETECT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::P);
 
 pub const ZOOM_IN: Key = Key::PageUp; // or mouse wheel up
 pub const ZOOM_OUT: Key = Key::PageDown; // or mouse wheel down
@@ -1,25 +1,48 @@
 use super::{
+    capture::Comparator,
+    decode::{DecodeMode, DecodeSettings},
+    history::History,
+    logging::MeasurementLogState,
+    markers::markers_ui,
+    math::MathMode,
+    persistence::{Persistence, PersistenceState},
+    plot::{TraceDash, TraceStyle},
+    reference::ReferenceTrace,
+    samples::sample_table_ui,
     utils::{
-        calc_new_trigger_level, calc_new_vertical_offset, selected_time_base, selected_voltage,
+        calc_channel_fit, calc_fine_time_base, calc_fine_vertical_scale, calc_new_trigger_holdoff,
+        calc_new_trigger_level, calc_new_vertical_offset, calc_trigger_level_midpoint,
+        clamp_time_base, clamp_vertical_scale, selected_time_base, selected_voltage,
+        suspect_probe_mismatch,
     },
-    AwgState, OwowonApp,
+    AwgState, OwowonApp, ScpiConsoleState, TriggerPulseState, VoltageDisplay, COMPACT_MEASUREMENTS,
+    MAX_FRAME_INTERVAL,
 };
 use crate::{app::utils::calc_new_horizontal_offset, optional_sender::OptionalSender};
-use egui::{Button, CollapsingHeader, ComboBox, Modifiers, TextEdit, TextStyle, Ui};
+use egui::{
+    Button, CollapsingHeader, CollapsingResponse, Color32, ComboBox, DragValue, Label, Modifiers,
+    RichText, ScrollArea, Slider, TextEdit, TextStyle, Ui,
+};
 use owowon::{
     consts::{PROBE_ATTENUATIONS, TIME_BASES, VERTICAL_SCALES},
     data::{
-        awg::AWG_MODES,
+        awg::{ArbitraryWaveform, AwgMode, AWG_DUTY_CYCLE_RANGE, AWG_MODES, AWG_SYMMETRY_RANGE},
+        diff::setting_changes,
         head::{
-            Channel, ChannelCoupling, ChannelInfo, DataHeader, MemoryDepth, SampleType,
-            TriggerCoupling, TriggerEdge, TriggerSweep,
+            Channel, ChannelCoupling, ChannelInfo, DataHeader, MemoryDepth, PulseCondition,
+            SampleType, TriggerCoupling, TriggerEdge, TriggerSweep, TriggerType,
         },
-        units::{Frequency, Time, Voltage},
+        measurement::{MeasurementKind, Measurements},
+        units::{Frequency, ProbeAttenuation, Time, Voltage},
     },
-    scaled_number::ScaledNumber,
-    OscilloscopeCommand, OscilloscopeRunCommand,
+    scaled_number::{NumberFormat, ScaledNumber},
+    OscilloscopeRunCommand,
+};
+use std::{
+    collections::BTreeSet,
+    fmt::{Display, Write},
+    ops::RangeInclusive,
 };
-use std::fmt::Write;
 
 const ARROW_DOWN: &str = "⬇";
 const ARROW_UP: &str = "⬆";
@@ -32,8 +55,20 @@ pub(crate) fn ui(
     command_tx: &OptionalSender<OscilloscopeRunCommand>,
 ) {
     let head = &app.osc_ui_state.head;
+    // taken out while the sections' contents borrow the app
+    let mut open_sections = std::mem::take(&mut app.persistent_state.open_sections);
 
     ui.add_space(ui.style().spacing.item_spacing.y);
+    if let Some(notice) = &app.notice {
+        let mut dismissed = false;
+        ui.group(|ui| {
+            ui.colored_label(Color32::YELLOW, notice);
+            dismissed = ui.button("Dismiss").clicked();
+        });
+        if dismissed {
+            app.notice = None;
+        }
+    }
     ui.group(|ui| {
         ui.heading("General");
 
@@ -80,9 +115,33 @@ pub(crate) fn ui(
             }
         });
 
-        time_base_ui(ui, head, &mut app.horizontal_offset_string, command_tx);
+        ui.label("Math");
+        let math_mode = &mut app.persistent_state.math_mode;
+        ui.columns(MathMode::ALL.len(), |cols| {
+            for (col, mode) in cols.iter_mut().zip(MathMode::ALL) {
+                col.selectable_value(math_mode, mode, mode.label());
+            }
+        });
+        ui.checkbox(&mut app.persistent_state.show_overlay, "On-screen info");
+        ui.checkbox(
+            &mut app.persistent_state.square_divisions,
+            "Square divisions",
+        )
+        .on_hover_text("Keeps the plot at the screen's proportions instead of filling the window");
+
+        ui.checkbox(&mut app.fine_scales, "Fine scales")
+            .on_hover_text("Small steps and arbitrary values instead of the 1-2-5 sequence");
 
-        ui.collapsing("Acquisition", |ui| {
+        time_base_ui(
+            ui,
+            head,
+            &mut app.horizontal_offset_string,
+            &mut app.time_scale_string,
+            app.fine_scales,
+            command_tx,
+        );
+
+        section(ui, &mut open_sections, "Acquisition", "Acquisition", |ui| {
             ui.label("Mode");
             ui.columns(2, |cols| {
                 if cols[0]
@@ -99,6 +158,23 @@ pub(crate) fn ui(
                     command_tx.set_acquisition_mode(SampleType::Peak);
                 }
             });
+            ui.horizontal(|ui| {
+                ui.label("Average");
+                ui.add(
+                    DragValue::new(&mut app.persistent_state.average_count)
+                        .clamp_range(1..=256)
+                        .suffix(" acquisitions"),
+                );
+                let averaged = app.osc_ui_state.averaging.averaged();
+                if averaged > 0 {
+                    ui.label(format!("({averaged} so far)"));
+                }
+            })
+            .response
+            .on_hover_text(
+                "Averaged in software on top of the mode above, 1 for off. Starts over when the \
+                settings change. The device's measurements aren't averaged.",
+            );
 
             ui.label("Depth");
             ui.columns(2, |cols| {
@@ -116,20 +192,118 @@ pub(crate) fn ui(
                     command_tx.set_acquisition_depth(MemoryDepth::EightK);
                 }
             });
+            if let Some((interval, record)) =
+                head.sample.sample_interval().zip(head.sample.record_time())
+            {
+                ui.label(format!("{interval} per sample, {record} per record"))
+                    .on_hover_text(
+                        "Events shorter than a sample can be missed, the record spans the samples \
+                        transferred (data_len)",
+                    );
+            }
+
+            ui.label("Frame interval");
+            let interval = &mut app.persistent_state.min_frame_interval;
+            if ui
+                .add(
+                    Slider::new(interval, 0.0..=MAX_FRAME_INTERVAL)
+                        .logarithmic(true)
+                        .smallest_positive(0.01)
+                        .custom_formatter(|seconds, _| {
+                            if seconds == 0.0 {
+                                "as fast as possible".to_string()
+                            } else {
+                                Time(seconds).to_string()
+                            }
+                        }),
+                )
+                .on_hover_text("Caps the acquisition rate, e.g. for long unattended logging")
+                .changed()
+            {
+                command_tx.set_min_frame_interval(app.persistent_state.min_frame_interval());
+            }
         })
     });
 
+    let measurements = app.osc_ui_state.measurements.as_ref();
     ui.group(|ui| {
         let ch1 = head.channel(Channel::Ch1);
-        channel_ui("Channel 1", ui, ch1, &mut app.ch1_offset_string, command_tx);
+        let ch1_measurements = measurements.map(|m| &m[0]);
+        channel_ui(
+            "Channel 1",
+            ui,
+            ch1,
+            &mut app.active_channel,
+            ch1_measurements,
+            &mut app.ch1_offset_string,
+            &mut app.ch1_scale_string,
+            app.fine_scales,
+            app.osc_ui_state.clipping[0],
+            &mut open_sections,
+            command_tx,
+        );
+        reference_ui(
+            ui,
+            head,
+            Channel::Ch1,
+            &app.osc_ui_state.ch1_data,
+            &mut app.osc_ui_state.references[0],
+        );
     });
     ui.group(|ui| {
         let ch2 = head.channel(Channel::Ch2);
-        channel_ui("Channel 2", ui, ch2, &mut app.ch2_offset_string, command_tx);
+        let ch2_measurements = measurements.map(|m| &m[1]);
+        channel_ui(
+            "Channel 2",
+            ui,
+            ch2,
+            &mut app.active_channel,
+            ch2_measurements,
+            &mut app.ch2_offset_string,
+            &mut app.ch2_scale_string,
+            app.fine_scales,
+            app.osc_ui_state.clipping[1],
+            &mut open_sections,
+            command_tx,
+        );
+        reference_ui(
+            ui,
+            head,
+            Channel::Ch2,
+            &app.osc_ui_state.ch2_data,
+            &mut app.osc_ui_state.references[1],
+        );
     });
 
+    let trigger_midpoint = {
+        let state = &app.osc_ui_state;
+        let data = match head.trigger.items.channel {
+            Channel::Ch1 => &state.ch1_data,
+            Channel::Ch2 => &state.ch2_data,
+            Channel::Ch3 | Channel::Ch4 => &Vec::new(),
+        };
+        calc_trigger_level_midpoint(head, data)
+    };
     ui.group(|ui| {
-        trigger_ui(ui, head, command_tx, &mut app.trigger_level_string);
+        trigger_ui(
+            ui,
+            head,
+            trigger_midpoint,
+            command_tx,
+            &mut app.trigger_level_string,
+            &mut app.trigger_holdoff_string,
+            &mut app.trigger_pulse,
+            &mut open_sections,
+        );
+
+        let alert = &mut app.persistent_state.trigger_alert;
+        ui.horizontal(|ui| {
+            ui.label("On trigger");
+            ui.checkbox(&mut alert.beep, "Beep");
+            ui.checkbox(&mut alert.flash, "Flash window");
+        })
+        .response
+        .on_hover_text("Once per trigger event, e.g. when a single shot arrives");
     });
     ui.group(|ui| {
         ui.with_layout(egui::Layout::top_down_justified(egui::Align::LEFT), |ui| {
@@ -140,49 +314,600 @@ pub(crate) fn ui(
             {
                 command_tx.set_measurements_enabled(measurements_enabled);
             }
+
+            let state = &mut app.persistent_state;
+            ui.horizontal(|ui| {
+                ui.label("Measure");
+                let channels = &mut state.measurement_channels;
+                let ch1 = ui.checkbox(&mut channels[0], "CH1").changed();
+                let ch2 = ui.checkbox(&mut channels[1], "CH2").changed();
+                if ch1 || ch2 {
+                    command_tx.set_measurement_channels(*channels);
+                }
+            });
+            section(
+                ui,
+                &mut open_sections,
+                "Shown measurements",
+                "Shown measurements",
+                |ui| {
+                    let kinds = &mut state.measurement_kinds;
+                    let mut changed = false;
+                    for kind in MeasurementKind::all() {
+                        let mut shown = kinds.contains(&kind);
+                        if ui.checkbox(&mut shown, kind.to_string()).changed() {
+                            changed = true;
+                            if shown {
+                                kinds.insert(kind);
+                            } else {
+                                kinds.remove(&kind);
+                            }
+                        }
+                    }
+                    if changed {
+                        command_tx.set_measurement_kinds(kinds.clone());
+                    }
+                },
+            );
+            ui.checkbox(&mut state.compact_measurements, "Compact")
+                .on_hover_text(format!(
+                    "Only shows the first {} of them, for small windows",
+                    COMPACT_MEASUREMENTS
+                ));
+            ui.checkbox(&mut state.measurements_by_kind, "Group by measurement")
+                .on_hover_text(
+                    "A row per measurement with the channels side by side, for comparing",
+                );
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut state.show_statistics, "Statistics over");
+                ui.add(
+                    DragValue::new(&mut state.statistics_window)
+                        .clamp_range(2..=100_000)
+                        .suffix(" readings"),
+                );
+                if ui.button("Reset").clicked() {
+                    app.measurement_statistics.reset();
+                }
+            });
+            measurement_log_ui(ui, &mut app.measurement_log);
+            ui.label("Voltage levels");
+            ui.columns(3, |cols| {
+                cols[0].selectable_value(&mut state.voltage_display, VoltageDisplay::Volts, "V");
+                cols[1].selectable_value(&mut state.voltage_display, VoltageDisplay::Dbv, "dBV");
+                cols[2].selectable_value(&mut state.voltage_display, VoltageDisplay::Dbm, "dBm");
+            });
+            if state.voltage_display == VoltageDisplay::Dbm {
+                ui.horizontal(|ui| {
+                    ui.label("Reference impedance");
+                    ui.add(
+                        DragValue::new(&mut state.dbm_impedance)
+                            .clamp_range(1.0..=1e6)
+                            .suffix("Ω"),
+                    );
+                });
+            }
+            ui.label("Numbers");
+            ui.columns(3, |cols| {
+                cols[0].selectable_value(&mut state.number_format, NumberFormat::SiPrefix, "1.0µ");
+                cols[1].selectable_value(
+                    &mut state.number_format,
+                    NumberFormat::Scientific,
+                    "1.0e-6",
+                );
+                cols[2].selectable_value(&mut state.number_format, NumberFormat::Plain, "0.000001");
+            });
+            // plain numbers are always written in full
+            if state.number_format != NumberFormat::Plain {
+                ui.horizontal(|ui| {
+                    ui.label("Digits");
+                    ui.add(DragValue::new(&mut state.display_precision).clamp_range(1..=6));
+                });
+            }
         });
     });
 
     ui.group(|ui| {
-        ui.collapsing("Waveform generator", |ui| {
-            awg(ui, &mut app.awg_state, command_tx)
+        ui.with_layout(egui::Layout::top_down_justified(egui::Align::LEFT), |ui| {
+            history_ui(
+                ui,
+                &mut app.osc_ui_state.history,
+                &mut app.persistent_state.history_len,
+            )
+        });
+    });
+
+    ui.group(|ui| {
+        ui.with_layout(egui::Layout::top_down_justified(egui::Align::LEFT), |ui| {
+            persistence_ui(
+                ui,
+                &mut app.persistent_state.persistence,
+                &mut app.osc_ui_state.persistence,
+            )
+        });
+    });
+
+    ui.group(|ui| {
+        section(ui, &mut open_sections, "Trace style", "Trace style", |ui| {
+            trace_style_ui(ui, &mut app.persistent_state.trace_styles)
+        })
+    });
+
+    ui.group(|ui| {
+        section(ui, &mut open_sections, "Samples", "Samples", |ui| {
+            sample_table_ui(ui, app)
         })
     });
+
+    ui.group(|ui| {
+        section(ui, &mut open_sections, "Markers", "Markers", |ui| {
+            markers_ui(ui, &mut app.osc_ui_state.markers, &mut app.annotating)
+        })
+    });
+
+    ui.group(|ui| {
+        section(
+            ui,
+            &mut open_sections,
+            "Capture condition",
+            "Capture condition",
+            |ui| capture_ui(ui, app),
+        )
+    });
+
+    ui.group(|ui| {
+        section(
+            ui,
+            &mut open_sections,
+            "Golden settings",
+            "Golden settings",
+            |ui| golden_ui(ui, &mut app.golden, &app.osc_ui_state.head),
+        )
+    });
+
+    ui.group(|ui| {
+        section(ui, &mut open_sections, "Decode", "Decode", |ui| {
+            decode_ui(ui, &mut app.persistent_state.decode)
+        })
+    });
+
+    ui.group(|ui| {
+        section(
+            ui,
+            &mut open_sections,
+            "Waveform generator",
+            "Waveform generator",
+            |ui| awg(ui, &mut app.awg_state, command_tx),
+        )
+    });
+
+    ui.group(|ui| {
+        section(
+            ui,
+            &mut open_sections,
+            "SCPI console",
+            "SCPI console",
+            |ui| {
+                // e.g. a `:RUNning STOP` from the console isn't a trigger event
+                if scpi_console(ui, &mut app.scpi_console, command_tx) {
+                    app.trigger_alert.user_command();
+                }
+                ui.separator();
+                ui.label("Sent on every connect, one command per line:")
+                    .on_hover_text("Queries are skipped, use the console for those");
+                ui.add(
+                    TextEdit::multiline(&mut app.persistent_state.connect_script)
+                        .font(TextStyle::Monospace)
+                        .hint_text(":CH1:COUP AC")
+                        .desired_rows(3)
+                        .desired_width(f32::INFINITY),
+                );
+                ui.checkbox(
+                    &mut app.persistent_state.batch_commands,
+                    "Batch settings into one USB write",
+                )
+                .on_hover_text(
+                    "Experimental, not verified on a device yet. Takes effect on the next connect",
+                );
+            },
+        )
+    });
+
+    app.persistent_state.open_sections = open_sections;
 }
 
-fn time_base_ui(
+/// A collapsing section that is remembered open across launches in `open_sections` under `key`.
+/// egui's own memory isn't persisted, see `persist_egui_memory`.
+fn section<R>(
     ui: &mut Ui,
-    head: &DataHeader,
-    horizontal_offset_string: &mut String,
+    open_sections: &mut BTreeSet<String>,
+    title: &str,
+    key: &str,
+    add_contents: impl FnOnce(&mut Ui) -> R,
+) -> CollapsingResponse<R> {
+    let response = CollapsingHeader::new(title)
+        .id_source(key)
+        .default_open(open_sections.contains(key))
+        .show(ui, add_contents);
+    // also while animating, it ends up at 0 once closed
+    if response.openness > 0.0 {
+        if !open_sections.contains(key) {
+            open_sections.insert(key.to_owned());
+        }
+    } else {
+        open_sections.remove(key);
+    }
+    response
+}
+
+/// Returns whether a command was sent.
+fn scpi_console(
+    ui: &mut Ui,
+    state: &mut ScpiConsoleState,
     command_tx: &OptionalSender<OscilloscopeRunCommand>,
-) {
-    ui.label("Time base (per div)");
-    let (mut selected, selected_timebase, smaller, bigger) = selected_time_base(head);
+) -> bool {
+    let mut sent = false;
+    ui.horizontal(|ui| {
+        let send_clicked = ui.button("Send").clicked();
+        let input = ui.add(
+            TextEdit::singleline(&mut state.input)
+                .font(TextStyle::Monospace)
+                .hint_text(":CH1:DISP?")
+                .desired_width(f32::INFINITY),
+        );
+        let enter_pressed =
+            input.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
 
-    ui.horizontal_top(|ui| {
-        if ui
-            .add_enabled(bigger.is_some(), Button::new("out"))
+        let cmd = state.input.trim();
+        if (send_clicked || enter_pressed) && !cmd.is_empty() {
+            state.log.push(format!("> {cmd}"));
+            command_tx.send_raw_command(cmd.to_owned());
+            sent = true;
+            state.input.clear();
+            input.request_focus();
+        }
+    });
+
+    ScrollArea::vertical()
+        .max_height(300.0)
+        .stick_to_bottom(true)
+        .show(ui, |ui| {
+            for entry in &state.log {
+                ui.add(Label::new(RichText::new(entry).monospace().small()).wrap(false));
+            }
+        });
+
+    ui.separator();
+    ui.horizontal(|ui| {
+        if state.raw_dump {
+            if ui.button("Stop raw dump").clicked() {
+                state.raw_dump = false;
+                command_tx.set_raw_dump(None);
+            }
+        } else if ui
+            .add_enabled(
+                !state.raw_dump_path.trim().is_empty(),
+                egui::Button::new("Start raw dump"),
+            )
+            .on_hover_text(
+                "Appends the exact bytes of every acquisition (header and channel data) to the \
+                file, one hex line per read. Useful for bug reports about models that don't work.",
+            )
             .clicked()
         {
-            command_tx.set_time_scale(bigger.unwrap());
+            state.raw_dump = true;
+            command_tx.set_raw_dump(Some(state.raw_dump_path.trim().into()));
+        }
+        ui.add_enabled(
+            !state.raw_dump,
+            TextEdit::singleline(&mut state.raw_dump_path)
+                .hint_text("path to .ndjson")
+                .desired_width(f32::INFINITY),
+        );
+    });
+    sent
+}
+
+fn history_ui(ui: &mut Ui, history: &mut History, history_len: &mut usize) {
+    ui.horizontal(|ui| {
+        ui.label("History");
+        ui.add(
+            DragValue::new(history_len)
+                .clamp_range(1..=10_000)
+                .suffix(" frames"),
+        );
+    });
+
+    // one past the newest frame means following the live data
+    let live = history.len();
+    let mut position = history.position().unwrap_or(live);
+    if ui
+        .add(
+            Slider::new(&mut position, 0..=live).custom_formatter(move |p, _| {
+                if p as usize >= live {
+                    "live".to_owned()
+                } else {
+                    format!("{}/{live}", p as usize + 1)
+                }
+            }),
+        )
+        .changed()
+    {
+        history.set_position((position < live).then_some(position));
+    }
+}
+
+pub(crate) fn measurement_log_ui(ui: &mut Ui, log: &mut MeasurementLogState) {
+    ui.horizontal(|ui| {
+        if log.is_logging() {
+            if ui.button("Stop logging").clicked() {
+                log.stop();
+            }
+        } else if ui.button("Start logging").clicked() {
+            log.start();
+        }
+        ui.add_enabled(
+            !log.is_logging(),
+            TextEdit::singleline(&mut log.path)
+                .hint_text("path to .csv")
+                .desired_width(f32::INFINITY),
+        );
+    });
+    if let Some(error) = &log.error {
+        ui.colored_label(Color32::RED, error);
+    } else if log.is_logging() {
+        ui.label(format!("{} rows logged", log.rows()));
+    }
+}
+
+fn capture_ui(ui: &mut Ui, app: &mut OwowonApp) {
+    let state = &app.persistent_state;
+    let queried = state.measurements_enabled
+        && state
+            .measurement_channels
+            .get(state.capture.channel as usize)
+            .is_some_and(|&measured| measured)
+        && state.measurement_kinds.contains(&state.capture.kind);
+
+    let condition = &mut app.persistent_state.capture;
+    let previous = condition.clone();
+
+    ui.checkbox(&mut condition.enabled, "Enabled");
+    ui.horizontal(|ui| {
+        ui.selectable_value(&mut condition.channel, Channel::Ch1, "CH1");
+        ui.selectable_value(&mut condition.channel, Channel::Ch2, "CH2");
+        ComboBox::from_id_source("capture_kind")
+            .selected_text(condition.kind.to_string())
+            .show_ui(ui, |ui| {
+                for kind in MeasurementKind::all() {
+                    ui.selectable_value(&mut condition.kind, kind, kind.to_string());
+                }
+            });
+    });
+    ui.horizontal(|ui| {
+        for comparator in Comparator::ALL {
+            ui.selectable_value(&mut condition.comparator, comparator, comparator.label());
+        }
+        ui.add(
+            DragValue::new(&mut condition.threshold)
+                .speed(0.01)
+                .suffix(condition.kind.unit()),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut condition.freeze, "Freeze");
+        ui.checkbox(&mut condition.beep, "Beep");
+    });
+    ui.checkbox(&mut condition.log, "Log only matching frames");
+
+    if *condition != previous {
+        app.capture_state.reset();
+    }
+
+    ui.horizontal(|ui| {
+        ui.label(format!("Matched {} times", app.capture_state.matches));
+        if ui.button("Reset").clicked() {
+            app.capture_state.reset();
+        }
+    });
+    if !queried {
+        ui.weak("Enable this measurement and channel under Measurements for it to be checked");
+    }
+}
+
+/// A snapshot of the settings to compare the live ones against, e.g. to see what someone changed.
+fn golden_ui(ui: &mut Ui, golden: &mut Option<DataHeader>, head: &DataHeader) {
+    ui.horizontal(|ui| {
+        if ui.button("Save current").clicked() {
+            *golden = Some(*head);
         }
         if ui
-            .add_enabled(smaller.is_some(), Button::new("in"))
+            .add_enabled(golden.is_some(), Button::new("Clear"))
             .clicked()
         {
-            command_tx.set_time_scale(smaller.unwrap());
+            *golden = None;
         }
-        if ComboBox::from_id_source("time_base")
-            .width(150.0)
-            .show_index(ui, &mut selected, TIME_BASES.len(), |i| {
-                TIME_BASES[i].to_string()
-            })
-            .changed()
+    });
+    let Some(golden) = golden else {
+        ui.weak("No snapshot saved");
+        return;
+    };
+
+    let changes = setting_changes(golden, head);
+    if changes.is_empty() {
+        ui.colored_label(Color32::GREEN, "Matches the snapshot");
+        return;
+    }
+    egui::Grid::new("golden_changes")
+        .num_columns(3)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.strong("Setting");
+            ui.strong("Snapshot");
+            ui.strong("Now");
+            ui.end_row();
+            for change in changes {
+                ui.label(change.setting);
+                ui.label(change.old);
+                ui.colored_label(Color32::YELLOW, change.new);
+                ui.end_row();
+            }
+        });
+}
+
+fn decode_ui(ui: &mut Ui, settings: &mut DecodeSettings) {
+    ui.columns(DecodeMode::ALL.len(), |cols| {
+        for (col, mode) in cols.iter_mut().zip(DecodeMode::ALL) {
+            col.selectable_value(&mut settings.mode, mode, mode.label());
+        }
+    });
+
+    match settings.mode {
+        DecodeMode::Off => {}
+        DecodeMode::Uart => {
+            let uart = &mut settings.uart;
+            ui.horizontal(|ui| {
+                ui.label("Channel");
+                ui.selectable_value(&mut uart.channel, Channel::Ch1, "CH1");
+                ui.selectable_value(&mut uart.channel, Channel::Ch2, "CH2");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Baud rate");
+                ui.add(
+                    DragValue::new(&mut uart.baud_rate)
+                        .clamp_range(1..=10_000_000)
+                        .speed(10.0),
+                );
+                ui.label("8N1");
+            });
+            decode_threshold_ui(ui, &mut uart.auto_threshold, &mut uart.threshold);
+            ui.checkbox(&mut uart.inverted, "Inverted (idle low)");
+        }
+        DecodeMode::I2c => {
+            let i2c = &mut settings.i2c;
+            ui.horizontal(|ui| {
+                ui.label("SCL");
+                ui.selectable_value(&mut i2c.scl, Channel::Ch1, "CH1");
+                ui.selectable_value(&mut i2c.scl, Channel::Ch2, "CH2");
+                ui.label(format!("SDA: {}", i2c.sda()));
+            });
+            decode_threshold_ui(ui, &mut i2c.auto_threshold, &mut i2c.threshold);
+        }
+    }
+}
+
+fn decode_threshold_ui(ui: &mut Ui, auto_threshold: &mut bool, threshold: &mut f64) {
+    ui.horizontal(|ui| {
+        ui.label("Threshold");
+        ui.checkbox(auto_threshold, "Auto");
+        ui.add_enabled(
+            !*auto_threshold,
+            DragValue::new(threshold).speed(0.05).suffix("V"),
+        );
+    })
+    .response
+    .on_hover_text("Auto: halfway between the lowest and highest voltage");
+}
+
+fn persistence_ui(
+    ui: &mut Ui,
+    persistence: &mut Persistence,
+    persistence_state: &mut PersistenceState,
+) {
+    ui.label("Persistence");
+    ui.columns(3, |cols| {
+        if cols[0]
+            .selectable_label(*persistence == Persistence::Off, "Off")
+            .clicked()
+        {
+            *persistence = Persistence::Off;
+        }
+        if cols[1]
+            .selectable_label(matches!(persistence, Persistence::Traces(_)), "Traces")
+            .clicked()
+            && !matches!(persistence, Persistence::Traces(_))
+        {
+            *persistence = Persistence::Traces(8);
+        }
+        if cols[2]
+            .selectable_label(*persistence == Persistence::Infinite, "Infinite")
+            .clicked()
         {
-            command_tx.set_time_scale(TIME_BASES[selected]);
+            *persistence = Persistence::Infinite;
         }
     });
 
+    match persistence {
+        Persistence::Off => {}
+        Persistence::Traces(depth) => {
+            ui.add(Slider::new(depth, 1..=64).suffix(" traces"));
+        }
+        Persistence::Infinite => {
+            if ui.button("Clear").clicked() {
+                persistence_state.clear();
+            }
+        }
+    }
+}
+
+fn time_base_ui(
+    ui: &mut Ui,
+    head: &DataHeader,
+    horizontal_offset_string: &mut String,
+    time_scale_string: &mut String,
+    fine: bool,
+    command_tx: &OptionalSender<OscilloscopeRunCommand>,
+) {
+    ui.label("Time base (per div)");
+    let (mut selected, _, smaller, bigger) = selected_time_base(head);
+
+    if fine {
+        value_changer_box(
+            ui,
+            time_scale_string,
+            |mods| command_tx.set_time_scale(calc_fine_time_base(head, mods, true)),
+            |mods| command_tx.set_time_scale(calc_fine_time_base(head, mods, false)),
+            |time_scale_string| {
+                if let Ok(time) = time_scale_string.trim().parse::<Time>() {
+                    command_tx.set_time_scale(clamp_time_base(time));
+                }
+            },
+            |time_scale_string| {
+                time_scale_string.clear();
+                let _ = write!(
+                    time_scale_string,
+                    "{}s",
+                    ScaledNumber(head.time_base.scale.0)
+                );
+            },
+            "out",
+            "in",
+        );
+    } else {
+        ui.horizontal_top(|ui| {
+            if ui
+                .add_enabled(bigger.is_some(), Button::new("out"))
+                .clicked()
+            {
+                command_tx.set_time_scale(bigger.unwrap());
+            }
+            if ui
+                .add_enabled(smaller.is_some(), Button::new("in"))
+                .clicked()
+            {
+                command_tx.set_time_scale(smaller.unwrap());
+            }
+            if ComboBox::from_id_source("time_base")
+                .width(150.0)
+                .show_index(ui, &mut selected, TIME_BASES.len(), |i| {
+                    TIME_BASES[i].to_string()
+                })
+                .changed()
+            {
+                command_tx.set_time_scale(TIME_BASES[selected]);
+            }
+        });
+    }
+
     ui.label(format!(
         "Horizontal offset ({:+.2} divs)",
         head.time_base.h_offset_grid_divs()
@@ -197,19 +922,20 @@ fn time_base_ui(
             let trimmed = horizontal_offset_string.trim();
             if let Some(o) = trimmed
                 .parse::<Time>()
-                .map(|time| time.0 / selected_timebase.0)
+                .map(|time| head.time_base.time_to_grid_divs(time))
                 .ok()
                 .or_else(|| trimmed.parse::<f64>().ok())
             {
-                command_tx.set_horizontal_offset(o);
+                command_tx.set_horizontal_offset(head.time_base.snap_grid_divs(o));
             }
         },
+        // the offset the device reports, not the one that was entered
         |horizontal_offset_string| {
             horizontal_offset_string.clear();
             let _ = write!(
                 horizontal_offset_string,
                 "{}s",
-                ScaledNumber(head.time_base.h_offset_grid_divs() * selected_timebase.0)
+                ScaledNumber(head.time_base.h_offset_time().0)
             );
         },
         ARROW_LEFT,
@@ -217,75 +943,221 @@ fn time_base_ui(
     );
 }
 
+#[allow(clippy::too_many_arguments)]
 fn channel_ui(
     name: &'static str,
     ui: &mut Ui,
     ch: &ChannelInfo,
+    active_channel: &mut Channel,
+    measurements: Option<&Measurements>,
     offset_string: &mut String,
+    scale_string: &mut String,
+    fine_scale: bool,
+    clipping: bool,
+    open_sections: &mut BTreeSet<String>,
     command_tx: &OptionalSender<OscilloscopeRunCommand>,
 ) {
-    ui.heading(name);
+    ui.horizontal(|ui| {
+        // highlighted while the keyboard shortcuts act on this channel
+        if ui
+            .selectable_label(*active_channel == ch.channel, RichText::new(name).heading())
+            .on_hover_text("Vertical scale/offset shortcuts act on the selected channel")
+            .clicked()
+        {
+            *active_channel = ch.channel;
+        }
+
+        let fit_measurements = measurements.filter(|m| calc_channel_fit(ch, m).is_some());
+        if ui
+            .add_enabled(fit_measurements.is_some(), Button::new("Fit"))
+            .on_disabled_hover_text("Needs the peak-to-peak and average measurements")
+            .clicked()
+        {
+            command_tx.fit_channel(ch, fit_measurements.unwrap());
+        }
+
+        // the effective scale, so a wrong probe setting stands out
+        ui.label(
+            RichText::new(format!("{}/div @ {}", ch.scale_attenuated(), ch.probe))
+                .strong()
+                .size(18.0),
+        )
+        .on_hover_text("Volts per div at the probe tip, with the configured probe attenuation");
+
+        if clipping {
+            ui.colored_label(Color32::RED, RichText::new("OVER").strong())
+                .on_hover_text(
+                    "The signal goes beyond the top or bottom of the screen, measurements of it \
+                    are off. Increase the scale or move the offset.",
+                );
+        }
+    });
+
+    if measurements.is_some_and(|m| suspect_probe_mismatch(ch, m)) {
+        ui.horizontal_wrapped(|ui| {
+            ui.colored_label(
+                Color32::YELLOW,
+                "Large signal with a 1X probe setting. \
+                If a 10X probe is connected, all readings are 10 times too small.",
+            );
+            if ui
+                .button("I'm using a 10X probe")
+                .on_hover_text("Sets the attenuation to 10X, so the readings are 10 times larger")
+                .clicked()
+            {
+                command_tx.correct_channel_attenuation(ch, ProbeAttenuation(10));
+            }
+        });
+    }
 
     channel_voffset(ui, ch, offset_string, command_tx);
-    channel_vscale(ui, ch, command_tx);
-    CollapsingHeader::new("Configuration")
-        .id_source(format!("collapsing_{}_config", ch.channel))
-        .show(ui, |ui| {
-            ui.label("Probe attenuation");
-            let mut current_pos = PROBE_ATTENUATIONS
-                .iter()
-                .position(|&v| v == ch.probe)
-                .unwrap_or(0);
+    channel_vscale(ui, ch, scale_string, fine_scale, command_tx);
+    let key = format!("collapsing_{}_config", ch.channel);
+    section(ui, open_sections, "Configuration", &key, |ui| {
+        ui.label("Probe attenuation");
+        let mut current_pos = PROBE_ATTENUATIONS
+            .iter()
+            .position(|&v| v == ch.probe)
+            .unwrap_or(0);
 
-            if ComboBox::from_id_source(format!("combobox_{}_attenuation", ch.channel))
-                .width(150.0)
-                .show_index(ui, &mut current_pos, PROBE_ATTENUATIONS.len(), |i| {
-                    format!("{}", PROBE_ATTENUATIONS[i])
-                })
-                .changed()
+        if ComboBox::from_id_source(format!("combobox_{}_attenuation", ch.channel))
+            .width(150.0)
+            .show_index(ui, &mut current_pos, PROBE_ATTENUATIONS.len(), |i| {
+                format!("{}", PROBE_ATTENUATIONS[i])
+            })
+            .changed()
+        {
+            command_tx.set_channel_attenuation(ch, PROBE_ATTENUATIONS[current_pos]);
+        }
+
+        ui.label("Coupling");
+        ui.columns(3, |cols| {
+            let mut new_coupling = None;
+            if cols[0]
+                .selectable_label(ch.coupling == ChannelCoupling::Dc, "DC")
+                .clicked()
             {
-                let _ = command_tx.blocking_send(OscilloscopeCommand::SetChannelAttenuation(
-                    ch.channel,
-                    PROBE_ATTENUATIONS[current_pos],
-                ));
+                new_coupling = Some(ChannelCoupling::Dc);
+            }
+            if cols[1]
+                .selectable_label(ch.coupling == ChannelCoupling::Ac, "AC")
+                .clicked()
+            {
+                new_coupling = Some(ChannelCoupling::Ac);
+            }
+            if cols[2]
+                .selectable_label(ch.coupling == ChannelCoupling::Gnd, "GND")
+                .clicked()
+            {
+                new_coupling = Some(ChannelCoupling::Gnd);
             }
 
-            ui.label("Coupling");
-            ui.columns(3, |cols| {
-                let mut new_coupling = None;
-                if cols[0]
-                    .selectable_label(ch.coupling == ChannelCoupling::Dc, "DC")
-                    .clicked()
-                {
-                    new_coupling = Some(ChannelCoupling::Dc);
-                }
-                if cols[1]
-                    .selectable_label(ch.coupling == ChannelCoupling::Ac, "AC")
-                    .clicked()
-                {
-                    new_coupling = Some(ChannelCoupling::Ac);
-                }
-                if cols[2]
-                    .selectable_label(ch.coupling == ChannelCoupling::Gnd, "GND")
-                    .clicked()
-                {
-                    new_coupling = Some(ChannelCoupling::Gnd);
-                }
+            if let Some(coupling) = new_coupling {
+                command_tx.set_channel_coupling(ch.channel, coupling);
+            }
+        });
 
-                if let Some(coupling) = new_coupling {
-                    command_tx.set_channel_coupling(ch.channel, coupling);
-                }
-            });
+        // the command isn't documented, only offered when the header reports the setting
+        if let Some(reported) = ch.bandwidth_limit {
+            let mut bandwidth_limit = reported.into();
+            if ui
+                .checkbox(&mut bandwidth_limit, "20 MHz bandwidth limit")
+                .on_hover_text("Filters out high frequency noise")
+                .changed()
+            {
+                command_tx.set_channel_bandwidth_limit(ch.channel, bandwidth_limit);
+            }
+        }
+    });
+}
+
+fn trace_style_ui(ui: &mut Ui, styles: &mut [TraceStyle; 2]) {
+    for (channel, style) in [Channel::Ch1, Channel::Ch2].into_iter().zip(styles) {
+        ui.horizontal(|ui| {
+            ui.label(channel.to_string());
+            ui.add(
+                DragValue::new(&mut style.width)
+                    .clamp_range(0.5..=5.0)
+                    .speed(0.1)
+                    .suffix(" pt"),
+            );
+            ComboBox::from_id_source(format!("combobox_{channel}_trace_dash"))
+                .selected_text(style.dash.label())
+                .show_ui(ui, |ui| {
+                    for dash in TraceDash::ALL {
+                        ui.selectable_value(&mut style.dash, dash, dash.label());
+                    }
+                });
+            ui.checkbox(&mut style.hidden, "Hidden").on_hover_text(
+                "Hides the trace here only, the device keeps the channel on and it's still measured",
+            );
         });
+    }
+}
+
+fn reference_ui(
+    ui: &mut Ui,
+    head: &DataHeader,
+    channel: Channel,
+    data: &[u8],
+    reference: &mut Option<ReferenceTrace>,
+) {
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(!data.is_empty(), Button::new("Save ref"))
+            .on_hover_text("Overlays the current trace on the following ones, at its own scale")
+            .clicked()
+        {
+            *reference = ReferenceTrace::new(*head, channel, data);
+        }
+        if ui
+            .add_enabled(reference.is_some(), Button::new("Clear ref"))
+            .clicked()
+        {
+            *reference = None;
+        }
+    });
 }
 
 fn channel_vscale(
     ui: &mut Ui,
     channel_info: &ChannelInfo,
+    scale_string: &mut String,
+    fine: bool,
     command_tx: &OptionalSender<OscilloscopeRunCommand>,
 ) {
     let channel = channel_info.channel;
     ui.label("Scale per div");
+    if fine {
+        let attenuation = channel_info.probe;
+        value_changer_box(
+            ui,
+            scale_string,
+            |mods| {
+                let scale = calc_fine_vertical_scale(channel_info, mods, true);
+                command_tx.set_vertical_scale(channel, scale, attenuation);
+            },
+            |mods| {
+                let scale = calc_fine_vertical_scale(channel_info, mods, false);
+                command_tx.set_vertical_scale(channel, scale, attenuation);
+            },
+            // entered at the probe tip, like it's shown
+            |scale_string| {
+                if let Ok(scale) = scale_string.trim().parse::<Voltage>() {
+                    let scale = clamp_vertical_scale(scale * (1.0 / attenuation.0 as f64));
+                    command_tx.set_vertical_scale(channel, scale, attenuation);
+                }
+            },
+            |scale_string| {
+                scale_string.clear();
+                let _ = write!(scale_string, "{}", channel_info.scale_attenuated());
+            },
+            "out",
+            "in",
+        );
+        return;
+    }
+
     ui.horizontal_top(|ui| {
         let attenuation = channel_info.probe;
 
@@ -306,10 +1178,7 @@ fn channel_vscale(
         if ComboBox::from_id_source(format!("combobox_{channel}_vscale"))
             .width(150.0)
             .show_index(ui, &mut selected_index, VERTICAL_SCALES.len(), |i| {
-                format!(
-                    "{:.2}",
-                    Voltage(VERTICAL_SCALES[i].0 * (attenuation.0 as f64))
-                )
+                format!("{:.2}", VERTICAL_SCALES[i] * attenuation.0 as f64)
             })
             .changed()
         {
@@ -357,7 +1226,7 @@ fn channel_voffset(
             let _ = write!(
                 channel_string,
                 "{}",
-                Voltage(channel_info.offset_grid_divs() * channel_info.scale_attenuated().0)
+                channel_info.scale_attenuated() * channel_info.offset_grid_divs()
             );
         },
         ARROW_DOWN,
@@ -365,11 +1234,16 @@ fn channel_voffset(
     );
 }
 
+#[allow(clippy::too_many_arguments)]
 fn trigger_ui(
     ui: &mut Ui,
     head: &DataHeader,
+    midpoint: Option<Voltage>,
     command_tx: &OptionalSender<OscilloscopeRunCommand>,
     trigger_level_string: &mut String,
+    trigger_holdoff_string: &mut String,
+    trigger_pulse: &mut TriggerPulseState,
+    open_sections: &mut BTreeSet<String>,
 ) {
     ui.heading("Trigger");
     ui.label("Source");
@@ -388,7 +1262,7 @@ fn trigger_ui(
         }
     });
 
-    trigger_level(ui, head, trigger_level_string, command_tx);
+    trigger_level(ui, head, midpoint, trigger_level_string, command_tx);
 
     let configuration = |ui: &mut Ui| {
         ui.label("Mode/Sweep (see tooltip)").on_hover_text(
@@ -414,6 +1288,26 @@ You'll have to press the play/pause button on the device itself.",
             }
         });
 
+        ui.label("Type");
+        ui.columns(2, |cols| {
+            if cols[0]
+                .selectable_label(head.trigger.r#type == TriggerType::Edge, "Edge")
+                .clicked()
+            {
+                command_tx.set_trigger_type(TriggerType::Edge);
+            }
+            if cols[1]
+                .selectable_label(head.trigger.r#type == TriggerType::Pulse, "Pulse width")
+                .clicked()
+            {
+                command_tx.set_trigger_type(TriggerType::Pulse);
+            }
+        });
+
+        if head.trigger.r#type == TriggerType::Pulse {
+            trigger_pulse_ui(ui, trigger_pulse, command_tx);
+        }
+
         ui.label("Edge");
         ui.columns(2, |cols| {
             if cols[0]
@@ -445,20 +1339,125 @@ You'll have to press the play/pause button on the device itself.",
                 command_tx.set_trigger_coupling(TriggerCoupling::Ac);
             }
         });
+
+        trigger_holdoff(ui, head, trigger_holdoff_string, command_tx);
     };
 
-    CollapsingHeader::new("Configuration")
-        .id_source("collapsing_trigger_config")
-        .show(ui, configuration);
+    section(
+        ui,
+        open_sections,
+        "Configuration",
+        "collapsing_trigger_config",
+        configuration,
+    );
+}
+
+fn trigger_pulse_ui(
+    ui: &mut Ui,
+    trigger_pulse: &mut TriggerPulseState,
+    command_tx: &OptionalSender<OscilloscopeRunCommand>,
+) {
+    ui.label("Pulse width");
+    ui.columns(3, |cols| {
+        for (col, (condition, label)) in cols.iter_mut().zip([
+            (PulseCondition::Shorter, "<"),
+            (PulseCondition::Equal, "="),
+            (PulseCondition::Longer, ">"),
+        ]) {
+            if col
+                .selectable_label(trigger_pulse.condition == condition, label)
+                .clicked()
+            {
+                trigger_pulse.condition = condition;
+                command_tx.set_trigger_pulse_condition(condition);
+            }
+        }
+    });
+
+    let lost_focus = TextEdit::singleline(&mut trigger_pulse.width)
+        .font(TextStyle::Button)
+        .hint_text("e.g. 10us")
+        .desired_width(f32::INFINITY)
+        .show(ui)
+        .response
+        .lost_focus();
+    if lost_focus {
+        let s = trigger_pulse.width.trim();
+        match s
+            .parse::<Time>()
+            .ok()
+            .or_else(|| s.parse::<ScaledNumber>().map(|f| Time(f.0)).ok())
+            .filter(|time| time.0 > 0.0)
+        {
+            Some(width) => {
+                trigger_pulse.width_error = None;
+                command_tx.set_trigger_pulse_width(width);
+            }
+            None => trigger_pulse.width_error = Some("invalid width".to_owned()),
+        }
+    }
+    if let Some(error) = &trigger_pulse.width_error {
+        ui.colored_label(Color32::RED, error);
+    }
+}
+
+fn trigger_holdoff(
+    ui: &mut Ui,
+    head: &DataHeader,
+    trigger_holdoff_string: &mut String,
+    command_tx: &OptionalSender<OscilloscopeRunCommand>,
+) {
+    ui.label("Holdoff");
+    value_changer_box(
+        ui,
+        trigger_holdoff_string,
+        |_| command_tx.set_trigger_holdoff(calc_new_trigger_holdoff(head, false)),
+        |_| command_tx.set_trigger_holdoff(calc_new_trigger_holdoff(head, true)),
+        |trigger_holdoff_string| {
+            let s = trigger_holdoff_string.trim();
+            if let Some(time) = s
+                .parse::<Time>()
+                .ok()
+                .or_else(|| s.parse::<ScaledNumber>().map(|f| Time(f.0)).ok())
+                .or_else(|| s.parse::<f64>().map(Time).ok())
+            {
+                command_tx.set_trigger_holdoff(time);
+            }
+        },
+        |trigger_holdoff_string| {
+            trigger_holdoff_string.clear();
+            match head.trigger.holdoff {
+                Some(holdoff) => {
+                    let _ = write!(trigger_holdoff_string, "{holdoff}");
+                }
+                None => trigger_holdoff_string.push_str("unknown"),
+            }
+        },
+        ARROW_DOWN,
+        ARROW_UP,
+    )
 }
 
 fn trigger_level(
     ui: &mut Ui,
     head: &DataHeader,
+    midpoint: Option<Voltage>,
     trigger_level_string: &mut String,
     command_tx: &OptionalSender<OscilloscopeRunCommand>,
 ) {
-    ui.label("Level");
+    ui.horizontal(|ui| {
+        ui.label("Level");
+        if ui
+            .add_enabled(midpoint.is_some(), Button::new("50%"))
+            .on_hover_text(
+                "Middle of the signal: its average if measured, else halfway between its peaks",
+            )
+            .on_disabled_hover_text("Needs the average measurement or a trace of the source")
+            .clicked()
+        {
+            command_tx.set_trigger_level(midpoint.unwrap());
+        }
+    });
     value_changer_box(
         ui,
         trigger_level_string,
@@ -526,22 +1525,33 @@ fn awg(ui: &mut Ui, awg_state: &mut AwgState, command_tx: &OptionalSender<Oscill
         if ui.button("Set config").clicked() {
             // parse here to work around egui not having an easy way for regular focus loss update
             // (only works on enter/tab with textboxes for some reason)
-            parse_awg_freq(awg_state);
-            parse_awg_voltage(
-                &mut awg_state.config.amplitude,
-                &mut awg_state.amplitude,
-                Voltage(1.0),
-            );
-            parse_awg_voltage(
-                &mut awg_state.config.offset,
-                &mut awg_state.offset,
-                Voltage(0.0),
-            );
-            command_tx.set_awg_config(awg_state.config);
+            if parse_awg_inputs(awg_state) {
+                command_tx.set_awg_config(awg_state.config.clone());
+            }
         }
     });
 
-    ui.checkbox(&mut awg_state.config.enabled, "Enabled");
+    if awg_state.config.channels.len() > 1 {
+        ui.horizontal(|ui| {
+            for channel in 0..awg_state.config.channels.len() {
+                let selected = awg_state.channel == channel;
+                if ui
+                    .selectable_label(selected, format!("CH{}", channel + 1))
+                    .clicked()
+                    && !selected
+                    && parse_awg_inputs(awg_state)
+                {
+                    awg_state.channel = channel;
+                    awg_state.load_inputs();
+                }
+            }
+        });
+    }
+
+    ui.checkbox(
+        &mut awg_state.config.channels[awg_state.channel].enabled,
+        "Enabled",
+    );
 
     ui.label("Function");
     if ComboBox::from_id_source("awg_mode")
@@ -551,82 +1561,184 @@ fn awg(ui: &mut Ui, awg_state: &mut AwgState, command_tx: &OptionalSender<Oscill
         })
         .changed()
     {
-        awg_state.config.mode = AWG_MODES[awg_state.mode_index];
+        awg_state.config.channels[awg_state.channel].mode = AWG_MODES[awg_state.mode_index];
     }
 
-    ui.label("Frequency");
-    if TextEdit::singleline(&mut awg_state.frequency)
-        .font(TextStyle::Button)
-        .desired_width(f32::INFINITY)
-        .show(ui)
-        .response
-        .lost_focus()
+    if awg_state.config.channels[awg_state.channel]
+        .mode
+        .has_duty_cycle()
     {
-        parse_awg_freq(awg_state);
-    };
-
-    ui.label("Amplitude");
-    if TextEdit::singleline(&mut awg_state.amplitude)
-        .font(TextStyle::Button)
-        .desired_width(f32::INFINITY)
-        .show(ui)
-        .response
-        .lost_focus()
+        ui.label("Duty cycle");
+        ui.add(
+            Slider::new(
+                &mut awg_state.config.channels[awg_state.channel].duty_cycle,
+                AWG_DUTY_CYCLE_RANGE,
+            )
+            .suffix("%"),
+        );
+    }
+    if awg_state.config.channels[awg_state.channel]
+        .mode
+        .has_symmetry()
     {
-        parse_awg_voltage(
-            &mut awg_state.config.amplitude,
-            &mut awg_state.amplitude,
-            Voltage(1.0),
+        ui.label("Symmetry");
+        ui.add(
+            Slider::new(
+                &mut awg_state.config.channels[awg_state.channel].symmetry,
+                AWG_SYMMETRY_RANGE,
+            )
+            .suffix("%"),
         );
-    };
+    }
+
+    let limits = &awg_state.limits;
+    let mut lost_focus = awg_input(
+        ui,
+        "Frequency",
+        &mut awg_state.frequency,
+        &limits.awg_frequency,
+        &awg_state.frequency_error,
+    );
+    lost_focus |= awg_input(
+        ui,
+        "Amplitude",
+        &mut awg_state.amplitude,
+        &limits.awg_amplitude,
+        &awg_state.amplitude_error,
+    );
+    lost_focus |= awg_input(
+        ui,
+        "Offset",
+        &mut awg_state.offset,
+        &limits.awg_offset,
+        &awg_state.offset_error,
+    );
+    if lost_focus {
+        parse_awg_inputs(awg_state);
+    }
+
+    ui.label("Arbitrary waveform (DAC values)");
+    ui.horizontal(|ui| {
+        if ui.button("Load arb from CSV").clicked() {
+            load_awg_arbitrary(awg_state);
+        }
+        ui.add(
+            TextEdit::singleline(&mut awg_state.arb_path)
+                .hint_text("path to .csv")
+                .desired_width(f32::INFINITY),
+        );
+    });
+    if let Some(error) = &awg_state.arb_error {
+        ui.colored_label(Color32::RED, error);
+    } else if let Some(arbitrary) = &awg_state.config.channels[awg_state.channel].arbitrary {
+        ui.label(format!("{} samples loaded", arbitrary.samples().len()));
+    }
+}
+
+fn load_awg_arbitrary(awg_state: &mut AwgState) {
+    let arbitrary = std::fs::read_to_string(awg_state.arb_path.trim())
+        .map_err(|e| e.to_string())
+        .and_then(|csv| {
+            ArbitraryWaveform::from_csv(&csv).map_err(|e| snafu::Report::from_error(e).to_string())
+        });
+
+    match arbitrary {
+        Ok(arbitrary) => {
+            awg_state.arb_error = None;
+            awg_state.config.channels[awg_state.channel].arbitrary = Some(arbitrary);
+            awg_state.config.channels[awg_state.channel].mode = AwgMode::Arbitrary;
+            awg_state.mode_index = AWG_MODES
+                .into_iter()
+                .position(|m| m == AwgMode::Arbitrary)
+                .unwrap();
+        }
+        Err(e) => awg_state.arb_error = Some(e),
+    }
+}
 
-    ui.label("Offset");
-    if TextEdit::singleline(&mut awg_state.offset)
+/// Text box with the allowed range as hint below it. Returns whether it lost focus.
+fn awg_input<T: Display>(
+    ui: &mut Ui,
+    label: &str,
+    string: &mut String,
+    range: &RangeInclusive<T>,
+    error: &Option<String>,
+) -> bool {
+    ui.label(label);
+    let lost_focus = TextEdit::singleline(string)
         .font(TextStyle::Button)
         .desired_width(f32::INFINITY)
         .show(ui)
         .response
-        .lost_focus()
-    {
-        parse_awg_voltage(
-            &mut awg_state.config.offset,
-            &mut awg_state.offset,
-            Voltage(0.0),
-        );
+        .lost_focus();
+    match error {
+        Some(error) => ui.colored_label(Color32::RED, error),
+        None => ui.small(format!("{} to {}", range.start(), range.end())),
     };
+    lost_focus
 }
 
-fn parse_awg_freq(awg_state: &mut AwgState) {
-    let s = awg_state.frequency.trim();
-    let freq = if let Some(freq) = s
-        .parse::<Frequency>()
-        .ok()
-        .or_else(|| s.parse::<ScaledNumber>().map(|f| Frequency(f.0)).ok())
-        .or_else(|| s.parse::<f64>().map(Frequency).ok())
-    {
-        awg_state.config.frequency = freq;
-        freq
-    } else {
-        Frequency(1_000_000.0)
-    };
-    awg_state.frequency.clear();
-    let _ = write!(&mut awg_state.frequency, "{freq}");
+/// Parses all AWG text boxes into the config. Returns whether all of them were valid.
+fn parse_awg_inputs(awg_state: &mut AwgState) -> bool {
+    let limits = &awg_state.limits;
+    awg_state.frequency_error = parse_awg_input(
+        &mut awg_state.frequency,
+        &mut awg_state.config.channels[awg_state.channel].frequency,
+        &limits.awg_frequency,
+        |s| {
+            s.parse::<Frequency>()
+                .ok()
+                .or_else(|| s.parse::<ScaledNumber>().map(|f| Frequency(f.0)).ok())
+                .or_else(|| s.parse::<f64>().map(Frequency).ok())
+        },
+    );
+    awg_state.amplitude_error = parse_awg_input(
+        &mut awg_state.amplitude,
+        &mut awg_state.config.channels[awg_state.channel].amplitude,
+        &limits.awg_amplitude,
+        parse_awg_voltage,
+    );
+    awg_state.offset_error = parse_awg_input(
+        &mut awg_state.offset,
+        &mut awg_state.config.channels[awg_state.channel].offset,
+        &limits.awg_offset,
+        parse_awg_voltage,
+    );
+
+    awg_state.frequency_error.is_none()
+        && awg_state.amplitude_error.is_none()
+        && awg_state.offset_error.is_none()
 }
 
-fn parse_awg_voltage(config: &mut Voltage, string: &mut String, default: Voltage) {
+/// Only updates `value` if the input is valid and in range, otherwise returns the reason for the
+/// user. The text box keeps the rejected input so it can be corrected.
+fn parse_awg_input<T: Copy + PartialOrd + Display>(
+    string: &mut String,
+    value: &mut T,
+    range: &RangeInclusive<T>,
+    parse: impl FnOnce(&str) -> Option<T>,
+) -> Option<String> {
     let s = string.trim();
-    let val = if let Some(voltage) = s
-        .parse::<Voltage>()
+    let Some(parsed) = parse(s) else {
+        return Some(format!("\"{s}\" is not a valid value"));
+    };
+    if !range.contains(&parsed) {
+        return Some(format!(
+            "{parsed} is out of range ({} to {})",
+            range.start(),
+            range.end()
+        ));
+    }
+
+    *value = parsed;
+    string.clear();
+    let _ = write!(string, "{parsed}");
+    None
+}
+
+fn parse_awg_voltage(s: &str) -> Option<Voltage> {
+    s.parse::<Voltage>()
         .ok()
         .or_else(|| s.parse::<ScaledNumber>().map(|f| Voltage(f.0)).ok())
         .or_else(|| s.parse::<f64>().map(Voltage).ok())
-    {
-        *config = voltage;
-        voltage
-    } else {
-        *config = default;
-        default
-    };
-    string.clear();
-    let _ = write!(string, "{val}");
 }
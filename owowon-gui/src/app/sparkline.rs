@@ -0,0 +1,92 @@
+use super::OscilloscopeUiState;
+use owowon::{consts::GRID_DIV_SIZE, data::head::Channel};
+use std::fmt::Write;
+
+/// Size of the copied traces, in characters per channel.
+const WIDTH: usize = 60;
+const HEIGHT: usize = 3;
+
+/// Samples at the top/bottom edge of the screen (4 divs from the center). Anything beyond is
+/// drawn on the edge, like the plot cuts it off.
+const EDGE: f64 = 4.0 * GRID_DIV_SIZE;
+
+/// The shown channels as braille sparklines, each prefixed by the channel name, for pasting where
+/// a screenshot is overkill.
+pub fn traces_as_text(state: &OscilloscopeUiState) -> String {
+    // same frame as on screen: the historical one while scrolled back
+    let (head, ch1_data, ch2_data) = match state.history.selected() {
+        Some(frame) => (
+            &frame.header,
+            frame.channel_data(Channel::Ch1),
+            frame.channel_data(Channel::Ch2),
+        ),
+        None => (
+            &state.head,
+            Some(state.ch1_data.as_slice()),
+            Some(state.ch2_data.as_slice()),
+        ),
+    };
+
+    let mut text = String::new();
+    for (channel, data) in [(Channel::Ch1, ch1_data), (Channel::Ch2, ch2_data)] {
+        let Some(data) = data.filter(|data| !data.is_empty()) else {
+            continue;
+        };
+        let samples = data.iter().map(|&s| s as i8).collect::<Vec<_>>();
+        let _ = writeln!(
+            text,
+            "{channel} ({}/div)",
+            head.channel(channel).scale_attenuated()
+        );
+        text.push_str(&sparkline(&samples, WIDTH, HEIGHT));
+        text.push('\n');
+    }
+    text
+}
+
+/// Renders `samples` as `height` lines of `width` braille characters (2x4 dots each), the
+/// vertical range being the screen's. Empty if there are no samples.
+pub fn sparkline(samples: &[i8], width: usize, height: usize) -> String {
+    if samples.is_empty() || width == 0 || height == 0 {
+        return String::new();
+    }
+
+    let (columns, rows) = (width * 2, height * 4);
+    let row = |sample: i8| {
+        let from_top = (EDGE - sample as f64) / (2.0 * EDGE) * (rows - 1) as f64;
+        from_top.round().clamp(0.0, (rows - 1) as f64) as usize
+    };
+
+    let mut cells = vec![0u8; width * height];
+    for column in 0..columns {
+        // each dot column covers its share of the samples, at least one
+        let start = column * samples.len() / columns;
+        let end = ((column + 1) * samples.len() / columns).max(start + 1);
+        let chunk = &samples[start..end];
+        let (min, max) = chunk.iter().fold((i8::MAX, i8::MIN), |(min, max), &s| {
+            (min.min(s), max.max(s))
+        });
+
+        // a vertical bar from min to max, so steep edges stay connected
+        for dot_row in row(max)..=row(min) {
+            cells[dot_row / 4 * width + column / 2] |= braille_dot(column % 2, dot_row % 4);
+        }
+    }
+
+    cells
+        .chunks(width)
+        .map(|line| {
+            line.iter()
+                .map(|&dots| char::from_u32(0x2800 + dots as u32).unwrap())
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The bit of a dot in a braille character (U+2800 plus the bits), dots 1-3 and 7 being the left
+/// column from top to bottom, 4-6 and 8 the right one.
+fn braille_dot(column: usize, row: usize) -> u8 {
+    const DOTS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+    DOTS[column][row]
+}
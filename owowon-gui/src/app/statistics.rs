@@ -0,0 +1,77 @@
+use owowon::data::measurement::{MeasurementKind, Measurements};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Rolling statistics of a single measurement over the last `window` readings.
+#[derive(Default)]
+pub struct Statistics {
+    values: VecDeque<f64>,
+}
+
+impl Statistics {
+    pub fn push(&mut self, value: f64, window: usize) {
+        self.values.push_back(value);
+        while self.values.len() > window.max(1) {
+            self.values.pop_front();
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn min(&self) -> f64 {
+        self.values.iter().copied().fold(f64::INFINITY, f64::min)
+    }
+
+    pub fn max(&self) -> f64 {
+        self.values
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.values.iter().sum::<f64>() / self.values.len() as f64
+    }
+
+    /// Population standard deviation
+    pub fn std_dev(&self) -> f64 {
+        let mean = self.mean();
+        let variance = self
+            .values
+            .iter()
+            .map(|value| (value - mean).powi(2))
+            .sum::<f64>()
+            / self.values.len() as f64;
+        variance.sqrt()
+    }
+}
+
+/// [`Statistics`] of every measurement, per channel (CH1, CH2).
+#[derive(Default)]
+pub struct MeasurementStatistics {
+    channels: [BTreeMap<MeasurementKind, Statistics>; 2],
+}
+
+impl MeasurementStatistics {
+    /// Adds the latest readings. Unavailable measurements are skipped.
+    pub fn push(&mut self, measurements: &[Measurements; 2], window: usize) {
+        for (stats, measurements) in self.channels.iter_mut().zip(measurements) {
+            for kind in MeasurementKind::all() {
+                if let Some(value) = measurements.value(kind) {
+                    stats.entry(kind).or_default().push(value.0, window);
+                }
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for stats in &mut self.channels {
+            stats.clear();
+        }
+    }
+
+    pub fn get(&self, channel_index: usize, kind: MeasurementKind) -> Option<&Statistics> {
+        self.channels[channel_index].get(&kind)
+    }
+}
@@ -0,0 +1,52 @@
+use owowon::data::head::RunStatus;
+
+/// What happens when the device triggers, e.g. while waiting for a single shot.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct TriggerAlertSettings {
+    pub beep: bool,
+    /// Flashes the taskbar button
+    pub flash: bool,
+}
+
+/// Follows the run status, so each trigger event alerts once instead of every frame the status
+/// stays triggered.
+#[derive(Debug, Default)]
+pub struct TriggerAlertState {
+    last_status: Option<RunStatus>,
+    /// The next status change may be the user's doing, see [`Self::user_command`]
+    user_command: bool,
+}
+
+impl TriggerAlertState {
+    /// Whether `status` starts a trigger event: triggered now, but not in the previous frame.
+    ///
+    /// The device also reports `Stopped` when the user presses Stop, so that only counts right
+    /// after the trigger was armed, and not right after a [`Self::user_command`].
+    pub fn triggered(&mut self, status: RunStatus) -> bool {
+        // the first frame of a run doesn't count, the trigger may have happened long before
+        let Some(previous) = self.last_status.replace(status) else {
+            return false;
+        };
+        let user_command = previous != status && std::mem::take(&mut self.user_command);
+        match status {
+            RunStatus::Stopped => {
+                !user_command && matches!(previous, RunStatus::Ready | RunStatus::NotStarted)
+            }
+            RunStatus::Triggering => {
+                !matches!(previous, RunStatus::Stopped | RunStatus::Triggering)
+            }
+            _ => false,
+        }
+    }
+
+    /// The user sent a command that may stop the acquisition (e.g. `:RUNning STOP` from the SCPI
+    /// console), so the next status change doesn't alert if it's a stop.
+    pub fn user_command(&mut self) {
+        self.user_command = true;
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
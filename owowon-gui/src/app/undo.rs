@@ -0,0 +1,111 @@
+use owowon::{data::head::DataHeader, OscilloscopeCommand};
+use std::collections::VecDeque;
+
+/// Number of changes that can be undone.
+const UNDO_LEN: usize = 50;
+
+/// A sent setting change and the command restoring the value it replaced.
+#[derive(Debug, Clone, Copy)]
+struct Change {
+    command: OscilloscopeCommand,
+    inverse: OscilloscopeCommand,
+}
+
+/// Undo/redo of the reversible settings (scales, offsets, trigger level, couplings).
+#[derive(Debug, Default)]
+pub struct UndoHistory {
+    undo: VecDeque<Change>,
+    redo: Vec<Change>,
+    /// Whether a change was recorded since the last header, which then still shows the old value
+    pending: bool,
+}
+
+impl UndoHistory {
+    /// Records a sent command, with the value it replaced taken from `head` (the last received
+    /// header). Auto changes everything at once, so it clears the history.
+    pub fn record(&mut self, command: OscilloscopeCommand, head: &DataHeader) {
+        if let OscilloscopeCommand::Auto = command {
+            self.clear();
+            return;
+        }
+        let Some(inverse) = inverse(command, head) else {
+            return;
+        };
+
+        self.redo.clear();
+        match self.undo.back_mut() {
+            // the header doesn't show the previous change yet, so `inverse` would restore the
+            // wrong value. Repeated steps (e.g. arrow keys) are merged into one instead.
+            Some(last) if self.pending && same_setting(last.command, command) => {
+                last.command = command;
+            }
+            _ => {
+                if self.undo.len() == UNDO_LEN {
+                    self.undo.pop_front();
+                }
+                self.undo.push_back(Change { command, inverse });
+            }
+        }
+        self.pending = true;
+    }
+
+    /// Called for each received header.
+    pub fn header_updated(&mut self) {
+        self.pending = false;
+    }
+
+    /// The command reverting the last change, if any.
+    pub fn undo(&mut self) -> Option<OscilloscopeCommand> {
+        let change = self.undo.pop_back()?;
+        self.redo.push(change);
+        self.pending = true;
+        Some(change.inverse)
+    }
+
+    /// The command re-applying the last undone change, if any.
+    pub fn redo(&mut self) -> Option<OscilloscopeCommand> {
+        let change = self.redo.pop()?;
+        self.undo.push_back(change);
+        self.pending = true;
+        Some(change.command)
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// The command setting what `command` changes back to its value in `head`, `None` for commands
+/// that aren't undoable.
+fn inverse(command: OscilloscopeCommand, head: &DataHeader) -> Option<OscilloscopeCommand> {
+    use OscilloscopeCommand::*;
+
+    Some(match command {
+        SetHorizontalOffset(_) => SetHorizontalOffset(head.time_base.h_offset_grid_divs()),
+        SetTimeScale(_) => SetTimeScale(head.time_base.scale),
+        SetChannelVOffset(channel, _) => {
+            SetChannelVOffset(channel, head.channel(channel).offset_grid_divs())
+        }
+        // sent attenuated, see `set_vertical_scale`
+        SetChannelVScale(channel, _) => {
+            SetChannelVScale(channel, head.channel(channel).scale_attenuated())
+        }
+        SetChannelCoupling(channel, _) => {
+            SetChannelCoupling(channel, head.channel(channel).coupling)
+        }
+        SetTriggerLevel(_) => SetTriggerLevel(head.trigger.items.level),
+        SetTriggerCoupling(_) => SetTriggerCoupling(head.trigger.items.coupling),
+        _ => return None,
+    })
+}
+
+fn same_setting(a: OscilloscopeCommand, b: OscilloscopeCommand) -> bool {
+    use OscilloscopeCommand::*;
+
+    match (a, b) {
+        (SetChannelVOffset(a, _), SetChannelVOffset(b, _))
+        | (SetChannelVScale(a, _), SetChannelVScale(b, _))
+        | (SetChannelCoupling(a, _), SetChannelCoupling(b, _)) => a == b,
+        _ => std::mem::discriminant(&a) == std::mem::discriminant(&b),
+    }
+}
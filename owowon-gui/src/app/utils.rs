@@ -4,61 +4,205 @@ use owowon::{
     consts::{GRID_DIV_SIZE, GRID_DIV_SIZE_INT, TIME_BASES, VERTICAL_SCALES},
     data::{
         head::{ChannelInfo, DataHeader},
-        units::{Time, Voltage},
+        measurement::Measurements,
+        units::{ProbeAttenuation, Time, Voltage},
     },
 };
 
+/// The [`TIME_BASES`] entry of the current time base (the closest one for fine time bases) and
+/// the ones directly below and above the current time base.
 pub fn selected_time_base(head: &DataHeader) -> (usize, Time, Option<Time>, Option<Time>) {
-    let selected_index = TIME_BASES
-        .iter()
-        .position(|&t| t.0.approx_eq_ulps(&head.time_base.scale.0, 2))
-        .unwrap_or(0);
-    let selected_timebase = TIME_BASES[selected_index];
-
-    (
-        selected_index,
-        selected_timebase,
-        selected_index.checked_sub(1).map(|i| TIME_BASES[i]),
-        TIME_BASES.get(selected_index + 1).copied(),
-    )
+    select_preset(&TIME_BASES, head.time_base.scale.0, |t| t.0)
 }
 
+/// Like [`selected_time_base`], for the vertical scale (1X, per div).
 pub fn selected_voltage(
     channel_info: &ChannelInfo,
 ) -> (usize, Voltage, Option<Voltage>, Option<Voltage>) {
-    let selected_index = VERTICAL_SCALES
+    select_preset(&VERTICAL_SCALES, channel_info.scale.0, |v| v.0)
+}
+
+fn select_preset<T: Copy>(
+    presets: &[T],
+    value: f64,
+    to_f64: impl Fn(T) -> f64,
+) -> (usize, T, Option<T>, Option<T>) {
+    let exact = presets
         .iter()
-        .position(|&v| v.0.approx_eq_ulps(&channel_info.scale.0, 2))
-        .unwrap_or(0);
-    let selected_voltage = VERTICAL_SCALES[selected_index];
+        .position(|&p| to_f64(p).approx_eq_ulps(&value, 2));
+    let selected_index = exact.unwrap_or_else(|| nearest_preset(presets, value, &to_f64));
+
+    // a fine value in between steps to the presets around it, not past them
+    let (smaller, bigger) = match exact {
+        Some(i) => (i.checked_sub(1), Some(i + 1).filter(|&i| i < presets.len())),
+        None => (
+            presets.iter().rposition(|&p| to_f64(p) < value),
+            presets.iter().position(|&p| to_f64(p) > value),
+        ),
+    };
 
     (
         selected_index,
-        selected_voltage,
-        selected_index.checked_sub(1).map(|i| VERTICAL_SCALES[i]),
-        VERTICAL_SCALES.get(selected_index + 1).copied(),
+        presets[selected_index],
+        smaller.map(|i| presets[i]),
+        bigger.map(|i| presets[i]),
+    )
+}
+
+/// Index of the [`VERTICAL_SCALES`] entry closest to `scale` (1X, per div), e.g. after the probe
+/// attenuation changed to one the current scale doesn't divide evenly by.
+pub fn nearest_vertical_scale(scale: Voltage) -> usize {
+    nearest_preset(&VERTICAL_SCALES, scale.0, |v| v.0)
+}
+
+fn nearest_preset<T: Copy>(presets: &[T], value: f64, to_f64: impl Fn(T) -> f64) -> usize {
+    // the presets are roughly logarithmic, so compare ratios instead of differences
+    let distance = |&p: &T| (to_f64(p) / value).ln().abs();
+    presets
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// Relative change of a fine scale step, see [`calc_fine_scale`].
+const FINE_SCALE_STEP: f64 = 0.02;
+
+/// `scale` one fine step larger or smaller: 2%, 10% with shift.
+fn calc_fine_scale(scale: f64, mods: Modifiers, larger: bool) -> f64 {
+    let step = if mods.shift {
+        5.0 * FINE_SCALE_STEP
+    } else {
+        FINE_SCALE_STEP
+    };
+    if larger {
+        scale * (1.0 + step)
+    } else {
+        scale / (1.0 + step)
+    }
+}
+
+pub fn calc_fine_time_base(head: &DataHeader, mods: Modifiers, larger: bool) -> Time {
+    clamp_time_base(Time(calc_fine_scale(head.time_base.scale.0, mods, larger)))
+}
+
+/// The vertical scale (1X, per div) one fine step from the current one.
+pub fn calc_fine_vertical_scale(
+    channel_info: &ChannelInfo,
+    mods: Modifiers,
+    larger: bool,
+) -> Voltage {
+    clamp_vertical_scale(Voltage(calc_fine_scale(channel_info.scale.0, mods, larger)))
+}
+
+/// Keeps a fine time base within the range of the [`TIME_BASES`].
+pub fn clamp_time_base(time: Time) -> Time {
+    Time(
+        time.0
+            .clamp(TIME_BASES[0].0, TIME_BASES[TIME_BASES.len() - 1].0),
     )
 }
 
+/// Keeps a fine vertical scale (1X) within the range of the [`VERTICAL_SCALES`].
+pub fn clamp_vertical_scale(scale: Voltage) -> Voltage {
+    Voltage(scale.0.clamp(
+        VERTICAL_SCALES[0].0,
+        VERTICAL_SCALES[VERTICAL_SCALES.len() - 1].0,
+    ))
+}
+
+/// Divisions a fitted signal should fill, leaving some headroom of the 8 visible ones.
+const FIT_DIVS: f64 = 6.0;
+
+/// Vertical scale (1X, per div) and offset (in divs) that center the signal and make its
+/// peak-to-peak fill about [`FIT_DIVS`], without touching time base or trigger like `:AUToset`.
+/// `None` if the peak-to-peak/average measurements aren't available.
+pub fn calc_channel_fit(
+    channel_info: &ChannelInfo,
+    measurements: &Measurements,
+) -> Option<(Voltage, f64)> {
+    let peak_to_peak = Voltage(measurements.peak_to_peak.0?.0);
+    let average = Voltage(measurements.average.0?.0);
+    let attenuation = channel_info.probe.0 as f64;
+
+    let scale = VERTICAL_SCALES
+        .iter()
+        .copied()
+        .find(|&scale| scale * attenuation * FIT_DIVS >= peak_to_peak)
+        .unwrap_or(VERTICAL_SCALES[VERTICAL_SCALES.len() - 1]);
+    let offset = -average / (scale * attenuation);
+
+    Some((scale, offset))
+}
+
+/// Peak-to-peak (in volts, as measured) above which a 1X probe setting is suspicious.
+const PROBE_MISMATCH_PEAK_TO_PEAK: f64 = 20.0;
+
+/// Whether the channel is set to a 1X probe while showing a signal that large, which is usually
+/// measured with a 10X probe. With a 10X probe connected, every reading would be 10 times too
+/// small.
+pub fn suspect_probe_mismatch(channel_info: &ChannelInfo, measurements: &Measurements) -> bool {
+    channel_info.probe == ProbeAttenuation(1)
+        && measurements
+            .peak_to_peak
+            .0
+            .is_some_and(|peak_to_peak| peak_to_peak.0 > PROBE_MISMATCH_PEAK_TO_PEAK)
+}
+
+/// Steps the holdoff through the 1-2-5 sequence of [`TIME_BASES`], starting from the shortest one
+/// while it's still unknown.
+pub fn calc_new_trigger_holdoff(head: &DataHeader, positive: bool) -> Time {
+    let Some(holdoff) = head.trigger.holdoff else {
+        return TIME_BASES[0];
+    };
+
+    let index = TIME_BASES
+        .iter()
+        .rposition(|&t| t <= holdoff * 1.001)
+        .unwrap_or(0);
+    let index = if positive {
+        (index + 1).min(TIME_BASES.len() - 1)
+    } else {
+        index.saturating_sub(1)
+    };
+    TIME_BASES[index]
+}
+
 pub fn calc_new_vertical_offset(
     channel_info: &ChannelInfo,
     mods: Modifiers,
     positive: bool,
 ) -> f64 {
-    (channel_info.offset + grid_offset_change(mods, positive)) as f64 / GRID_DIV_SIZE
+    (channel_info.offset + grid_offset_change(mods, positive, GRID_DIV_SIZE_INT)) as f64
+        / GRID_DIV_SIZE
 }
 
 pub fn calc_new_horizontal_offset(head: &DataHeader, mods: Modifiers, positive: bool) -> f64 {
-    (head.time_base.h_offset + grid_offset_change(mods, positive)) as f64 / GRID_DIV_SIZE
+    let time_base = &head.time_base;
+    let samples_per_div = time_base.screen.samples_per_div;
+    let change = grid_offset_change(mods, positive, samples_per_div.round() as i64);
+    time_base.snap_grid_divs((time_base.h_offset + change) as f64 / samples_per_div)
 }
 
-fn grid_offset_change(mods: Modifiers, positive: bool) -> i64 {
+/// The horizontal offset (in grid divs) that keeps the time at the plot position `anchor_x` in
+/// place when switching to the time base `new_scale`.
+pub fn calc_anchored_horizontal_offset(head: &DataHeader, new_scale: Time, anchor_x: f64) -> f64 {
+    let time_base = &head.time_base;
+    // the plot position `x` shows the time `(x + h_offset) / samples_per_div * scale`
+    let h_offset =
+        (anchor_x + time_base.h_offset as f64) * (time_base.scale / new_scale) - anchor_x;
+    h_offset / time_base.screen.samples_per_div
+}
+
+/// A step of one unit (with ctrl), a whole div (with shift) or a fifth of one.
+fn grid_offset_change(mods: Modifiers, positive: bool, div_size: i64) -> i64 {
     let magnitude = if mods.command {
         1
     } else if mods.shift {
-        GRID_DIV_SIZE_INT
+        div_size
     } else {
-        GRID_DIV_SIZE_INT / 5
+        div_size / 5
     };
 
     if positive {
@@ -71,7 +215,18 @@ fn grid_offset_change(mods: Modifiers, positive: bool) -> i64 {
 pub fn calc_new_trigger_level(head: &DataHeader, mods: Modifiers, positive: bool) -> Voltage {
     let scale_per_unit = head.channel(head.trigger.items.channel).scale_per_unit();
 
-    Voltage(head.trigger.items.level.0 + trigger_level_change(mods, positive, scale_per_unit))
+    head.trigger.items.level + Voltage(trigger_level_change(mods, positive, scale_per_unit))
+}
+
+/// The trigger level at the midpoint of the trigger channel's signal, halfway between its lowest
+/// and highest sample (`data` being its screen data). `None` without data.
+pub fn calc_trigger_level_midpoint(head: &DataHeader, data: &[u8]) -> Option<Voltage> {
+    // min/max over all bytes also covers the peak detect pairs
+    let samples = data.iter().map(|&sample| sample as i8);
+    let (min, max) = (samples.clone().min()?, samples.max()?);
+    // through the channel's offset and scale, like the trace is drawn
+    let channel = head.channel(head.trigger.items.channel);
+    Some(channel.units_to_voltage((min as f64 + max as f64) / 2.0))
 }
 
 fn trigger_level_change(mods: Modifiers, positive: bool, scale_per_unit: f64) -> f64 {
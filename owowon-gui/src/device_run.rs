@@ -1,9 +1,15 @@
 use egui::Context;
 use owowon::{
-    device::{run_device_loop, Device, RunError},
+    device::{
+        demo::DemoIo, run_device_loop, run_transport_loop, Device, ErrorClass, FromUsbDeviceError,
+        RunError,
+    },
     InitialDeviceRunConfig, OscilloscopeMessage, OscilloscopeRunCommand,
 };
-use std::thread;
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
 use tokio::{sync::mpsc, task::LocalSet};
 
 #[derive(Debug, Default)]
@@ -12,6 +18,8 @@ pub enum DeviceRunState {
     Stopped,
     Running(DeviceRun),
     Error(RunError),
+    /// The device dropped off mid-session, waiting for it to reappear.
+    Reconnecting(Reconnect),
 }
 
 impl DeviceRunState {
@@ -31,6 +39,7 @@ impl DeviceRunState {
                 }
             }
             DeviceRunState::Error(_) => {}
+            DeviceRunState::Reconnecting(_) => {}
         }
     }
 
@@ -38,15 +47,139 @@ impl DeviceRunState {
         match self {
             DeviceRunState::Stopped => {}
             DeviceRunState::Running(run) => {
-                let thread = run.data_thread.take().unwrap();
-                // dropping the run, including the channels, causing the other thread to exit
-                // eventually
+                // an error this late has no one left to report it to
+                let _ = run.shutdown();
                 *self = DeviceRunState::Stopped;
-                thread.join().unwrap().unwrap();
             }
             DeviceRunState::Error(_) => {}
+            DeviceRunState::Reconnecting(_) => *self = DeviceRunState::Stopped,
+        }
+    }
+}
+
+/// A device error as shown to the user: its class for a targeted hint, and the full report.
+#[derive(Debug)]
+pub struct DeviceError {
+    pub class: ErrorClass,
+    pub report: String,
+}
+
+impl DeviceError {
+    /// What the user can do about it
+    pub fn hint(&self) -> &'static str {
+        match self.class {
+            ErrorClass::WrongDevice => "This device is not a supported oscilloscope.",
+            ErrorClass::PipeNotFound => {
+                "The device doesn't offer the expected USB endpoints. Make sure it's in \
+                oscilloscope mode and not used as a USB drive."
+            }
+            ErrorClass::Timeout => {
+                "The device stopped responding. Close any open menu on the device, or replug it."
+            }
+            ErrorClass::DeviceBusy => {
+                "Another application (or another instance of this one) is using the device. \
+                Close it and try again."
+            }
+            ErrorClass::Other => "Communicating with the device failed.",
+        }
+    }
+}
+
+impl From<&RunError> for DeviceError {
+    fn from(e: &RunError) -> Self {
+        Self {
+            class: e.class(),
+            report: snafu::Report::from_error(e).to_string(),
+        }
+    }
+}
+
+impl From<FromUsbDeviceError> for DeviceError {
+    fn from(e: FromUsbDeviceError) -> Self {
+        Self {
+            class: e.class(),
+            report: snafu::Report::from_error(e).to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Reconnect {
+    device_id: String,
+    attempt: u32,
+    next_attempt: Instant,
+    /// When to give up, only when waiting for the device on launch. A dropped device is waited
+    /// for until cancelled.
+    deadline: Option<Instant>,
+}
+
+impl Reconnect {
+    const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+    const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+    pub fn new(device_id: String) -> Self {
+        Self {
+            device_id,
+            attempt: 0,
+            next_attempt: Instant::now() + Self::INITIAL_BACKOFF,
+            deadline: None,
+        }
+    }
+
+    /// Waits at most `timeout` for a device that isn't there (yet), connecting the moment it
+    /// shows up.
+    pub fn waiting(device_id: String, timeout: Duration) -> Self {
+        let now = Instant::now();
+        Self {
+            device_id,
+            attempt: 0,
+            next_attempt: now,
+            deadline: Some(now + timeout),
         }
     }
+
+    pub fn device_id(&self) -> &str {
+        &self.device_id
+    }
+
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Time left until the next reconnection attempt is due.
+    pub fn remaining(&self) -> Duration {
+        self.next_attempt.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// Time left until giving up, `None` if waiting indefinitely.
+    pub fn time_left(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.time_left().is_some_and(|left| left.is_zero())
+    }
+
+    /// Schedules the next attempt, doubling the backoff up to [`Self::MAX_BACKOFF`].
+    pub fn failed(&mut self) {
+        self.attempt += 1;
+        let backoff = Self::INITIAL_BACKOFF
+            .saturating_mul(1 << self.attempt.min(16))
+            .min(Self::MAX_BACKOFF);
+        self.next_attempt = Instant::now() + backoff;
+    }
+}
+
+/// What a [`DeviceRun`] talks to.
+pub enum DeviceBackend {
+    Usb(Device),
+    /// The simulated device, see [`DemoIo`]
+    Demo,
 }
 
 #[derive(Debug)]
@@ -57,7 +190,11 @@ pub struct DeviceRun {
 }
 
 impl DeviceRun {
-    pub fn new(device: Device, egui_ctx: &Context, initial_config: InitialDeviceRunConfig) -> Self {
+    pub fn new(
+        backend: DeviceBackend,
+        egui_ctx: &Context,
+        initial_config: InitialDeviceRunConfig,
+    ) -> Self {
         let rt = tokio::runtime::Builder::new_current_thread()
             .enable_time()
             .build()
@@ -73,10 +210,29 @@ impl DeviceRun {
                 let local = LocalSet::new();
 
                 let run = local.run_until(async move {
-                    run_device_loop(device, message_tx, command_rx, initial_config, move || {
-                        egui_ctx.request_repaint()
-                    })
-                    .await
+                    let notify_updated = move || egui_ctx.request_repaint();
+                    match backend {
+                        DeviceBackend::Usb(device) => {
+                            run_device_loop(
+                                device,
+                                message_tx,
+                                command_rx,
+                                initial_config,
+                                notify_updated,
+                            )
+                            .await
+                        }
+                        DeviceBackend::Demo => {
+                            run_transport_loop(
+                                DemoIo::new(),
+                                message_tx,
+                                command_rx,
+                                initial_config,
+                                notify_updated,
+                            )
+                            .await
+                        }
+                    }
                 });
 
                 rt.block_on(run)
@@ -102,6 +258,17 @@ impl DeviceRun {
             .unwrap_or(true)
     }
 
+    /// Ends the device loop after it sent the commands issued so far, so last-moment setting
+    /// changes still reach the device. Waits for the loop to finish.
+    pub fn shutdown(&mut self) -> Option<Result<(), RunError>> {
+        // the loop must not wait on messages that won't be read anymore
+        self.message_rx.close();
+        let _ = self
+            .command_tx
+            .blocking_send(OscilloscopeRunCommand::Shutdown);
+        self.join()
+    }
+
     pub fn join(&mut self) -> Option<Result<(), RunError>> {
         self.data_thread.take().map(|t| t.join().unwrap())
     }
@@ -9,7 +9,7 @@ use windows::{
     Foundation::{EventRegistrationToken, TypedEventHandler},
 };
 
-type DeviceList = Arc<RwLock<HashMap<String, DeviceInformation>>>;
+pub type DeviceList = Arc<RwLock<HashMap<String, DeviceInformation>>>;
 
 pub struct DeviceSelector {
     list: DeviceList,
@@ -1,4 +1,3 @@
-#![feature(array_chunks)]
 #![warn(clippy::all, rust_2018_idioms)]
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
@@ -12,6 +11,13 @@ mod selectable_label_full_width;
 static ALLOC: snmalloc_rs::SnMalloc = snmalloc_rs::SnMalloc;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // warnings from the device loop, e.g. a device that couldn't be identified
+    #[cfg(not(feature = "trace-io"))]
+    let level = tracing_subscriber::filter::LevelFilter::WARN;
+    #[cfg(feature = "trace-io")]
+    let level = tracing_subscriber::filter::LevelFilter::TRACE;
+    tracing_subscriber::fmt().with_max_level(level).init();
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_min_inner_size(egui::vec2(800.0, 400.0))
@@ -1,14 +1,40 @@
+use std::cell::RefCell;
 use tokio::sync::mpsc::{self, error::SendError};
 
-pub struct OptionalSender<T>(pub Option<mpsc::Sender<T>>);
+/// The part of a sent value that's kept for [`OptionalSender::take_sent`], if any.
+pub trait Recorded {
+    type Record;
+
+    fn record(&self) -> Option<Self::Record>;
+}
+
+pub struct OptionalSender<T: Recorded> {
+    sender: Option<mpsc::Sender<T>>,
+    /// Everything recorded from the values sent successfully, see [`OptionalSender::take_sent`]
+    sent: RefCell<Vec<T::Record>>,
+}
+
+impl<T: Recorded> OptionalSender<T> {
+    pub fn new(sender: Option<mpsc::Sender<T>>) -> Self {
+        Self {
+            sender,
+            sent: Default::default(),
+        }
+    }
 
-impl<T> OptionalSender<T> {
     #[track_caller]
     pub fn blocking_send(&self, value: impl Into<T>) -> Result<(), SendError<T>> {
-        if let Some(sender) = &self.0 {
-            sender.blocking_send(value.into())
-        } else {
-            Ok(())
+        if let Some(sender) = &self.sender {
+            let value = value.into();
+            let record = value.record();
+            sender.blocking_send(value)?;
+            self.sent.borrow_mut().extend(record);
         }
+        Ok(())
+    }
+
+    /// The records of the values sent since the last call, oldest first.
+    pub fn take_sent(&self) -> Vec<T::Record> {
+        self.sent.take()
     }
 }
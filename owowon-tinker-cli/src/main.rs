@@ -1,9 +1,57 @@
-use owowon::device::Device;
-use std::time::Instant;
+use owowon::device::{Device, FromUsbDeviceError};
+use std::{process::ExitCode, time::Instant};
+
+const USAGE: &str = "usage: owowon-tinker-cli [--list | --device-index N | --device-id ID]
+
+Sends each line from stdin to the device, printing the replies to queries (lines ending in `?`).
+Without options, the first connected device is used.";
 
 #[tokio::main(flavor = "current_thread")]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let device = Device::from_first_vid_pid_match().await?;
+async fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let device = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => Device::from_first_vid_pid_match().await,
+        ["--list"] => {
+            for (index, entry) in Device::list().await?.iter().enumerate() {
+                println!(
+                    "{index}: {} (VID {:04X}, PID {:04X})\n   {}",
+                    entry.name, entry.vid, entry.pid, entry.id
+                );
+            }
+            return Ok(ExitCode::SUCCESS);
+        }
+        ["--device-index", index] => {
+            let devices = Device::list().await?;
+            let Some(entry) = index
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| devices.get(index))
+            else {
+                eprintln!(
+                    "Error: no device {index}, {} found (see --list)",
+                    devices.len()
+                );
+                return Ok(ExitCode::FAILURE);
+            };
+            Device::from_device_id(entry.id.as_str()).await
+        }
+        ["--device-id", id] => Device::from_device_id(id).await,
+        _ => {
+            eprintln!("{USAGE}");
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+    let device = match device {
+        Ok(device) => device,
+        // nothing to debug about these, a backtrace-y error would only confuse
+        Err(
+            e @ (FromUsbDeviceError::NoDeviceFound | FromUsbDeviceError::NoMatchingDevice { .. }),
+        ) => {
+            eprintln!("Error: {e}");
+            return Ok(ExitCode::FAILURE);
+        }
+        Err(e) => return Err(e.into()),
+    };
     let mut io = device.raw_io()?;
 
     let mut buf = [0u8; 10240];
@@ -27,5 +75,5 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("{} ms", time.elapsed().as_millis());
     }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
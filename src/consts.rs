@@ -6,8 +6,16 @@ pub use attenuation::PROBE_ATTENUATIONS;
 pub use time_bases::TIME_BASES;
 pub use vscale::VERTICAL_SCALES;
 
-/// Grid division size
+/// Vertical grid division size, in sample steps. The horizontal one is model-dependent, see
+/// [`ScreenGeometry`](crate::data::screen::ScreenGeometry).
 pub const GRID_DIV_SIZE_INT: i64 = 25;
 pub const GRID_DIV_SIZE: f64 = 25.0;
-pub const GRID_DIV_COUNT_HORIZONTAL: f64 = 12.0;
+/// Sample value at the top (and negated, the bottom) of the ADC range. Signals beyond it are cut
+/// off there.
+pub const SAMPLE_CLIP_LEVEL: i8 = 127;
+/// Most channels of any supported model. The header says how many the connected one has, see
+/// [`Channels`](crate::data::head::Channels).
+pub const MAX_CHANNELS: usize = 4;
+/// Fallback screen width in samples, see
+/// [`DataHeader::screen_samples`](crate::data::head::DataHeader::screen_samples).
 pub const SAMPLES: usize = 300;
@@ -1,4 +1,9 @@
 pub mod awg;
+pub mod decode;
+pub mod diff;
 pub mod head;
+pub mod limits;
 pub mod measurement;
+pub mod model;
+pub mod screen;
 pub mod units;
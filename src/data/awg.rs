@@ -1,4 +1,6 @@
 use super::units::{Frequency, Voltage};
+use snafu::{ensure, ResultExt, Snafu};
+use std::{num::ParseIntError, ops::RangeInclusive};
 use strum::{Display, EnumCount, EnumIter, EnumString};
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, EnumString, EnumIter, EnumCount, Display)]
@@ -29,6 +31,21 @@ pub enum AwgMode {
     #[strum(serialize = "Bessely")]
     BesselY,
     Sinc,
+    /// User-defined waveform, see [`ArbitraryWaveform`]
+    #[strum(serialize = "ARB")]
+    Arbitrary,
+}
+
+impl AwgMode {
+    /// Whether [`AwgChannelConfig::duty_cycle`] applies to this mode.
+    pub fn has_duty_cycle(self) -> bool {
+        matches!(self, AwgMode::Square | AwgMode::Pulse)
+    }
+
+    /// Whether [`AwgChannelConfig::symmetry`] applies to this mode.
+    pub fn has_symmetry(self) -> bool {
+        self == AwgMode::Ramp
+    }
 }
 
 pub const AWG_MODES: [AwgMode; AwgMode::COUNT] = [
@@ -44,18 +61,40 @@ pub const AWG_MODES: [AwgMode; AwgMode::COUNT] = [
     AwgMode::BesselJ,
     AwgMode::BesselY,
     AwgMode::Sinc,
+    AwgMode::Arbitrary,
 ];
 
-#[derive(Debug, Clone, Copy)]
+/// Configuration of all generator outputs. Single-output models have exactly one channel.
+#[derive(Debug, Clone)]
 pub struct AwgConfig {
+    pub channels: Vec<AwgChannelConfig>,
+}
+
+impl Default for AwgConfig {
+    fn default() -> Self {
+        Self {
+            channels: vec![Default::default()],
+        }
+    }
+}
+
+// not `Copy` because of the owned arbitrary waveform table
+#[derive(Debug, Clone)]
+pub struct AwgChannelConfig {
     pub enabled: bool,
     pub mode: AwgMode,
     pub frequency: Frequency,
     pub amplitude: Voltage,
     pub offset: Voltage,
+    /// Duty cycle in percent, for square/pulse modes
+    pub duty_cycle: f64,
+    /// Ramp symmetry in percent (50% = triangle, 0%/100% = sawtooth), for ramp mode
+    pub symmetry: f64,
+    /// Uploaded to the device on set, if present. Can't be read back.
+    pub arbitrary: Option<ArbitraryWaveform>,
 }
 
-impl Default for AwgConfig {
+impl Default for AwgChannelConfig {
     fn default() -> Self {
         Self {
             enabled: Default::default(),
@@ -63,10 +102,117 @@ impl Default for AwgConfig {
             frequency: Frequency(1_000_000.0),
             amplitude: Voltage(1.0),
             offset: Default::default(),
+            duty_cycle: 50.0,
+            symmetry: 50.0,
+            arbitrary: None,
         }
     }
 }
 
+/// How the generator scales frequencies and amplitude/offset in its replies. Model-dependent, see
+/// [`ModelProfile`](super::model::ModelProfile).
+///
+/// Setting them always takes the documented Hz and V.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AwgUnits {
+    /// Reported value per Hz
+    pub frequency_scale: f64,
+    /// Reported value per V
+    pub voltage_scale: f64,
+}
+
+impl AwgUnits {
+    /// Hz and V, as documented in the SCPI manual.
+    pub const DOCUMENTED: Self = Self {
+        frequency_scale: 1.0,
+        voltage_scale: 1.0,
+    };
+
+    // BUG: the HDS200 series reports frequencies in micro-Hertz and amplitude/offset in millivolts
+    // (FW 1.5.1), while setting them takes Hz and V.
+    pub const HDS200S: Self = Self {
+        frequency_scale: 1e6,
+        voltage_scale: 1e3,
+    };
+
+    /// Converts a frequency as reported by the generator.
+    pub fn frequency_from_device(&self, raw: f64) -> Frequency {
+        Frequency(raw / self.frequency_scale)
+    }
+
+    /// Converts an amplitude/offset as reported by the generator.
+    pub fn voltage_from_device(&self, raw: f64) -> Voltage {
+        Voltage(raw / self.voltage_scale)
+    }
+}
+
+/// Duty cycle range (in percent) accepted by the generator.
+pub const AWG_DUTY_CYCLE_RANGE: RangeInclusive<f64> = 1.0..=99.0;
+/// Ramp symmetry range (in percent) accepted by the generator.
+pub const AWG_SYMMETRY_RANGE: RangeInclusive<f64> = 0.0..=100.0;
+
+/// Maximum number of samples of an arbitrary waveform.
+pub const ARB_MAX_SAMPLES: usize = 8192;
+/// Sample values accepted by the 14 bit DAC of the generator.
+pub const ARB_SAMPLE_RANGE: RangeInclusive<i16> = -8192..=8191;
+
+/// A validated arbitrary waveform sample table, in raw DAC values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArbitraryWaveform(Vec<i16>);
+
+impl ArbitraryWaveform {
+    pub fn new(samples: Vec<i16>) -> Result<Self, ArbitraryWaveformError> {
+        ensure!(!samples.is_empty(), EmptySnafu);
+        ensure!(
+            samples.len() <= ARB_MAX_SAMPLES,
+            TooLongSnafu { len: samples.len() }
+        );
+        if let Some((index, &value)) = samples
+            .iter()
+            .enumerate()
+            .find(|(_, v)| !ARB_SAMPLE_RANGE.contains(v))
+        {
+            return OutOfRangeSnafu { index, value }.fail();
+        }
+
+        Ok(Self(samples))
+    }
+
+    /// Parses integer DAC values, separated by commas and/or whitespace (e.g. one per line).
+    pub fn from_csv(csv: &str) -> Result<Self, ArbitraryWaveformError> {
+        let samples = csv
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .enumerate()
+            .map(|(index, s)| s.parse().context(ParseSnafu { index }))
+            .collect::<Result<_, _>>()?;
+
+        Self::new(samples)
+    }
+
+    pub fn samples(&self) -> &[i16] {
+        &self.0
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum ArbitraryWaveformError {
+    #[snafu(display("the waveform has no samples"))]
+    Empty,
+    #[snafu(display(
+        "the waveform has {len} samples, but the device only supports up to {ARB_MAX_SAMPLES}"
+    ))]
+    TooLong { len: usize },
+    #[snafu(display(
+        "sample {index} ({value}) is outside of the DAC range {}..={}",
+        ARB_SAMPLE_RANGE.start(),
+        ARB_SAMPLE_RANGE.end()
+    ))]
+    OutOfRange { index: usize, value: i16 },
+    #[snafu(display("sample {index} is not an integer"))]
+    Parse { source: ParseIntError, index: usize },
+}
+
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString)]
 #[strum(serialize_all = "UPPERCASE")]
 pub enum AwgChannelDisplay {
@@ -90,3 +236,58 @@ impl From<bool> for AwgChannelDisplay {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Formats like `set_awg_config`, then parses how the device reports the applied values back
+    /// (in `units`) like `read_awg_config`.
+    fn round_trip(units: AwgUnits, config: &AwgChannelConfig) -> (Frequency, Voltage, Voltage) {
+        let sent = [config.frequency.0, config.amplitude.0, config.offset.0]
+            .map(|value| value.to_string().parse::<f64>().unwrap());
+        let reported = [
+            sent[0] * units.frequency_scale,
+            sent[1] * units.voltage_scale,
+            sent[2] * units.voltage_scale,
+        ]
+        .map(|raw| format!("{raw}\n"));
+
+        let [frequency, amplitude, offset] = reported.map(|s| s.trim().parse::<f64>().unwrap());
+        (
+            units.frequency_from_device(frequency),
+            units.voltage_from_device(amplitude),
+            units.voltage_from_device(offset),
+        )
+    }
+
+    #[test]
+    fn awg_config_survives_set_read_round_trip() {
+        for units in [AwgUnits::HDS200S, AwgUnits::DOCUMENTED] {
+            for (frequency, amplitude, offset) in [
+                (Frequency(1e3), Voltage(1.0), Voltage(0.0)),
+                (Frequency(2.5e6), Voltage(0.3), Voltage(-0.25)),
+                (Frequency(0.1), Voltage(2.5), Voltage(1.2)),
+            ] {
+                let config = AwgChannelConfig {
+                    frequency,
+                    amplitude,
+                    offset,
+                    ..Default::default()
+                };
+
+                assert_eq!(round_trip(units, &config), (frequency, amplitude, offset));
+            }
+        }
+    }
+
+    #[test]
+    fn hds200s_units_scale_the_readout() {
+        let units = AwgUnits::HDS200S;
+        assert_eq!(units.frequency_from_device(1e9), Frequency(1e3));
+        assert_eq!(units.voltage_from_device(500.0), Voltage(0.5));
+
+        let units = AwgUnits::DOCUMENTED;
+        assert_eq!(units.frequency_from_device(1e3), Frequency(1e3));
+    }
+}
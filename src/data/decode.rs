@@ -0,0 +1,36 @@
+//! Decoding serial protocols from the acquired (screen) data.
+
+use super::units::Voltage;
+
+pub mod i2c;
+pub mod uart;
+
+/// The threshold halfway between the lowest and highest voltage, `None` without any samples.
+pub fn auto_threshold(voltages: &[Voltage]) -> Option<Voltage> {
+    let min = voltages
+        .iter()
+        .copied()
+        .reduce(|a, b| if b < a { b } else { a })?;
+    let max = voltages
+        .iter()
+        .copied()
+        .reduce(|a, b| if b > a { b } else { a })?;
+    Some((min + max) / 2.0)
+}
+
+/// The logic level of each sample, `true` if above `threshold`.
+pub fn logic_levels(voltages: &[Voltage], threshold: Voltage) -> impl Iterator<Item = bool> + '_ {
+    voltages.iter().map(move |&voltage| voltage > threshold)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_threshold_is_the_midpoint() {
+        let voltages = [0.2, 3.3, 0.1, 3.1].map(Voltage);
+        assert_eq!(auto_threshold(&voltages), Some(Voltage(1.7)));
+        assert_eq!(auto_threshold(&[]), None);
+    }
+}
@@ -0,0 +1,344 @@
+use super::{auto_threshold, logic_levels};
+use crate::data::units::{Time, Voltage};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct I2cConfig {
+    /// Logic threshold of both lines, halfway between the lowest and highest voltage of each line
+    /// if `None`
+    pub threshold: Option<Voltage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum I2cEvent {
+    /// Start condition, including repeated starts
+    Start,
+    Stop,
+    /// First byte after a start: 7-bit address and the R/W bit
+    Address {
+        address: u8,
+        read: bool,
+        ack: bool,
+    },
+    /// A data byte, `read` if sent by the addressed device
+    Data {
+        value: u8,
+        read: bool,
+        ack: bool,
+    },
+    /// A byte cut short by a start or stop condition after `bits` bits
+    Incomplete {
+        bits: u8,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct I2cSymbol {
+    /// SCL rising edge of the first bit (or the time of a start/stop condition)
+    pub start: Time,
+    /// SCL rising edge of the ACK bit (or the time of a start/stop condition)
+    pub end: Time,
+    pub event: I2cEvent,
+}
+
+impl I2cSymbol {
+    /// A NAK from the addressed device. The NAK a controller sends after the last byte it reads
+    /// is the normal end of a read, and doesn't count.
+    pub fn is_error(&self) -> bool {
+        match self.event {
+            I2cEvent::Address { ack, .. } => !ack,
+            I2cEvent::Data { read, ack, .. } => !read && !ack,
+            I2cEvent::Incomplete { .. } => true,
+            I2cEvent::Start | I2cEvent::Stop => false,
+        }
+    }
+}
+
+/// Byte currently being clocked in
+struct PartialByte {
+    start: Time,
+    bits: u8,
+    value: u8,
+}
+
+/// Decodes I2C transfers from the SCL and SDA voltages, sampled at `times`.
+///
+/// Bits are read on the rising SCL edges, so SCL needs to be sampled at least a couple of times
+/// per clock period. Clocks before the first start condition are ignored.
+pub fn decode(
+    config: &I2cConfig,
+    times: &[Time],
+    scl: &[Voltage],
+    sda: &[Voltage],
+) -> Vec<I2cSymbol> {
+    let levels = |voltages: &[Voltage]| {
+        config
+            .threshold
+            .or_else(|| auto_threshold(voltages))
+            .map(|threshold| logic_levels(voltages, threshold).collect::<Vec<_>>())
+            .unwrap_or_default()
+    };
+    let (scl, sda) = (levels(scl), levels(sda));
+
+    let mut symbols = Vec::new();
+    // `None` outside of a transfer, the address byte is expected next if `Some(true)`
+    let mut expecting_address = None;
+    // the direction of the data bytes, from the R/W bit of the address
+    let mut read = false;
+    let mut byte: Option<PartialByte> = None;
+    // sampled on the rising SCL edge, but only a bit once SCL falls again: the controller raises
+    // SCL before a stop or repeated start as well
+    let mut pending_bit: Option<(Time, bool)> = None;
+
+    for (i, &time) in times
+        .iter()
+        .enumerate()
+        .take(scl.len().min(sda.len()))
+        .skip(1)
+    {
+        let scl_high = scl[i - 1] && scl[i];
+        let sda_fell = sda[i - 1] && !sda[i];
+        let sda_rose = !sda[i - 1] && sda[i];
+
+        // SDA changing while SCL is high: start or stop condition
+        if scl_high && (sda_fell || sda_rose) {
+            pending_bit = None;
+            if let Some(partial) = byte.take() {
+                symbols.push(I2cSymbol {
+                    start: partial.start,
+                    end: time,
+                    event: I2cEvent::Incomplete { bits: partial.bits },
+                });
+            }
+            let event = if sda_fell {
+                expecting_address = Some(true);
+                I2cEvent::Start
+            } else {
+                expecting_address = None;
+                I2cEvent::Stop
+            };
+            symbols.push(I2cSymbol {
+                start: time,
+                end: time,
+                event,
+            });
+            continue;
+        }
+
+        if expecting_address.is_some() && !scl[i - 1] && scl[i] {
+            pending_bit = Some((time, sda[i]));
+        }
+
+        // falling SCL edge: the sampled bit is valid
+        let (Some(address_phase), Some((bit_time, bit))) = (expecting_address, pending_bit) else {
+            continue;
+        };
+        if !scl[i - 1] || scl[i] {
+            continue;
+        }
+        pending_bit = None;
+
+        let partial = byte.get_or_insert(PartialByte {
+            start: bit_time,
+            bits: 0,
+            value: 0,
+        });
+
+        if partial.bits < 8 {
+            partial.value = partial.value << 1 | bit as u8;
+            partial.bits += 1;
+            continue;
+        }
+
+        // the 9th bit is the ACK (pulled low) or NAK
+        let ack = !bit;
+        let event = if address_phase {
+            read = partial.value & 1 != 0;
+            I2cEvent::Address {
+                address: partial.value >> 1,
+                read,
+                ack,
+            }
+        } else {
+            I2cEvent::Data {
+                value: partial.value,
+                read,
+                ack,
+            }
+        };
+        symbols.push(I2cSymbol {
+            start: partial.start,
+            end: bit_time,
+            event,
+        });
+        byte = None;
+        expecting_address = Some(false);
+    }
+
+    symbols
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds SCL/SDA from a sequence of line states, each held for 4 samples.
+    struct Bus {
+        scl: Vec<Voltage>,
+        sda: Vec<Voltage>,
+    }
+
+    impl Bus {
+        fn new() -> Self {
+            let mut bus = Self {
+                scl: Vec::new(),
+                sda: Vec::new(),
+            };
+            bus.set(true, true);
+            bus
+        }
+
+        fn set(&mut self, scl: bool, sda: bool) {
+            let level = |high| Voltage(if high { 3.3 } else { 0.0 });
+            for _ in 0..4 {
+                self.scl.push(level(scl));
+                self.sda.push(level(sda));
+            }
+        }
+
+        fn start(&mut self) {
+            self.set(true, true);
+            self.set(true, false);
+            self.set(false, false);
+        }
+
+        fn stop(&mut self) {
+            self.set(false, false);
+            self.set(true, false);
+            self.set(true, true);
+        }
+
+        fn bit(&mut self, bit: bool) {
+            self.set(false, bit);
+            self.set(true, bit);
+            self.set(false, bit);
+        }
+
+        fn byte(&mut self, value: u8, ack: bool) {
+            for bit in (0..8).rev() {
+                self.bit(value & (1 << bit) != 0);
+            }
+            self.bit(!ack);
+        }
+
+        fn decode(&self) -> Vec<I2cEvent> {
+            let times = (0..self.scl.len())
+                .map(|i| Time(i as f64 * 1e-6))
+                .collect::<Vec<_>>();
+            decode(&I2cConfig { threshold: None }, &times, &self.scl, &self.sda)
+                .into_iter()
+                .map(|symbol| symbol.event)
+                .collect()
+        }
+    }
+
+    #[test]
+    fn decodes_write_and_read() {
+        let mut bus = Bus::new();
+        bus.start();
+        bus.byte(0x50 << 1, true);
+        bus.byte(0x12, true);
+        // repeated start, then read two bytes
+        bus.set(false, true);
+        bus.start();
+        bus.byte(0x50 << 1 | 1, true);
+        bus.byte(0xA5, true);
+        bus.byte(0x5A, false);
+        bus.stop();
+
+        assert_eq!(
+            bus.decode(),
+            [
+                I2cEvent::Start,
+                I2cEvent::Address {
+                    address: 0x50,
+                    read: false,
+                    ack: true
+                },
+                I2cEvent::Data {
+                    value: 0x12,
+                    read: false,
+                    ack: true
+                },
+                I2cEvent::Start,
+                I2cEvent::Address {
+                    address: 0x50,
+                    read: true,
+                    ack: true
+                },
+                I2cEvent::Data {
+                    value: 0xA5,
+                    read: true,
+                    ack: true
+                },
+                I2cEvent::Data {
+                    value: 0x5A,
+                    read: true,
+                    ack: false
+                },
+                I2cEvent::Stop,
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_missing_acks_and_incomplete_bytes() {
+        let mut bus = Bus::new();
+        bus.start();
+        bus.byte(0x3C << 1, false);
+        bus.stop();
+        bus.start();
+        bus.bit(true);
+        bus.bit(false);
+        bus.bit(true);
+        bus.stop();
+
+        let mut bus_symbols = bus.decode().into_iter();
+        assert_eq!(
+            bus_symbols.nth(1),
+            Some(I2cEvent::Address {
+                address: 0x3C,
+                read: false,
+                ack: false
+            })
+        );
+        assert_eq!(bus_symbols.nth(2), Some(I2cEvent::Incomplete { bits: 3 }));
+
+        let nak = I2cSymbol {
+            start: Time(0.0),
+            end: Time(0.0),
+            event: I2cEvent::Address {
+                address: 0x3C,
+                read: false,
+                ack: false,
+            },
+        };
+        assert!(nak.is_error());
+        // the controller ending a read
+        let read_nak = I2cSymbol {
+            event: I2cEvent::Data {
+                value: 0,
+                read: true,
+                ack: false,
+            },
+            ..nak
+        };
+        assert!(!read_nak.is_error());
+    }
+
+    #[test]
+    fn ignores_clocks_outside_of_transfers() {
+        let mut bus = Bus::new();
+        bus.byte(0xFF, true);
+        assert_eq!(bus.decode(), []);
+    }
+}
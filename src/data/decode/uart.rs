@@ -0,0 +1,223 @@
+use super::{auto_threshold, logic_levels};
+use crate::data::units::{Time, Voltage};
+use snafu::{ensure, Snafu};
+
+/// Fewer samples per bit and the bit centers can't be hit reliably.
+pub const MIN_SAMPLES_PER_BIT: f64 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UartConfig {
+    pub baud_rate: u32,
+    /// Logic threshold, halfway between the lowest and highest voltage if `None`
+    pub threshold: Option<Voltage>,
+    /// Idle low instead of high, e.g. RS-232 levels or an inverted TTL signal
+    pub inverted: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UartFrame {
+    /// Start of the start bit
+    pub start: Time,
+    /// End of the stop bit
+    pub end: Time,
+    pub value: u8,
+    /// The stop bit wasn't at the idle level
+    pub framing_error: bool,
+}
+
+#[derive(Debug, Snafu)]
+pub enum UartDecodeError {
+    #[snafu(display("the baud rate must not be 0"))]
+    InvalidBaudRate,
+    #[snafu(display(
+        "only {samples_per_bit:.1} samples per bit, at least {MIN_SAMPLES_PER_BIT} are needed"
+    ))]
+    Undersampled { samples_per_bit: f64 },
+}
+
+/// Decodes 8N1 UART frames: a start bit, 8 data bits (LSB first), no parity and one stop bit.
+///
+/// `times` are the (evenly spaced) times of the `voltages`, see
+/// [`TimeBase::sample_times`](crate::data::head::TimeBase::sample_times). Frames cut off by the
+/// end of the screen are skipped.
+pub fn decode(
+    config: &UartConfig,
+    times: &[Time],
+    voltages: &[Voltage],
+) -> Result<Vec<UartFrame>, UartDecodeError> {
+    ensure!(config.baud_rate > 0, InvalidBaudRateSnafu);
+
+    let threshold = config.threshold.or_else(|| auto_threshold(voltages));
+    let (&[first, second, ..], Some(threshold)) = (times, threshold) else {
+        return Ok(Vec::new());
+    };
+
+    let sample_period = second - first;
+    let bit_time = Time(1.0 / config.baud_rate as f64);
+    let samples_per_bit = bit_time / sample_period;
+    ensure!(
+        samples_per_bit >= MIN_SAMPLES_PER_BIT,
+        UndersampledSnafu { samples_per_bit }
+    );
+
+    // `true` while idle (mark, a 1 bit), `false` for a space (the start bit, a 0 bit)
+    let idle = logic_levels(voltages, threshold)
+        .map(|high| high != config.inverted)
+        .collect::<Vec<_>>();
+    let index_at = |time: Time| ((time - first) / sample_period).round();
+    let level_at = |time: Time| {
+        let index = index_at(time);
+        (index >= 0.0).then(|| idle.get(index as usize).copied())?
+    };
+
+    let mut frames = Vec::new();
+    let mut i = 1;
+    while i < idle.len() {
+        if !idle[i - 1] || idle[i] {
+            i += 1;
+            continue;
+        }
+
+        // the edge is somewhere between the two samples
+        let start = times[i] - sample_period * 0.5;
+        let bit_center = |bit: usize| start + bit_time * (bit as f64 + 0.5);
+
+        // back to idle in the middle of the start bit: just a glitch
+        if level_at(bit_center(0)) != Some(false) {
+            i += 1;
+            continue;
+        }
+
+        let Some(bits) = (1..=9)
+            .map(|bit| level_at(bit_center(bit)))
+            .collect::<Option<Vec<_>>>()
+        else {
+            break;
+        };
+
+        let value = bits[..8]
+            .iter()
+            .enumerate()
+            .fold(0, |value, (bit, &set)| value | (set as u8) << bit);
+        frames.push(UartFrame {
+            start,
+            end: start + bit_time * 10.0,
+            value,
+            framing_error: !bits[8],
+        });
+
+        // the next start bit can begin right after the middle of the stop bit
+        i = index_at(bit_center(9)) as usize + 1;
+    }
+
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_PERIOD: f64 = 10e-6;
+
+    /// 8N1 frames at `baud_rate`, with some idle time before, between and after them.
+    fn signal(bytes: &[u8], baud_rate: u32, low: f64, high: f64) -> (Vec<Time>, Vec<Voltage>) {
+        let mut bits = vec![true; 5];
+        for &byte in bytes {
+            bits.push(false);
+            bits.extend((0..8).map(|bit| byte & (1 << bit) != 0));
+            bits.push(true);
+            bits.push(true);
+        }
+        bits.extend([true; 5]);
+
+        let bit_time = 1.0 / baud_rate as f64;
+        let count = (bits.len() as f64 * bit_time / SAMPLE_PERIOD) as usize;
+        let times = (0..count)
+            .map(|i| Time(i as f64 * SAMPLE_PERIOD - 1e-3))
+            .collect::<Vec<_>>();
+        let voltages = times
+            .iter()
+            .map(|time| {
+                let bit = ((time.0 + 1e-3) / bit_time) as usize;
+                Voltage(if bits[bit] { high } else { low })
+            })
+            .collect();
+
+        (times, voltages)
+    }
+
+    fn config(baud_rate: u32) -> UartConfig {
+        UartConfig {
+            baud_rate,
+            threshold: None,
+            inverted: false,
+        }
+    }
+
+    #[test]
+    fn decodes_8n1() {
+        let (times, voltages) = signal(b"Hi!\x00\xff", 9600, 0.0, 3.3);
+        let frames = decode(&config(9600), &times, &voltages).unwrap();
+
+        let values = frames.iter().map(|frame| frame.value).collect::<Vec<_>>();
+        assert_eq!(values, b"Hi!\x00\xff");
+        assert!(frames.iter().all(|frame| !frame.framing_error));
+
+        // the first start bit begins after 5 idle bits
+        let bit_time = 1.0 / 9600.0;
+        assert!((frames[0].start.0 - (5.0 * bit_time - 1e-3)).abs() < SAMPLE_PERIOD);
+        assert!(((frames[0].end - frames[0].start).0 - 10.0 * bit_time).abs() < 1e-9);
+    }
+
+    #[test]
+    fn decodes_inverted_idle_level() {
+        let (times, voltages) = signal(b"OK", 4800, 5.0, -5.0);
+        let frames = decode(
+            &UartConfig {
+                inverted: true,
+                ..config(4800)
+            },
+            &times,
+            &voltages,
+        )
+        .unwrap();
+
+        let values = frames.iter().map(|frame| frame.value).collect::<Vec<_>>();
+        assert_eq!(values, b"OK");
+    }
+
+    #[test]
+    fn detects_framing_errors() {
+        let (times, mut voltages) = signal(b"A", 9600, 0.0, 3.3);
+        // pull the stop bit low: start bit after 5 idle bits, stop bit is the 10th bit of the frame
+        let bit_time = 1.0 / 9600.0;
+        for (time, voltage) in times.iter().zip(&mut voltages) {
+            let bit = ((time.0 + 1e-3) / bit_time) as usize;
+            if bit == 5 + 9 {
+                *voltage = Voltage(0.0);
+            }
+        }
+
+        let frames = decode(&config(9600), &times, &voltages).unwrap();
+        assert_eq!(frames[0].value, b'A');
+        assert!(frames[0].framing_error);
+    }
+
+    #[test]
+    fn rejects_undersampled_signals() {
+        let (times, voltages) = signal(b"A", 9600, 0.0, 3.3);
+        assert!(matches!(
+            decode(&config(50_000), &times, &voltages),
+            Err(UartDecodeError::Undersampled { .. })
+        ));
+        assert!(matches!(
+            decode(&config(0), &times, &voltages),
+            Err(UartDecodeError::InvalidBaudRate)
+        ));
+    }
+
+    #[test]
+    fn empty_without_samples() {
+        assert_eq!(decode(&config(9600), &[], &[]).unwrap(), Vec::new());
+    }
+}
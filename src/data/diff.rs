@@ -0,0 +1,130 @@
+use super::head::{Channel, DataHeader};
+use std::fmt::Display;
+
+/// A setting that differs between two headers, both values formatted for display.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingChange {
+    pub setting: String,
+    pub old: String,
+    pub new: String,
+}
+
+/// The settings (time base, acquisition, channels, trigger) of `new` that differ from `old`.
+///
+/// What the signal does (run status, sampling rate, measured frequency) isn't compared, neither
+/// are channels only one of the headers has.
+pub fn setting_changes(old: &DataHeader, new: &DataHeader) -> Vec<SettingChange> {
+    let mut changes = Vec::new();
+    let mut compare = |setting: &str, old: &dyn Display, new: &dyn Display| {
+        let (old, new) = (old.to_string(), new.to_string());
+        if old != new {
+            changes.push(SettingChange {
+                setting: setting.to_owned(),
+                old,
+                new,
+            });
+        }
+    };
+
+    if old.time_base != new.time_base {
+        compare(
+            "Time base",
+            &format_args!("{}/div", old.time_base.scale),
+            &format_args!("{}/div", new.time_base.scale),
+        );
+        compare(
+            "Horizontal offset",
+            &old.time_base.h_offset_time(),
+            &new.time_base.h_offset_time(),
+        );
+    }
+    compare(
+        "Acquisition",
+        &old.sample.sample_type,
+        &new.sample.sample_type,
+    );
+    compare("Memory depth", &old.sample.depmem, &new.sample.depmem);
+
+    let shared_channels = old.channels.len().min(new.channels.len());
+    for ch in &Channel::ALL[..shared_channels] {
+        let (old, new) = (old.channel(*ch), new.channel(*ch));
+        if old == new {
+            continue;
+        }
+        compare(&format!("{ch} display"), &old.display, &new.display);
+        compare(&format!("{ch} coupling"), &old.coupling, &new.coupling);
+        compare(&format!("{ch} probe"), &old.probe, &new.probe);
+        compare(
+            &format!("{ch} scale"),
+            &format_args!("{}/div", old.scale_attenuated()),
+            &format_args!("{}/div", new.scale_attenuated()),
+        );
+        compare(
+            &format!("{ch} offset"),
+            &format_args!("{:.2} div", old.offset_grid_divs()),
+            &format_args!("{:.2} div", new.offset_grid_divs()),
+        );
+        if let (Some(old), Some(new)) = (old.bandwidth_limit, new.bandwidth_limit) {
+            compare(&format!("{ch} bandwidth limit"), &old, &new);
+        }
+    }
+
+    let (old_trigger, new_trigger) = (&old.trigger, &new.trigger);
+    if old_trigger != new_trigger {
+        let (old_items, new_items) = (&old_trigger.items, &new_trigger.items);
+        compare("Trigger type", &old_trigger.r#type, &new_trigger.r#type);
+        compare("Trigger source", &old_items.channel, &new_items.channel);
+        compare("Trigger edge", &old_items.edge, &new_items.edge);
+        compare("Trigger level", &old_items.level, &new_items.level);
+        compare("Trigger coupling", &old_items.coupling, &new_items.coupling);
+        compare("Trigger sweep", &old_items.sweep, &new_items.sweep);
+        // only known once set from here, see `Trigger::holdoff`
+        if let (Some(old), Some(new)) = (old_trigger.holdoff, new_trigger.holdoff) {
+            compare("Trigger holdoff", &old, &new);
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEAD: &str = include_str!("../../tests/fixtures/hds2102s_head.json");
+
+    #[test]
+    fn lists_changed_settings_only() {
+        let old: DataHeader = serde_json::from_str(HEAD).unwrap();
+        assert!(setting_changes(&old, &old).is_empty());
+
+        // the signal changing isn't a setting change
+        let new = HEAD
+            .replace(r#""RUNSTATUS":"TRIG""#, r#""RUNSTATUS":"STOP""#)
+            .replace(r#""FREQUENCE":1000.0"#, r#""FREQUENCE":999.0"#);
+        let new: DataHeader = serde_json::from_str(&new).unwrap();
+        assert!(setting_changes(&old, &new).is_empty());
+
+        let new = HEAD
+            .replace(r#""SCALE":"500us""#, r#""SCALE":"1.0ms""#)
+            .replace(r#""COUPLING":"AC""#, r#""COUPLING":"DC""#)
+            .replace(r#""Sweep":"AUTO""#, r#""Sweep":"NORMal""#);
+        let new: DataHeader = serde_json::from_str(&new).unwrap();
+        let changes = setting_changes(&old, &new);
+        assert_eq!(
+            changes
+                .iter()
+                .map(|change| change.setting.as_str())
+                .collect::<Vec<_>>(),
+            ["Time base", "CH2 coupling", "Trigger sweep"]
+        );
+        assert_eq!(
+            changes[0],
+            SettingChange {
+                setting: "Time base".to_owned(),
+                old: "500µs/div".to_owned(),
+                new: "1.0ms/div".to_owned(),
+            }
+        );
+    }
+}
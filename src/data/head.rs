@@ -1,9 +1,19 @@
+use super::screen::ScreenGeometry;
 use super::units::{ProbeAttenuation, SamplingRate, Time, Voltage};
-use crate::consts::GRID_DIV_SIZE;
-use serde::Deserialize;
-use strum::{Display, EnumString};
+use crate::consts::{GRID_DIV_SIZE, MAX_CHANNELS, SAMPLES};
+use arrayvec::ArrayVec;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_with::{serde_as, DisplayFromStr, PickFirst};
+use std::ops::Deref;
+use strum::{Display, EnumIter, EnumString};
 
-#[derive(Debug, Deserialize, Clone, Copy)]
+// Header quirks of other models (e.g. the HDS272S) are tolerated by:
+// - accepting numbers that arrive as strings (`PickFirst<(_, DisplayFromStr)>`)
+// - trimming whitespace around unit values (see `units`)
+// Unknown fields are ignored. There's no captured HDS272S header yet, so field names are only
+// those seen on the HDS2102S.
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq)]
 #[serde(rename_all(deserialize = "UPPERCASE"))]
 pub struct DataHeader {
     // pub idn: &'a str,
@@ -12,7 +22,7 @@ pub struct DataHeader {
     pub time_base: TimeBase,
     pub sample: Sample,
     #[serde(rename = "CHANNEL")]
-    pub channels: [ChannelInfo; 2],
+    pub channels: Channels,
     pub datatype: DataType,
     #[serde(rename = "RUNSTATUS")]
     pub run_status: RunStatus,
@@ -21,56 +31,103 @@ pub struct DataHeader {
 }
 
 impl DataHeader {
+    /// See [`Channels::get`].
     pub fn channel(&self, ch: Channel) -> &ChannelInfo {
-        &self.channels[ch as usize]
+        self.channels.get(ch)
     }
 
     pub fn channel_enabled(&self, ch: Channel) -> bool {
         self.channel(ch).display == ChannelDisplay::On
     }
-}
 
-impl Default for DataHeader {
-    fn default() -> Self {
-        Self {
-            time_base: Default::default(),
-            sample: Default::default(),
-            channels: [
-                ChannelInfo::default(),
-                ChannelInfo {
-                    channel: Channel::Ch2,
-                    ..Default::default()
-                },
-            ],
-            datatype: Default::default(),
-            run_status: Default::default(),
-            trigger: Default::default(),
+    /// Which channels are displayed, indexed by [`Channel`]. Channels the model doesn't have are
+    /// off.
+    pub fn channels_enabled(&self) -> [bool; MAX_CHANNELS] {
+        Channel::ALL.map(|ch| self.channel_enabled(ch))
+    }
+
+    /// Number of screen columns, the width of the model's [`ScreenGeometry`], falling back to
+    /// [`SAMPLES`].
+    ///
+    /// This isn't `data_len`, which counts bytes: screen data that is longer than the screen
+    /// contains two bytes per column.
+    pub fn screen_samples(&self) -> usize {
+        let width = self.time_base.screen.width();
+        if width >= 1.0 {
+            width as usize
+        } else {
+            SAMPLES
         }
     }
+
+    /// See [`TimeBase::sample_times`].
+    pub fn sample_times(&self, count: usize) -> impl Iterator<Item = Time> {
+        self.time_base.sample_times(count)
+    }
 }
 
-#[derive(Debug, Deserialize, Default, Clone, Copy)]
+#[serde_as]
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq)]
 #[serde(rename_all(deserialize = "UPPERCASE"))]
 pub struct TimeBase {
     pub scale: Time,
     /// Horizontal offset, in samples
     #[serde(rename = "HOFFSET")]
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
     pub h_offset: i64,
+    /// Not part of the header. Filled in by the device loop from the model's `*IDN?`, the default
+    /// before that.
+    #[serde(skip)]
+    pub screen: ScreenGeometry,
 }
 
 impl TimeBase {
     pub fn h_offset_grid_divs(&self) -> f64 {
-        self.h_offset as f64 / GRID_DIV_SIZE
+        self.h_offset as f64 / self.screen.samples_per_div
+    }
+
+    /// The horizontal offset as the time of the screen center relative to the trigger point.
+    pub fn h_offset_time(&self) -> Time {
+        Time(self.h_offset_grid_divs() * self.scale.0)
+    }
+
+    /// Converts a time relative to the trigger point into grid divs at this scale, rounded to
+    /// whole samples so it converts back to the same time.
+    pub fn time_to_grid_divs(&self, time: Time) -> f64 {
+        self.snap_grid_divs(time.0 / self.scale.0)
+    }
+
+    /// Rounds grid divs to the nearest whole sample.
+    pub fn snap_grid_divs(&self, divs: f64) -> f64 {
+        let samples_per_div = self.screen.samples_per_div;
+        (divs * samples_per_div).round() / samples_per_div
+    }
+
+    /// The time of each of `count` samples spanning the screen width, relative to the trigger
+    /// point.
+    ///
+    /// The sample just left of the screen center is at the trigger point when there's no
+    /// horizontal offset (see [`ScreenGeometry::center`]).
+    pub fn sample_times(&self, count: usize) -> impl Iterator<Item = Time> {
+        let sample_period = self.scale.0 * self.screen.horizontal_divs / count as f64;
+        let center = count as f64 / 2.0 - 1.0;
+        let offset = self.h_offset_grid_divs() * self.scale.0;
+
+        (0..count).map(move |i| Time((i as f64 - center) * sample_period + offset))
     }
 }
 
-#[derive(Debug, Deserialize, Default, Clone, Copy)]
+#[serde_as]
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq)]
 #[serde(rename_all(deserialize = "UPPERCASE"))]
 pub struct Sample {
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
     pub fullscreen: i32,
     #[serde(rename = "SLOWMOVE")]
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
     pub slow_move: i32,
     #[serde(rename = "DATALEN")]
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
     pub data_len: i32,
     #[serde(rename = "SAMPLERATE")]
     pub sampling_rate: SamplingRate,
@@ -79,7 +136,77 @@ pub struct Sample {
     pub depmem: MemoryDepth,
 }
 
-#[derive(Debug, Deserialize, Default, Clone, Copy)]
+impl Sample {
+    /// Time between two samples, `None` if the device reports no sampling rate.
+    pub fn sample_interval(&self) -> Option<Time> {
+        (self.sampling_rate.0 > 0.0).then(|| Time(self.sampling_rate.0.recip()))
+    }
+
+    /// Time span of the record, `data_len` samples at [`Self::sample_interval`].
+    pub fn record_time(&self) -> Option<Time> {
+        let samples = u32::try_from(self.data_len).ok().filter(|&n| n > 0)?;
+        self.sample_interval()
+            .map(|interval| interval * samples as f64)
+    }
+}
+
+/// The channels of a header, as many as the model has. Stays `Copy` (like the rest of the header)
+/// by keeping room for [`MAX_CHANNELS`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Channels {
+    infos: [ChannelInfo; MAX_CHANNELS],
+    count: usize,
+}
+
+impl Channels {
+    /// Channels beyond the model's count are off, so callers iterating [`Channel::ALL`] don't
+    /// need to check the count.
+    pub fn get(&self, ch: Channel) -> &ChannelInfo {
+        &self.infos[ch as usize]
+    }
+
+    fn new(channels: &[ChannelInfo]) -> Self {
+        let mut infos = Channel::ALL.map(|channel| ChannelInfo {
+            channel,
+            display: ChannelDisplay::Off,
+            ..Default::default()
+        });
+        infos[..channels.len()].copy_from_slice(channels);
+        Self {
+            infos,
+            count: channels.len(),
+        }
+    }
+}
+
+impl Deref for Channels {
+    type Target = [ChannelInfo];
+
+    /// The model's channels, in order.
+    fn deref(&self) -> &[ChannelInfo] {
+        &self.infos[..self.count]
+    }
+}
+
+/// Two displayed channels, like the HDS200 series.
+impl Default for Channels {
+    fn default() -> Self {
+        Self::new(&[Channel::Ch1, Channel::Ch2].map(|channel| ChannelInfo {
+            channel,
+            ..Default::default()
+        }))
+    }
+}
+
+impl<'de> Deserialize<'de> for Channels {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let channels = ArrayVec::<ChannelInfo, MAX_CHANNELS>::deserialize(deserializer)?;
+        Ok(Self::new(&channels))
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq)]
 #[serde(rename_all(deserialize = "UPPERCASE"))]
 pub struct ChannelInfo {
     #[serde(rename = "NAME")]
@@ -90,9 +217,14 @@ pub struct ChannelInfo {
     /// Channel scale, in volts per grid square (for some reason), unattenuated.
     pub scale: Voltage,
     /// Offset in units
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
     pub offset: i64,
     #[serde(rename = "FREQUENCE")]
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
     pub frequency: f64,
+    /// 20 MHz bandwidth limit filter (ON/OFF), `None` if the firmware doesn't report it
+    #[serde(default, alias = "BWLIMIT", alias = "BANDLIMIT")]
+    pub bandwidth_limit: Option<ChannelDisplay>,
 }
 
 impl ChannelInfo {
@@ -102,31 +234,73 @@ impl ChannelInfo {
 
     /// Calculate the scale in volts per grid square, with probe attenuation applied.
     pub fn scale_attenuated(&self) -> Voltage {
-        Voltage(self.scale.0 * self.probe.0 as f64)
+        self.scale * self.probe.0 as f64
     }
 
     pub fn offset_grid_divs(&self) -> f64 {
         self.offset as f64 / GRID_DIV_SIZE
     }
+
+    /// Converts a raw sample to the voltage at the probe tip.
+    pub fn sample_to_voltage(&self, sample: i8) -> Voltage {
+        self.units_to_voltage(sample as f64)
+    }
+
+    /// Like [`Self::sample_to_voltage`], for fractional positions (e.g. averaged samples or a
+    /// point on the plot).
+    pub fn units_to_voltage(&self, units: f64) -> Voltage {
+        Voltage((units - self.offset as f64) * self.scale_per_unit())
+    }
+
+    /// The inverse of [`Self::units_to_voltage`], i.e. where `voltage` ends up on the screen.
+    pub fn voltage_to_units(&self, voltage: Voltage) -> f64 {
+        voltage.0 / self.scale_per_unit() + self.offset as f64
+    }
+
+    /// Decodes raw screen data to voltages, one per screen column.
+    ///
+    /// Data longer than `screen_samples` (see [`DataHeader::screen_samples`]) is two-byte packed,
+    /// incl. the min/max pairs of peak detect, and is reduced to the midpoint of each pair.
+    pub fn voltages<'a>(
+        &'a self,
+        data: &'a [u8],
+        screen_samples: usize,
+    ) -> impl Iterator<Item = Voltage> + 'a {
+        let packed = data.len() > screen_samples;
+        let chunk_size = if packed { 2 } else { 1 };
+
+        data.chunks_exact(chunk_size).map(move |chunk| {
+            if packed {
+                self.units_to_voltage((chunk[0] as i8 as f64 + chunk[1] as i8 as f64) / 2.0)
+            } else {
+                self.sample_to_voltage(chunk[0] as i8)
+            }
+        })
+    }
 }
 
-#[derive(Debug, Deserialize, Default, Clone, Copy)]
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all(deserialize = "UPPERCASE"))]
 pub enum DataType {
     #[default]
     Screen,
 }
 
-#[derive(Debug, Deserialize, Default, Clone, Copy)]
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq)]
 #[serde(rename_all(deserialize = "PascalCase"))]
 pub struct Trigger {
     pub mode: TriggerMode,
     pub r#type: TriggerType,
     pub items: TriggerItems,
+    /// Not part of the header. Filled in by the device loop from the `:TRIGger:HOLDoff?` read-back
+    /// once it has been set, `None` before that.
+    #[serde(skip)]
+    pub holdoff: Option<Time>,
 }
 
-#[derive(Debug, Deserialize, Default, Clone, Copy)]
-#[serde(rename_all(deserialize = "PascalCase"))]
+// In non-edge trigger types the edge specific items may be missing
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all(deserialize = "PascalCase"), default)]
 pub struct TriggerItems {
     pub channel: Channel,
     pub level: Voltage,
@@ -135,7 +309,7 @@ pub struct TriggerItems {
     pub sweep: TriggerSweep,
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Display)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, Display)]
 pub enum TriggerEdge {
     #[default]
     #[serde(rename = "RISE")]
@@ -153,10 +327,38 @@ pub enum TriggerMode {
     Single,
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+/// Serialized as in the header, `Display`/`FromStr` use the SCPI token.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Display, EnumString, EnumIter,
+)]
 pub enum TriggerType {
     #[default]
+    #[serde(alias = "EDGE")]
+    #[strum(serialize = "EDGE")]
     Edge,
+    #[serde(alias = "PULSe")]
+    #[strum(serialize = "PULSe")]
+    Pulse,
+    // NOTE: slope and video are only here so headers of devices set to them still parse,
+    // they can't be configured yet
+    #[serde(alias = "SLOPe")]
+    #[strum(serialize = "SLOPe")]
+    Slope,
+    #[serde(alias = "VIDeo")]
+    #[strum(serialize = "VIDeo")]
+    Video,
+}
+
+/// Which pulse widths trigger in pulse trigger mode, compared to the configured width.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString, EnumIter)]
+pub enum PulseCondition {
+    #[strum(serialize = ">")]
+    Longer,
+    #[strum(serialize = "<")]
+    Shorter,
+    #[default]
+    #[strum(serialize = "=")]
+    Equal,
 }
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Display)]
@@ -179,14 +381,23 @@ pub enum MemoryDepth {
     EightK,
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Display)]
-#[serde(rename_all(deserialize = "UPPERCASE"))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, Display)]
+#[serde(rename_all = "UPPERCASE")]
 pub enum Channel {
     #[default]
     #[strum(serialize = "CH1")]
     Ch1 = 0,
     #[strum(serialize = "CH2")]
     Ch2 = 1,
+    #[strum(serialize = "CH3")]
+    Ch3 = 2,
+    #[strum(serialize = "CH4")]
+    Ch4 = 3,
+}
+
+impl Channel {
+    /// Indexed by the channel, see [`DataHeader::channels_enabled`].
+    pub const ALL: [Channel; MAX_CHANNELS] = [Self::Ch1, Self::Ch2, Self::Ch3, Self::Ch4];
 }
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Display, EnumString)]
@@ -215,8 +426,8 @@ impl From<bool> for ChannelDisplay {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Display)]
-#[serde(rename_all(deserialize = "UPPERCASE"))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, Display)]
+#[serde(rename_all = "UPPERCASE")]
 pub enum ChannelCoupling {
     #[default]
     #[strum(serialize = "DC")]
@@ -227,8 +438,8 @@ pub enum ChannelCoupling {
     Gnd,
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Display)]
-#[serde(rename_all(deserialize = "UPPERCASE"))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, Display)]
+#[serde(rename_all = "UPPERCASE")]
 pub enum TriggerCoupling {
     #[default]
     #[strum(serialize = "DC")]
@@ -237,7 +448,7 @@ pub enum TriggerCoupling {
     Ac,
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Display)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, Display)]
 pub enum TriggerSweep {
     #[default]
     #[serde(rename = "AUTO")]
@@ -272,3 +483,66 @@ pub enum RunStatus {
     #[serde(rename = "TRIG")]
     Triggering,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_times_follow_time_base() {
+        // 500us/div, 300 samples over 12 divs: 20us per sample
+        let time_base = TimeBase {
+            scale: Time(500e-6),
+            h_offset: 0,
+            ..Default::default()
+        };
+        let times = time_base.sample_times(300).collect::<Vec<_>>();
+
+        assert_eq!(times.len(), 300);
+        assert!((times[0].0 - -149.0 * 20e-6).abs() < 1e-12);
+        assert_eq!(times[149], Time(0.0));
+        assert!((times[299].0 - 150.0 * 20e-6).abs() < 1e-12);
+
+        // one div (25 samples) of horizontal offset moves everything by one scale
+        let shifted = TimeBase {
+            h_offset: 25,
+            ..time_base
+        };
+        let shifted_times = shifted.sample_times(300).collect::<Vec<_>>();
+        assert!((shifted_times[149].0 - 500e-6).abs() < 1e-12);
+    }
+
+    #[test]
+    fn settings_serialize_like_the_header() {
+        let json = serde_json::to_string(&(ChannelCoupling::Gnd, TriggerCoupling::Ac)).unwrap();
+        assert_eq!(json, r#"["GND","AC"]"#);
+        let parsed: (ChannelCoupling, TriggerCoupling) = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, (ChannelCoupling::Gnd, TriggerCoupling::Ac));
+    }
+
+    #[test]
+    fn h_offset_time_round_trips() {
+        for scale in [Time(2e-9), Time(500e-6), Time(20.0)] {
+            for h_offset in [-8000, -13, -1, 0, 1, 7, 25, 7999] {
+                let time_base = TimeBase {
+                    scale,
+                    h_offset,
+                    ..Default::default()
+                };
+                let divs = time_base.time_to_grid_divs(time_base.h_offset_time());
+                assert_eq!(divs, time_base.h_offset_grid_divs());
+            }
+        }
+    }
+
+    #[test]
+    fn h_offset_snaps_to_whole_samples() {
+        let time_base = TimeBase {
+            scale: Time(500e-6),
+            ..Default::default()
+        };
+        assert_eq!(time_base.snap_grid_divs(1.013), 1.0);
+        assert_eq!(time_base.snap_grid_divs(1.03), 1.04);
+        assert_eq!(time_base.snap_grid_divs(-20.0), -20.0);
+    }
+}
@@ -0,0 +1,30 @@
+use super::units::{Frequency, Voltage};
+use std::ops::RangeInclusive;
+
+/// Model-dependent value ranges accepted by the device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceLimits {
+    /// Number of function generator outputs, at least 1
+    pub awg_channels: usize,
+    pub awg_frequency: RangeInclusive<Frequency>,
+    /// Peak-to-peak
+    pub awg_amplitude: RangeInclusive<Voltage>,
+    pub awg_offset: RangeInclusive<Voltage>,
+}
+
+impl DeviceLimits {
+    /// HDS200 series (HDS242S/HDS272S/HDS2102S), taken from the datasheet.
+    pub const HDS200S: Self = Self {
+        awg_channels: 1,
+        awg_frequency: Frequency(0.1)..=Frequency(25e6),
+        awg_amplitude: Voltage(0.01)..=Voltage(5.0),
+        awg_offset: Voltage(-2.5)..=Voltage(2.5),
+    };
+}
+
+impl Default for DeviceLimits {
+    /// Those of the default [`ModelProfile`](super::model::ModelProfile).
+    fn default() -> Self {
+        super::model::ModelProfile::default().limits
+    }
+}
@@ -1,11 +1,14 @@
-use super::head::Channel;
+use super::{head::Channel, units::Voltage};
 use arrayvec::ArrayVec;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use strum::{Display, EnumCount, EnumIter, IntoEnumIterator};
 
 mod data;
-use crate::scaled_number::ScaledNumber;
+use crate::scaled_number::{DynamicDecimals, NumberFormat, ScaledNumber, DEFAULT_PRECISION};
 pub use data::*;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq)]
 pub struct Measurements {
     pub peak_to_peak: PeakToPeak,
     pub amplitude: Amplitude,
@@ -17,10 +20,22 @@ pub struct Measurements {
     pub rms: Rms,
     // frequency is derived from period
     // trough width is derived from peak width
+    // duty cycle is derived from period and peak width
 }
 
 impl Measurements {
-    pub const MEASUREMENT_COUNT: usize = 9;
+    pub const MEASUREMENT_COUNT: usize = MeasurementKind::COUNT;
+    pub const DUTY_CYCLE_DISPLAY_PREFIX: &'static str = "Duty";
+
+    /// Collects the measurements from a set of responses. Unrecognized or malformed responses are
+    /// skipped, leaving the respective measurement empty.
+    pub fn parse_all<'a>(responses: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut measurements = Self::default();
+        for response in responses {
+            measurements.with_parsed(response);
+        }
+        measurements
+    }
 
     pub fn with_parsed(&mut self, buf: &str) {
         if let Ok(peak_to_peak) = buf.parse() {
@@ -47,51 +62,355 @@ impl Measurements {
     }
 
     pub fn for_display(&self) -> ArrayVec<String, { Self::MEASUREMENT_COUNT }> {
-        let mut out = ArrayVec::new();
-        out.push(self.peak_to_peak.to_string());
-        out.push(self.amplitude.to_string());
-        out.push(Frequency(self.period.0.map(|s| ScaledNumber(s.0.powi(-1)))).to_string());
-        out.push(self.period.to_string());
-
-        out.push(self.rise_time.to_string());
-        out.push(self.peak_width.to_string());
-
-        out.push(
-            TroughWidth(
-                self.period
-                    .0
-                    .zip(self.peak_width.0)
-                    .map(|(period, peak)| ScaledNumber(period.0 - peak.0)),
-            )
-            .to_string(),
+        self.for_display_with(
+            MeasurementKind::iter(),
+            NumberFormat::SiPrefix,
+            DEFAULT_PRECISION,
+            |voltage| voltage.to_string(),
+        )
+    }
+
+    /// Like [`Self::for_display`], but only renders the given `kinds` in `format` with `precision`
+    /// (see [`ScaledNumber::fmt_with`]), and formats the voltage level measurements
+    /// (peak-to-peak, amplitude, RMS) with `fmt_voltage`, e.g. to show them in dBV.
+    pub fn for_display_with(
+        &self,
+        kinds: impl IntoIterator<Item = MeasurementKind>,
+        format: NumberFormat,
+        precision: usize,
+        fmt_voltage: impl Fn(Voltage) -> String,
+    ) -> ArrayVec<String, { Self::MEASUREMENT_COUNT }> {
+        let voltage_for_display = |prefix: &str, value: Option<ScaledNumber>| {
+            let value = value.map(|v| fmt_voltage(Voltage(v.0)));
+            format!("{prefix}={}", value.unwrap_or_default())
+        };
+        let for_display = |prefix: &str, unit: &str, value: Option<ScaledNumber>| match value {
+            Some(value) => format!("{prefix}={:.precision$}{unit}", value.display_with(format)),
+            None => format!("{prefix}="),
+        };
+
+        kinds
+            .into_iter()
+            .map(|kind| {
+                let value = self.value(kind);
+                match kind {
+                    MeasurementKind::PeakToPeak => {
+                        voltage_for_display(PeakToPeak::DISPLAY_PREFIX, value)
+                    }
+                    MeasurementKind::Amplitude => {
+                        voltage_for_display(Amplitude::DISPLAY_PREFIX, value)
+                    }
+                    MeasurementKind::Rms => voltage_for_display(Rms::DISPLAY_PREFIX, value),
+                    MeasurementKind::Frequency => {
+                        for_display(Frequency::DISPLAY_PREFIX, kind.unit(), value)
+                    }
+                    MeasurementKind::Period => {
+                        for_display(Period::DISPLAY_PREFIX, kind.unit(), value)
+                    }
+                    MeasurementKind::RiseTime => {
+                        for_display(RiseTime::DISPLAY_PREFIX, kind.unit(), value)
+                    }
+                    MeasurementKind::PeakWidth => {
+                        for_display(PeakWidth::DISPLAY_PREFIX, kind.unit(), value)
+                    }
+                    MeasurementKind::TroughWidth => {
+                        for_display(TroughWidth::DISPLAY_PREFIX, kind.unit(), value)
+                    }
+                    // a percentage, SI prefixes would only get in the way
+                    MeasurementKind::DutyCycle => match value {
+                        Some(value) => format!(
+                            "{}={}{}",
+                            Self::DUTY_CYCLE_DISPLAY_PREFIX,
+                            DynamicDecimals(value.0, precision),
+                            kind.unit()
+                        ),
+                        None => format!("{}=", Self::DUTY_CYCLE_DISPLAY_PREFIX),
+                    },
+                    MeasurementKind::Average => {
+                        for_display(Average::DISPLAY_PREFIX, kind.unit(), value)
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// A Markdown table with a row per measurement in `kinds` and, per channel, the value as shown
+    /// (see [`Self::for_display_with`]) and as a plain number in the base unit. Unavailable values
+    /// are left blank.
+    pub fn markdown_table(
+        channels: &[(Channel, &Measurements)],
+        kinds: &[MeasurementKind],
+        format: NumberFormat,
+        precision: usize,
+        fmt_voltage: impl Fn(Voltage) -> String,
+    ) -> String {
+        let mut table = String::from("| Measurement | Unit |");
+        for (channel, _) in channels {
+            table += &format!(" {channel} | {channel} (raw) |");
+        }
+        table += "\n|---|---|";
+        table += &"---|---|".repeat(channels.len());
+
+        let displayed = channels
+            .iter()
+            .map(|(_, measurements)| {
+                measurements.for_display_with(
+                    kinds.iter().copied(),
+                    format,
+                    precision,
+                    &fmt_voltage,
+                )
+            })
+            .collect::<Vec<_>>();
+        for (row, kind) in kinds.iter().enumerate() {
+            table += &format!("\n| {kind} | {} |", kind.unit());
+            for ((_, measurements), displayed) in channels.iter().zip(&displayed) {
+                // without the prefix, the row already names the measurement
+                let shown = displayed[row]
+                    .split_once('=')
+                    .map_or("", |(_, value)| value);
+                let raw = measurements
+                    .value(*kind)
+                    .map(|value| value.display_with(NumberFormat::Plain).to_string())
+                    .unwrap_or_default();
+                table += &format!(" {shown} | {raw} |");
+            }
+        }
+        table += "\n";
+        table
+    }
+
+    /// The value of a single measurement, incl. the derived ones. `None` if unavailable.
+    pub fn value(&self, kind: MeasurementKind) -> Option<ScaledNumber> {
+        match kind {
+            MeasurementKind::PeakToPeak => self.peak_to_peak.0,
+            MeasurementKind::Amplitude => self.amplitude.0,
+            MeasurementKind::Frequency => self.period.0.map(|s| ScaledNumber(s.0.powi(-1))),
+            MeasurementKind::Period => self.period.0,
+            MeasurementKind::RiseTime => self.rise_time.0,
+            MeasurementKind::PeakWidth => self.peak_width.0,
+            MeasurementKind::TroughWidth => self
+                .period
+                .0
+                .zip(self.peak_width.0)
+                .map(|(period, peak)| ScaledNumber(period.0 - peak.0)),
+            // in percent, empty instead of NaN/infinite for a zero period
+            MeasurementKind::DutyCycle => self
+                .period
+                .0
+                .zip(self.peak_width.0)
+                .filter(|(period, _)| period.0 > 0.0)
+                .map(|(period, peak)| ScaledNumber(peak.0 / period.0 * 100.0)),
+            MeasurementKind::Rms => self.rms.0,
+            MeasurementKind::Average => self.average.0,
+        }
+    }
+
+    /// The queries needed for the given `kinds`, without duplicates.
+    pub fn measurement_commands(ch: Channel, kinds: &BTreeSet<MeasurementKind>) -> Vec<String> {
+        let mut queries = kinds
+            .iter()
+            .flat_map(|kind| kind.queries())
+            .collect::<Vec<_>>();
+        queries.sort_unstable();
+        queries.dedup();
+
+        queries
+            .into_iter()
+            // the SCPI short form, accepted however a firmware spells out the long one
+            // (`:MEASurement`, `:MEASuremen`), so there's no dialect to detect
+            .map(|query| format!(":MEAS:{ch}:{query}?"))
+            .collect()
+    }
+}
+
+/// A measurement shown to the user, in display order.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    EnumIter,
+    EnumCount,
+    Display,
+    Serialize,
+    Deserialize,
+)]
+pub enum MeasurementKind {
+    #[strum(to_string = "Peak-to-peak")]
+    PeakToPeak,
+    Amplitude,
+    Frequency,
+    Period,
+    #[strum(to_string = "Rise time")]
+    RiseTime,
+    #[strum(to_string = "Peak width")]
+    PeakWidth,
+    #[strum(to_string = "Trough width")]
+    TroughWidth,
+    #[strum(to_string = "Duty cycle")]
+    DutyCycle,
+    #[strum(to_string = "RMS")]
+    Rms,
+    Average,
+}
+
+impl MeasurementKind {
+    pub fn all() -> BTreeSet<Self> {
+        Self::iter().collect()
+    }
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            MeasurementKind::PeakToPeak => PeakToPeak::UNIT,
+            MeasurementKind::Amplitude => Amplitude::UNIT,
+            MeasurementKind::Frequency => Frequency::UNIT,
+            MeasurementKind::Period => Period::UNIT,
+            MeasurementKind::RiseTime => RiseTime::UNIT,
+            MeasurementKind::PeakWidth => PeakWidth::UNIT,
+            MeasurementKind::TroughWidth => TroughWidth::UNIT,
+            MeasurementKind::DutyCycle => "%",
+            MeasurementKind::Rms => Rms::UNIT,
+            MeasurementKind::Average => Average::UNIT,
+        }
+    }
+
+    /// The `:MEAS:CHx:<query>?` queries this measurement is read or derived from.
+    fn queries(self) -> &'static [&'static str] {
+        match self {
+            MeasurementKind::PeakToPeak => &["PKPK"],
+            MeasurementKind::Amplitude => &["VAMP"],
+            // frequency is derived from period
+            MeasurementKind::Frequency | MeasurementKind::Period => &["PER"],
+            MeasurementKind::RiseTime => &["RT"],
+            MeasurementKind::PeakWidth => &["PWID"],
+            // trough width and duty cycle are derived from period and peak width
+            MeasurementKind::TroughWidth | MeasurementKind::DutyCycle => &["PER", "PWID"],
+            MeasurementKind::Rms => &["SQUA"],
+            MeasurementKind::Average => &["AVER"],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_documented_responses() {
+        assert_eq!(
+            "Vpp=3.720V\n".parse(),
+            Ok(PeakToPeak(Some(ScaledNumber(3.72))))
+        );
+        assert_eq!(
+            "F=1.000kHz\n".parse(),
+            Ok(Frequency(Some(ScaledNumber(1000.0))))
         );
+        let rise_time = "RT=32.00ns\n".parse::<RiseTime>().unwrap().0.unwrap();
+        assert!((rise_time.0 - 32e-9).abs() < 1e-15);
+    }
 
-        out.push(self.rms.to_string());
-        out.push(self.average.to_string());
-        out
-    }
-
-    pub fn channel_to_measurement_commands(ch: Channel) -> &'static [&'static [u8]] {
-        if ch == Channel::Ch1 {
-            &[
-                b":MEAS:CH1:PKPK?",
-                b":MEAS:CH1:VAMP?",
-                b":MEAS:CH1:AVER?",
-                b":MEAS:CH1:PER?",
-                b":MEAS:CH1:RT?",
-                b":MEAS:CH1:PWID?",
-                b":MEAS:CH1:SQUA?",
-            ]
-        } else {
-            &[
-                b":MEAS:CH2:PKPK?",
-                b":MEAS:CH2:VAMP?",
-                b":MEAS:CH2:AVER?",
-                b":MEAS:CH2:PER?",
-                b":MEAS:CH2:RT?",
-                b":MEAS:CH2:PWID?",
-                b":MEAS:CH2:SQUA?",
-            ]
+    #[test]
+    fn parses_unavailable_measurements_as_empty() {
+        assert_eq!("Vpp=?\n".parse(), Ok(PeakToPeak(None)));
+        assert_eq!("RT=OFF\n".parse(), Ok(RiseTime(None)));
+    }
+
+    #[test]
+    fn rejects_truncated_or_garbled_responses() {
+        assert!("Vpp=3.7".parse::<PeakToPeak>().is_err());
+        assert!("RT=32.00n".parse::<RiseTime>().is_err());
+        assert!("Vpp=3.x2V\n".parse::<PeakToPeak>().is_err());
+        assert!("Vpp=3.7\u{fffd}V\n".parse::<PeakToPeak>().is_err());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for response in [
+            "Vpp=3.720V",
+            "F=1.000kHz",
+            "RT=32.00ns",
+            "T=1.000ms",
+            "Vpp=",
+        ] {
+            let displayed = match response.split('=').next() {
+                Some("Vpp") => response.parse::<PeakToPeak>().unwrap().to_string(),
+                Some("F") => response.parse::<Frequency>().unwrap().to_string(),
+                Some("RT") => response.parse::<RiseTime>().unwrap().to_string(),
+                Some("T") => response.parse::<Period>().unwrap().to_string(),
+                _ => unreachable!(),
+            };
+            assert_eq!(displayed, response);
         }
     }
+
+    #[test]
+    fn derives_values() {
+        let measurements = Measurements::parse_all(["T=1.000ms\n", "PW=250.0us\n"]);
+
+        let frequency = measurements.value(MeasurementKind::Frequency).unwrap();
+        assert!((frequency.0 - 1e3).abs() < 1e-9);
+        let trough_width = measurements.value(MeasurementKind::TroughWidth).unwrap();
+        assert!((trough_width.0 - 750e-6).abs() < 1e-12);
+        let duty_cycle = measurements.value(MeasurementKind::DutyCycle).unwrap();
+        assert!((duty_cycle.0 - 25.0).abs() < 1e-9);
+        assert_eq!(measurements.value(MeasurementKind::Rms), None);
+    }
+
+    #[test]
+    fn builds_markdown_tables() {
+        let ch1 = Measurements::parse_all(["Vpp=3.720V\n", "T=1.000ms\n"]);
+        let ch2 = Measurements::default();
+        let table = Measurements::markdown_table(
+            &[(Channel::Ch1, &ch1), (Channel::Ch2, &ch2)],
+            &[MeasurementKind::PeakToPeak, MeasurementKind::Frequency],
+            NumberFormat::SiPrefix,
+            DEFAULT_PRECISION,
+            |voltage| voltage.to_string(),
+        );
+
+        assert_eq!(
+            table,
+            "| Measurement | Unit | CH1 | CH1 (raw) | CH2 | CH2 (raw) |\n\
+             |---|---|---|---|---|---|\n\
+             | Peak-to-peak | V | 3.720V | 3.72 |  |  |\n\
+             | Frequency | Hz | 1.000kHz | 1000 |  |  |\n"
+        );
+    }
+
+    #[test]
+    fn shows_duty_cycle_in_percent() {
+        let duty_cycle = |responses: &[&str]| {
+            Measurements::parse_all(responses.iter().copied())
+                .for_display_with(
+                    [MeasurementKind::DutyCycle],
+                    NumberFormat::SiPrefix,
+                    DEFAULT_PRECISION,
+                    |voltage| voltage.to_string(),
+                )
+                .remove(0)
+        };
+
+        assert_eq!(duty_cycle(&["T=1.000ms\n", "PW=2.000us\n"]), "Duty=0.200%");
+        // blank instead of NaN% without (or with a zero) period
+        assert_eq!(duty_cycle(&["PW=250.0us\n"]), "Duty=");
+        assert_eq!(duty_cycle(&["T=0.000s\n", "PW=250.0us\n"]), "Duty=");
+    }
+
+    #[test]
+    fn parse_all_skips_malformed_responses() {
+        let measurements =
+            Measurements::parse_all(["Vpp=3.720V\n", "Va=3.6", "T=1000.0us\n", "RMS=?\n"]);
+
+        assert_eq!(
+            measurements.peak_to_peak,
+            PeakToPeak(Some(ScaledNumber(3.72)))
+        );
+        assert_eq!(measurements.amplitude, Amplitude(None));
+        assert_eq!(measurements.period, Period(Some(ScaledNumber(1e-3))));
+        assert_eq!(measurements.rms, Rms(None));
+    }
 }
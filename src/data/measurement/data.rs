@@ -22,6 +22,11 @@ macro_rules! decl_measurement {
         #[derive(Debug, Default, PartialEq, DeserializeFromStr)]
         pub struct $name(pub Option<ScaledNumber>);
 
+        impl $name {
+            pub const DISPLAY_PREFIX: &'static str = $disp_prefix;
+            pub const UNIT: &'static str = $unit;
+        }
+
         impl FromStr for $name {
             type Err = &'static str;
 
@@ -29,15 +34,17 @@ macro_rules! decl_measurement {
                 let s = s
                     .strip_prefix(concat!($prefix, "="))
                     .ok_or("not a measurement")?;
+                // device responses end with a newline, `Display` output doesn't
+                let s = s.strip_suffix('\n').unwrap_or(s);
 
-                if s.ends_with("?\n") || s.ends_with("OFF\n") {
+                // empty is how `Display` writes an unavailable measurement
+                if s.is_empty() || s.ends_with('?') || s.ends_with("OFF") {
                     Ok(Self(None))
                 } else {
-                    let s = s
-                        .strip_suffix(concat!($unit, "\n"))
-                        .expect("not a measurement");
+                    // can be truncated or garbled under heavy USB load, don't panic on it
+                    let s = s.strip_suffix($unit).ok_or("truncated measurement")?;
                     Ok(Self(Some(ScaledNumber(
-                        f64::parse_scaled(s).expect("invalid measurement"),
+                        f64::parse_scaled(s).ok_or("invalid measurement")?,
                     ))))
                 }
             }
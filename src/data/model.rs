@@ -0,0 +1,59 @@
+use super::{awg::AwgUnits, limits::DeviceLimits, screen::ScreenGeometry};
+
+/// Everything that depends on the connected model, resolved once from its `*IDN?` reply.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelProfile {
+    pub limits: DeviceLimits,
+    pub screen: ScreenGeometry,
+    pub awg_units: AwgUnits,
+}
+
+impl ModelProfile {
+    /// HDS200 series (HDS242S/HDS272S/HDS2102S)
+    pub const HDS200S: Self = Self {
+        limits: DeviceLimits::HDS200S,
+        screen: ScreenGeometry::HDS200S,
+        awg_units: AwgUnits::HDS200S,
+    };
+
+    /// Picks the profile for the model in an `*IDN?` response (`OWON,<model>,<serial>,<fw>`).
+    ///
+    /// Other models get the defaults, but the documented generator units: only the HDS200 series
+    /// is known to mis-scale them.
+    pub fn from_idn(idn: &str) -> Self {
+        match idn.split(',').nth(1).map(str::trim) {
+            Some(model) if model.starts_with("HDS2") => Self::HDS200S,
+            _ => Self {
+                awg_units: AwgUnits::DOCUMENTED,
+                ..Self::default()
+            },
+        }
+    }
+}
+
+impl Default for ModelProfile {
+    // the HDS200 series is the only one known so far
+    fn default() -> Self {
+        Self::HDS200S
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_from_idn() {
+        let profile = ModelProfile::from_idn("OWON,HDS2102S,2112205,V1.5.1\n");
+        assert_eq!(profile, ModelProfile::HDS200S);
+        assert_eq!(profile.screen.width(), 300.0);
+        assert_eq!(profile.screen.center(), 149.0);
+
+        let profile = ModelProfile::from_idn("OWON,XDS3102A,2112205,V1.0.0\n");
+        assert_eq!(profile.awg_units, AwgUnits::DOCUMENTED);
+        assert_eq!(profile.limits, DeviceLimits::default());
+        assert_eq!(profile.screen, ScreenGeometry::default());
+
+        assert_eq!(ModelProfile::from_idn("").awg_units, AwgUnits::DOCUMENTED);
+    }
+}
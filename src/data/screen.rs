@@ -0,0 +1,38 @@
+/// Horizontal layout of the device's screen, which the header's horizontal offset (in samples) and
+/// the screen data are relative to. Model-dependent, see
+/// [`ModelProfile`](super::model::ModelProfile).
+///
+/// Vertically, the grid is always [`GRID_DIV_SIZE`](crate::consts::GRID_DIV_SIZE) sample steps
+/// per div, as that follows from the 8 bit samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenGeometry {
+    /// Screen data samples per grid division
+    pub samples_per_div: f64,
+    pub horizontal_divs: f64,
+}
+
+impl ScreenGeometry {
+    /// HDS200 series (HDS242S/HDS272S/HDS2102S): 300 samples over 12 divs.
+    pub const HDS200S: Self = Self {
+        samples_per_div: 25.0,
+        horizontal_divs: 12.0,
+    };
+
+    /// Screen width in samples.
+    pub fn width(&self) -> f64 {
+        self.samples_per_div * self.horizontal_divs
+    }
+
+    /// Index of the sample just left of the screen center, which is at the trigger point when
+    /// there's no horizontal offset.
+    pub fn center(&self) -> f64 {
+        self.width() / 2.0 - 1.0
+    }
+}
+
+impl Default for ScreenGeometry {
+    /// That of the default [`ModelProfile`](super::model::ModelProfile).
+    fn default() -> Self {
+        super::model::ModelProfile::default().screen
+    }
+}
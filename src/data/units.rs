@@ -1,18 +1,40 @@
 use crate::scaled_number::{ScaledNumber, ScaledNumberExt, SiScale};
+use derive_more::{Add, Div, Mul, Neg, Sub};
 use serde_with::DeserializeFromStr;
 use std::{
     fmt::{Display, Write},
+    ops,
     str::FromStr,
 };
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, DeserializeFromStr)]
+// Arithmetic on the unit types: adding/subtracting the same unit, scaling by an `f64` and dividing
+// by the same unit (a plain ratio).
+macro_rules! impl_ratio {
+    ($($name:ident),*) => {
+        $(
+            impl ops::Div for $name {
+                type Output = f64;
+
+                fn div(self, rhs: Self) -> f64 {
+                    self.0 / rhs.0
+                }
+            }
+        )*
+    };
+}
+
+impl_ratio!(Frequency, SamplingRate, Time, Voltage);
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, PartialOrd, Add, Sub, Neg, Mul, Div, DeserializeFromStr,
+)]
 pub struct Frequency(pub f64);
 
 impl FromStr for Frequency {
     type Err = &'static str;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = s.strip_suffix("Hz").ok_or("not a sampling rate")?;
+        let s = s.trim().strip_suffix("Hz").ok_or("not a sampling rate")?;
         Ok(Self(f64::parse_scaled(s).ok_or("invalid sampling rate")?))
     }
 }
@@ -24,14 +46,16 @@ impl Display for Frequency {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, DeserializeFromStr)]
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, PartialOrd, Add, Sub, Neg, Mul, Div, DeserializeFromStr,
+)]
 pub struct SamplingRate(pub f64);
 
 impl FromStr for SamplingRate {
     type Err = &'static str;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = s.strip_suffix("Sa/s").ok_or("not a sampling rate")?;
+        let s = s.trim().strip_suffix("Sa/s").ok_or("not a sampling rate")?;
         Ok(Self(f64::parse_scaled(s).ok_or("invalid sampling rate")?))
     }
 }
@@ -43,14 +67,16 @@ impl Display for SamplingRate {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, DeserializeFromStr)]
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, PartialOrd, Add, Sub, Neg, Mul, Div, DeserializeFromStr,
+)]
 pub struct Time(pub f64);
 
 impl FromStr for Time {
     type Err = &'static str;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = s.strip_suffix('s').ok_or("not a time value")?;
+        let s = s.trim().strip_suffix('s').ok_or("not a time value")?;
         Ok(Self(f64::parse_scaled(s).ok_or("invalid time value")?))
     }
 }
@@ -63,9 +89,11 @@ impl Display for Time {
             self.0.unscale()
         };
 
-        f.write_fmt(format_args!("{val:.0}"))?;
+        // one decimal below 10 (`1.5s`, `2.0ms`), none above (`500ms`), as the device writes them
         if val.abs() < 10.0 {
-            f.write_str(".0")?;
+            f.write_fmt(format_args!("{val:.1}"))?;
+        } else {
+            f.write_fmt(format_args!("{val:.0}"))?;
         }
 
         // respects alternate formatting
@@ -74,14 +102,16 @@ impl Display for Time {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, DeserializeFromStr)]
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, PartialOrd, Add, Sub, Neg, Mul, Div, DeserializeFromStr,
+)]
 pub struct Voltage(pub f64);
 
 impl FromStr for Voltage {
     type Err = &'static str;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = s.strip_suffix(&['v', 'V']).ok_or("not a voltage")?;
+        let s = s.trim().strip_suffix(['v', 'V']).ok_or("not a voltage")?;
         Ok(Self(f64::parse_scaled(s).ok_or("invalid voltage")?))
     }
 }
@@ -93,6 +123,48 @@ impl Display for Voltage {
     }
 }
 
+impl Voltage {
+    /// Level in dBV, relative to 1V. Zero or negative voltages result in `-∞`.
+    pub fn to_dbv(self) -> Decibels {
+        let level = if self.0 > 0.0 {
+            20.0 * self.0.log10()
+        } else {
+            f64::NEG_INFINITY
+        };
+
+        Decibels(level, "dBV")
+    }
+
+    /// Power level in dBm (relative to 1mW) of this voltage, taken as RMS, across a load of
+    /// `impedance` ohms. Zero or negative voltages result in `-∞`.
+    pub fn to_dbm(self, impedance: f64) -> Decibels {
+        let level = if self.0 > 0.0 && impedance > 0.0 {
+            let watts = self.0 * self.0 / impedance;
+            10.0 * (watts / 1e-3).log10()
+        } else {
+            f64::NEG_INFINITY
+        };
+
+        Decibels(level, "dBm")
+    }
+}
+
+/// A level in decibels, with the unit denoting the reference (e.g. `dBV`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decibels(pub f64, pub &'static str);
+
+impl Display for Decibels {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0 == f64::NEG_INFINITY {
+            f.write_str("-∞")?;
+        } else {
+            let prec = f.precision().unwrap_or(2);
+            f.write_fmt(format_args!("{:.prec$}", self.0))?;
+        }
+        f.write_str(self.1)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, DeserializeFromStr)]
 pub struct ProbeAttenuation(pub u32);
 
@@ -101,7 +173,8 @@ impl FromStr for ProbeAttenuation {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let s = s
-            .strip_suffix(&['x', 'X'])
+            .trim()
+            .strip_suffix(['x', 'X'])
             .ok_or("not an attenuation factor")?;
 
         Ok(Self(s.parse().map_err(|_| "invalid attenuation factor")?))
@@ -120,3 +193,36 @@ impl Default for ProbeAttenuation {
         Self(10)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(Voltage(1.5) + Voltage(0.5), Voltage(2.0));
+        assert_eq!(Voltage(1.5) - Voltage(0.5), Voltage(1.0));
+        assert_eq!(-Voltage(1.5), Voltage(-1.5));
+        assert_eq!(Time(2e-3) * 10.0, Time(2e-2));
+        assert_eq!(Frequency(1e3) / 4.0, Frequency(250.0));
+        assert_eq!(Voltage(2.0) / Voltage(0.5), 4.0);
+        assert!(Time(1e-6) < Time(1e-3));
+    }
+
+    #[test]
+    fn time_display() {
+        assert_eq!(Time(1.5).to_string(), "1.5s");
+        assert_eq!(Time(2.5).to_string(), "2.5s");
+        assert_eq!(Time(500e-3).to_string(), "500ms");
+        assert_eq!(Time(0.0).to_string(), "0.0s");
+        assert_eq!(Time(2e-9).to_string(), "2.0ns");
+        assert_eq!(format!("{:#}", Time(10e-6)), "10us");
+    }
+
+    #[test]
+    fn sub_one_frequency_display() {
+        assert_eq!(Frequency(0.5).to_string(), "500.0mHz");
+        assert_eq!(Frequency(0.0).to_string(), "0.000Hz");
+        assert_eq!(SamplingRate(0.0).to_string(), "0.000Sa/s");
+    }
+}
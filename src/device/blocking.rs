@@ -0,0 +1,190 @@
+//! Synchronous access to a device, for scripts and tests that don't want to set up an async
+//! runtime and the channels of [`run_device_loop`](super::run_device_loop) themselves.
+
+use super::{
+    get_measurements, get_signal, identify, read_awg_config, send_command, set_awg_config,
+    AcquireMeasurementError, AcquireSignalDataError, Device, DeviceInitializationError,
+    FromUsbDeviceError, Io, IoError, RawDump, ReadAwgConfigError, RunError, SetAwgConfigError,
+};
+use crate::{
+    consts::MAX_CHANNELS,
+    data::{
+        awg::AwgConfig,
+        head::{Channel, DataHeader},
+        limits::DeviceLimits,
+        measurement::{MeasurementKind, Measurements},
+        model::ModelProfile,
+        units::{Time, Voltage},
+    },
+    OscilloscopeCommand, SettingMismatch, SignalData,
+};
+use snafu::{ResultExt, Snafu};
+use std::collections::BTreeSet;
+use tokio::runtime::{Builder, Runtime};
+use windows::core::HSTRING;
+
+/// A device driven on its own single-threaded runtime, every call blocks until the device
+/// answered.
+pub struct BlockingScope {
+    runtime: Runtime,
+    io: Io,
+    /// Keeps the USB pipes `io` uses open
+    _device: Device,
+    profile: ModelProfile,
+    /// Displayed channels as of the last capture, decides which data is requested
+    channels_enabled: [bool; MAX_CHANNELS],
+    raw_dump: Option<RawDump>,
+}
+
+impl BlockingScope {
+    pub fn open(device_id: impl Into<HSTRING>) -> Result<Self, BlockingScopeError> {
+        Self::new(Device::blocking_from_device_id(device_id)?)
+    }
+
+    pub fn new(device: Device) -> Result<Self, BlockingScopeError> {
+        let runtime = Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .context(RuntimeSnafu)?;
+        let mut io = device.raw_io().context(IoOpenSnafu)?;
+        let profile = runtime.block_on(identify(&mut io));
+
+        Ok(Self {
+            runtime,
+            io,
+            _device: device,
+            profile,
+            channels_enabled: DataHeader::default().channels_enabled(),
+            raw_dump: None,
+        })
+    }
+
+    /// Records the raw bytes of every capture from now on, or stops with `None`.
+    pub fn set_raw_dump(&mut self, raw_dump: Option<RawDump>) {
+        self.raw_dump = raw_dump;
+    }
+
+    /// The limits of the model, identified when opening the device.
+    pub fn limits(&self) -> &DeviceLimits {
+        &self.profile.limits
+    }
+
+    /// Reads the header and the data of the displayed channels.
+    pub fn capture(&mut self) -> Result<SignalData, AcquireSignalDataError> {
+        let mut signal_data = self.runtime.block_on(get_signal(
+            &mut self.io,
+            &self.channels_enabled,
+            self.raw_dump.as_mut(),
+        ))?;
+
+        let enabled = signal_data.header.channels_enabled();
+        if enabled != self.channels_enabled {
+            // the displayed channels changed since the last capture, the data is of the wrong ones
+            self.channels_enabled = enabled;
+            signal_data = self.runtime.block_on(get_signal(
+                &mut self.io,
+                &self.channels_enabled,
+                self.raw_dump.as_mut(),
+            ))?;
+        }
+
+        // stop at the first failed write instead of dumping a gap
+        if let Some(source) = self.raw_dump.as_mut().and_then(RawDump::take_error) {
+            self.raw_dump = None;
+            return Err(AcquireSignalDataError::WriteRawDump { source });
+        }
+
+        signal_data.header.time_base.screen = self.profile.screen;
+        Ok(signal_data)
+    }
+
+    /// Queries the given measurements of `channel`, one round trip each.
+    pub fn measurements(
+        &mut self,
+        channel: Channel,
+        kinds: &BTreeSet<MeasurementKind>,
+    ) -> Result<Measurements, AcquireMeasurementError> {
+        let commands = Measurements::measurement_commands(channel, kinds);
+        self.runtime
+            .block_on(get_measurements(&mut self.io, &commands))
+    }
+
+    /// Sends any command, waiting for the device to settle where needed.
+    ///
+    /// Returns what the device applied instead, if it read back different from the request.
+    pub fn command(
+        &mut self,
+        cmd: OscilloscopeCommand,
+    ) -> Result<Option<SettingMismatch>, RunError> {
+        self.runtime
+            .block_on(send_command(cmd, &mut self.io, &self.profile.screen))
+    }
+
+    /// See [`command`](Self::command) for the returned mismatch
+    pub fn set_time_scale(&mut self, scale: Time) -> Result<Option<SettingMismatch>, RunError> {
+        self.command(OscilloscopeCommand::SetTimeScale(scale))
+    }
+
+    /// Offset in grid divisions
+    pub fn set_horizontal_offset(&mut self, offset: f64) -> Result<(), RunError> {
+        self.command(OscilloscopeCommand::SetHorizontalOffset(offset))
+            .map(drop)
+    }
+
+    /// Scale per division as shown on the device, i.e. including the probe attenuation
+    ///
+    /// See [`command`](Self::command) for the returned mismatch
+    pub fn set_vertical_scale(
+        &mut self,
+        channel: Channel,
+        scale: Voltage,
+    ) -> Result<Option<SettingMismatch>, RunError> {
+        self.command(OscilloscopeCommand::SetChannelVScale(channel, scale))
+    }
+
+    /// Offset in grid divisions
+    pub fn set_vertical_offset(&mut self, channel: Channel, offset: f64) -> Result<(), RunError> {
+        self.command(OscilloscopeCommand::SetChannelVOffset(channel, offset))
+            .map(drop)
+    }
+
+    pub fn set_trigger_level(&mut self, level: Voltage) -> Result<(), RunError> {
+        self.command(OscilloscopeCommand::SetTriggerLevel(level))
+            .map(drop)
+    }
+
+    pub fn awg_config(&mut self) -> Result<AwgConfig, ReadAwgConfigError> {
+        self.runtime.block_on(read_awg_config(
+            &mut self.io,
+            self.profile.limits.awg_channels,
+            &self.profile.awg_units,
+        ))
+    }
+
+    pub fn set_awg_config(&mut self, config: AwgConfig) -> Result<(), SetAwgConfigError> {
+        self.runtime.block_on(set_awg_config(&mut self.io, config))
+    }
+
+    /// Sends a raw SCPI query and returns the reply.
+    pub fn query(&mut self, query: &str) -> Result<Vec<u8>, IoError> {
+        let buf = &mut [0u8; 10240];
+        let reply = self
+            .runtime
+            .block_on(self.io.send_with_output(query.as_bytes(), buf))?;
+        Ok(reply.to_vec())
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum BlockingScopeError {
+    #[snafu(context(false))]
+    FromUsbDevice {
+        source: FromUsbDeviceError,
+    },
+    Runtime {
+        source: std::io::Error,
+    },
+    IoOpen {
+        source: DeviceInitializationError,
+    },
+}
@@ -0,0 +1,879 @@
+//! A simulated oscilloscope, so the device loop (and the GUI) can run without hardware.
+//!
+//! CH1 is connected to a fixed 1kHz sine, CH2 to the function generator output. The replies follow
+//! the formats of the HDS2102S (see `tests/fixtures`), the commands change the simulated settings
+//! like they would on the device.
+
+use super::{IoError, Transport};
+use crate::{
+    consts::{GRID_DIV_SIZE, SAMPLES, TIME_BASES, VERTICAL_SCALES},
+    data::{
+        awg::{AwgChannelConfig, AwgChannelDisplay, AwgMode, AwgUnits, ARB_SAMPLE_RANGE},
+        head::{
+            Channel, ChannelCoupling, ChannelDisplay, ChannelInfo, MemoryDepth, SampleType,
+            TriggerEdge, TriggerSweep, TriggerType,
+        },
+        screen::ScreenGeometry,
+        units::{Frequency, ProbeAttenuation, SamplingRate, Time, Voltage},
+    },
+    scaled_number::ScaledNumber,
+};
+use std::{
+    collections::VecDeque,
+    f64::consts::TAU,
+    fmt::Display,
+    io::Write,
+    time::{Duration, Instant},
+};
+
+/// `*IDN?` reply of the demo device
+pub const DEMO_IDN: &str = "OWON,HDS2102S,DEMO,V1.5.1";
+/// Generator units of the demo device, mis-scaled like the model it pretends to be
+const DEMO_AWG_UNITS: AwgUnits = AwgUnits::HDS200S;
+
+/// Time between two acquisitions, roughly what a real device manages over USB
+const FRAME_INTERVAL: Duration = Duration::from_millis(30);
+/// Points per period the waveforms are evaluated at for triggering and measurements
+const PERIOD_POINTS: usize = 1000;
+/// Points per screen column that peak detect takes the min/max of
+const PEAK_POINTS: usize = 4;
+/// Peak noise added to every sample, in screen units
+const NOISE_UNITS: f64 = 0.75;
+
+/// The simulated device. Talks like a real one through [`Transport`].
+pub struct DemoIo {
+    started: Instant,
+    channels: [ChannelInfo; 2],
+    time_scale: Time,
+    h_offset: i64,
+    trigger: TriggerSettings,
+    sample_type: SampleType,
+    depth: MemoryDepth,
+    awg: AwgChannelConfig,
+    /// Uploaded arbitrary waveform, normalized to -1..=1
+    arbitrary: Vec<f64>,
+    /// State of the noise generator
+    noise: u64,
+    /// The current acquisition, taken when the header is requested
+    frame: Frame,
+    replies: VecDeque<Reply>,
+}
+
+#[derive(Clone, Copy)]
+struct Frame {
+    /// Seconds since the demo started of the trigger point
+    time: f64,
+    triggered: bool,
+}
+
+enum Reply {
+    Bytes(Vec<u8>),
+    /// Screen data, generated when it's read so it matches the header requested in between
+    Screen(Channel),
+}
+
+/// One period of a signal, or the level of a flat line
+struct Period {
+    frequency: Option<f64>,
+    points: Vec<f64>,
+}
+
+struct TriggerSettings {
+    ty: TriggerType,
+    source: Channel,
+    edge: TriggerEdge,
+    level: Voltage,
+    sweep: TriggerSweep,
+    holdoff: Time,
+}
+
+/// A periodic signal at the probe tip.
+struct Waveform {
+    mode: AwgMode,
+    frequency: f64,
+    amplitude: f64,
+    offset: f64,
+    duty_cycle: f64,
+    symmetry: f64,
+}
+
+impl Default for DemoIo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DemoIo {
+    pub fn new() -> Self {
+        let channel = |channel, probe, scale, offset| ChannelInfo {
+            channel,
+            display: ChannelDisplay::On,
+            coupling: ChannelCoupling::Dc,
+            probe: ProbeAttenuation(probe),
+            scale: Voltage(scale),
+            offset,
+            frequency: 0.0,
+            // like the HDS2xx firmware, which doesn't report it
+            bandwidth_limit: None,
+        };
+
+        Self {
+            started: Instant::now(),
+            channels: [
+                channel(Channel::Ch1, 10, 0.1, -75),
+                channel(Channel::Ch2, 1, 1.0, 50),
+            ],
+            time_scale: Time(500e-6),
+            h_offset: 0,
+            trigger: TriggerSettings {
+                ty: TriggerType::Edge,
+                source: Channel::Ch1,
+                edge: TriggerEdge::Rising,
+                level: Voltage(1.65),
+                sweep: TriggerSweep::Auto,
+                holdoff: Time(100e-9),
+            },
+            sample_type: SampleType::Sample,
+            depth: MemoryDepth::EightK,
+            awg: AwgChannelConfig {
+                enabled: true,
+                mode: AwgMode::Square,
+                frequency: Frequency(2e3),
+                amplitude: Voltage(2.0),
+                ..Default::default()
+            },
+            arbitrary: Vec::new(),
+            noise: 0x2545_f491_4f6c_dd1d,
+            frame: Frame {
+                time: 0.0,
+                triggered: false,
+            },
+            replies: VecDeque::new(),
+        }
+    }
+
+    /// The signal the channel is connected to, before coupling.
+    fn waveform(&self, channel: Channel) -> Option<Waveform> {
+        match channel {
+            Channel::Ch1 => Some(Waveform {
+                mode: AwgMode::Sine,
+                frequency: 1e3,
+                amplitude: 3.3,
+                offset: 1.65,
+                duty_cycle: 50.0,
+                symmetry: 50.0,
+            }),
+            Channel::Ch2 => self.awg.enabled.then_some(Waveform {
+                mode: self.awg.mode,
+                frequency: self.awg.frequency.0,
+                amplitude: self.awg.amplitude.0,
+                offset: self.awg.offset.0,
+                duty_cycle: self.awg.duty_cycle,
+                symmetry: self.awg.symmetry,
+            }),
+            // the simulated model has two channels
+            Channel::Ch3 | Channel::Ch4 => None,
+        }
+    }
+
+    /// One period of the channel's signal as the scope sees it, i.e. with coupling applied.
+    fn period(&self, channel: Channel) -> Period {
+        let info = &self.channels[channel as usize];
+        let waveform = match self.waveform(channel) {
+            Some(waveform) if info.coupling != ChannelCoupling::Gnd => waveform,
+            _ => {
+                return Period {
+                    frequency: None,
+                    points: vec![0.0],
+                }
+            }
+        };
+
+        let mut points = (0..PERIOD_POINTS)
+            .map(|i| waveform.value(i as f64 / PERIOD_POINTS as f64, &self.arbitrary))
+            .collect::<Vec<_>>();
+        if info.coupling == ChannelCoupling::Ac {
+            let mean = mean(&points);
+            points.iter_mut().for_each(|v| *v -= mean);
+        }
+
+        Period {
+            frequency: Some(waveform.frequency),
+            points,
+        }
+    }
+
+    /// The time the current acquisition is triggered at, or `None` if the trigger condition isn't
+    /// met.
+    fn trigger_time(&self, now: f64) -> Option<f64> {
+        // NOTE: only edge triggers are simulated, everything else free-runs
+        if self.trigger.ty != TriggerType::Edge {
+            return None;
+        }
+        let Period {
+            frequency: Some(frequency),
+            points,
+        } = self.period(self.trigger.source)
+        else {
+            return None;
+        };
+
+        let level = self.trigger.level.0;
+        let crossing = (0..points.len()).find_map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % points.len()]);
+            let crosses = match self.trigger.edge {
+                TriggerEdge::Rising => a < level && b >= level,
+                TriggerEdge::Falling => a > level && b <= level,
+            };
+            crosses.then(|| (i as f64 + (level - a) / (b - a)) / points.len() as f64)
+        })?;
+
+        // the latest crossing before now
+        let phase = (now * frequency).rem_euclid(1.0);
+        Some(now - (phase - crossing).rem_euclid(1.0) / frequency)
+    }
+
+    /// Starts a new acquisition.
+    fn acquire(&mut self) {
+        let now = self.started.elapsed().as_secs_f64();
+        self.frame = match self.trigger_time(now) {
+            Some(time) => Frame {
+                time,
+                triggered: true,
+            },
+            None => Frame {
+                time: now,
+                triggered: false,
+            },
+        };
+    }
+
+    fn screen_data(&mut self, channel: Channel) -> Vec<u8> {
+        let info = self.channels[channel as usize];
+        let period = self.period(channel);
+        let time_base = self.time_base();
+        let column_time = time_base.scale.0 * time_base.screen.horizontal_divs / SAMPLES as f64;
+        let times = time_base.sample_times(SAMPLES).collect::<Vec<_>>();
+
+        let mut data = (self.data_len() as u32).to_le_bytes().to_vec();
+        for time in times {
+            match self.sample_type {
+                SampleType::Sample => data.push(self.sample(&info, &period, time.0) as u8),
+                // like the device, the min/max of each column's interval as a pair
+                SampleType::Peak => {
+                    let (min, max) = (0..PEAK_POINTS)
+                        .map(|i| {
+                            let offset = column_time * i as f64 / PEAK_POINTS as f64;
+                            self.sample(&info, &period, time.0 + offset)
+                        })
+                        .fold((i8::MAX, i8::MIN), |(min, max), s| (min.min(s), max.max(s)));
+                    data.extend([min as u8, max as u8]);
+                }
+            }
+        }
+
+        data
+    }
+
+    /// The raw sample of `period` at `time` relative to the trigger point, with noise.
+    fn sample(&mut self, info: &ChannelInfo, period: &Period, time: f64) -> i8 {
+        let voltage = period.voltage_at(self.frame.time + time);
+        let units = info.voltage_to_units(Voltage(voltage)) + self.noise();
+        units.round().clamp(i8::MIN as f64, i8::MAX as f64) as i8
+    }
+
+    /// Screen data bytes per channel: one per column, two (a min/max pair) in peak detect.
+    fn data_len(&self) -> usize {
+        match self.sample_type {
+            SampleType::Sample => SAMPLES,
+            SampleType::Peak => SAMPLES * 2,
+        }
+    }
+
+    fn time_base(&self) -> crate::data::head::TimeBase {
+        crate::data::head::TimeBase {
+            scale: self.time_scale,
+            h_offset: self.h_offset,
+            screen: ScreenGeometry::HDS200S,
+        }
+    }
+
+    /// Uniform noise in `-NOISE_UNITS..NOISE_UNITS`
+    fn noise(&mut self) -> f64 {
+        // xorshift, plenty for some fuzz on the trace
+        self.noise ^= self.noise << 13;
+        self.noise ^= self.noise >> 7;
+        self.noise ^= self.noise << 17;
+        ((self.noise >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0) * NOISE_UNITS
+    }
+
+    fn header(&self) -> Vec<u8> {
+        let sample_rate = {
+            let depth = match self.depth {
+                MemoryDepth::FourK => 4e3,
+                MemoryDepth::EightK => 8e3,
+            };
+            SamplingRate((depth / (self.time_scale.0 * 12.0)).min(250e6))
+        };
+        let run_status = match (self.frame.triggered, self.trigger.sweep) {
+            (true, _) => "TRIG",
+            (false, TriggerSweep::Auto) => "AUTo",
+            (false, _) => "READy",
+        };
+        let channel = |info: &ChannelInfo| {
+            let frequency = match self.period(info.channel).frequency {
+                Some(frequency) if info.display == ChannelDisplay::On => frequency,
+                _ => 0.0,
+            };
+            serde_json::json!({
+                "NAME": info.channel.to_string(),
+                "DISPLAY": info.display.to_string(),
+                "COUPLING": info.coupling.to_string(),
+                "PROBE": info.probe.to_string(),
+                "SCALE": format!("{:#}", info.scale),
+                "OFFSET": info.offset,
+                "FREQUENCE": frequency,
+            })
+        };
+
+        let header = serde_json::json!({
+            "TIMEBASE": {
+                "SCALE": format!("{:#}", self.time_scale),
+                "HOFFSET": self.h_offset,
+            },
+            "SAMPLE": {
+                "FULLSCREEN": self.data_len(),
+                "SLOWMOVE": -1,
+                "DATALEN": self.data_len(),
+                "SAMPLERATE": format!("{sample_rate:#}"),
+                "TYPE": match self.sample_type {
+                    SampleType::Sample => "SAMPle",
+                    SampleType::Peak => "PEAK",
+                },
+                "DEPMEM": self.depth.to_string(),
+            },
+            "CHANNEL": [channel(&self.channels[0]), channel(&self.channels[1])],
+            "DATATYPE": "SCREEN",
+            "RUNSTATUS": run_status,
+            "IDN": DEMO_IDN,
+            "MODEL": "HDS2102S",
+            "Trig": {
+                "Mode": "SINGle",
+                "Type": self.trigger.ty.to_string(),
+                "Items": {
+                    "Channel": self.trigger.source.to_string(),
+                    "Level": format!("{:#}", self.trigger.level),
+                    "Edge": self.trigger.edge.to_string(),
+                    "Coupling": "DC",
+                    "Sweep": match self.trigger.sweep {
+                        TriggerSweep::Auto => "AUTO",
+                        TriggerSweep::Normal => "NORMal",
+                        TriggerSweep::Single => "SINGlE",
+                    },
+                },
+            },
+        })
+        .to_string();
+
+        let mut bytes = (header.len() as u32).to_le_bytes().to_vec();
+        bytes.extend(header.into_bytes());
+        bytes
+    }
+
+    /// The reply to `:MEAS:<channel>:<query>?`, computed over one period of the signal.
+    fn measurement(&self, channel: Channel, query: &str) -> Option<String> {
+        let Period { frequency, points } = self.period(channel);
+        let max = points.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let min = points.iter().copied().fold(f64::INFINITY, f64::min);
+        let mid = (max + min) / 2.0;
+        // timing measurements need an actual signal, the device answers `?` otherwise
+        let frequency = frequency.filter(|_| max - min > 1e-6);
+        let per_point = frequency.map(|f| 1.0 / (f * points.len() as f64));
+
+        let value = |prefix: &str, value: Option<f64>, unit: &str| match value {
+            Some(value) => format!("{prefix}={:#}{unit}\n", ScaledNumber(value)),
+            None => format!("{prefix}=?\n"),
+        };
+
+        Some(match query {
+            "PKPK" => value("Vpp", Some(max - min), "V"),
+            "VAMP" => value("Va", Some(max - min), "V"),
+            "AVER" => value("V", Some(mean(&points)), "V"),
+            "SQUA" => value(
+                "RMS",
+                Some(mean(&points.iter().map(|v| v * v).collect::<Vec<_>>()).sqrt()),
+                "V",
+            ),
+            "PER" => value("T", frequency.map(|f| 1.0 / f), "s"),
+            "RT" => {
+                let low = min + 0.1 * (max - min);
+                let high = min + 0.9 * (max - min);
+                let rise = points
+                    .iter()
+                    .position(|&v| v <= low)
+                    .and_then(|start| {
+                        let len = points.len();
+                        (1..len)
+                            .map(|i| (start + i) % len)
+                            .skip_while(|&i| points[i] <= low)
+                            .position(|i| points[i] >= high)
+                    })
+                    // ideal edges still take one point
+                    .map(|points| (points + 1) as f64);
+                value("RT", per_point.zip(rise).map(|(dt, n)| dt * n), "s")
+            }
+            "PWID" => {
+                let above = points.iter().filter(|&&v| v > mid).count() as f64;
+                value("PW", per_point.map(|dt| dt * above), "s")
+            }
+            _ => return None,
+        })
+    }
+
+    /// Sets the scales and offsets to show the displayed signals, like the device's auto setup.
+    fn autoset(&mut self) {
+        for channel in [Channel::Ch1, Channel::Ch2] {
+            let Period {
+                frequency: Some(_),
+                points,
+            } = self.period(channel)
+            else {
+                continue;
+            };
+            let max = points.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let min = points.iter().copied().fold(f64::INFINITY, f64::min);
+
+            let info = &mut self.channels[channel as usize];
+            let probe = info.probe.0 as f64;
+            // about 6 divisions tall
+            info.scale = VERTICAL_SCALES
+                .into_iter()
+                .find(|scale| scale.0 * probe * 6.0 >= max - min)
+                .unwrap_or(VERTICAL_SCALES[VERTICAL_SCALES.len() - 1]);
+            info.offset = (-(max + min) / 2.0 / info.scale_per_unit()).round() as i64;
+        }
+
+        if let Period {
+            frequency: Some(frequency),
+            points,
+        } = self.period(self.trigger.source)
+        {
+            // about 3 periods on screen
+            self.time_scale = TIME_BASES
+                .into_iter()
+                .find(|scale| scale.0 * 12.0 * frequency >= 3.0)
+                .unwrap_or(TIME_BASES[TIME_BASES.len() - 1]);
+            self.h_offset = 0;
+            self.trigger.level = Voltage(mean(&points));
+        }
+    }
+
+    fn handle_command(&mut self, command: &[u8]) {
+        // binary payload, can't be split up
+        if let Some(block) = command.strip_prefix(b":FUNC:ARB:DATA ") {
+            self.upload_arbitrary(block);
+            return;
+        }
+
+        for command in String::from_utf8_lossy(command).split(';') {
+            let command = command.trim();
+            let (command, arg) = command
+                .split_once(' ')
+                .map_or((command, ""), |(c, a)| (c, a.trim()));
+            if let Some(reply) = self.handle_single(command, arg) {
+                self.replies.push_back(reply);
+            }
+        }
+    }
+
+    /// Handles a single command, returning the reply if it has one.
+    fn handle_single(&mut self, command: &str, arg: &str) -> Option<Reply> {
+        let text = |s: String| Some(Reply::Bytes(format!("{s}\n").into_bytes()));
+        if command == "*IDN?" {
+            return text(DEMO_IDN.to_string());
+        }
+        let mut parts = command.split(':').skip(1);
+
+        match (parts.next()?, parts.next(), parts.next(), parts.next()) {
+            ("DATa", Some("WAVe"), Some("SCReen"), Some(what)) => match what {
+                "HEAD?" => {
+                    self.acquire();
+                    Some(Reply::Bytes(self.header()))
+                }
+                "CH1?" => Some(Reply::Screen(Channel::Ch1)),
+                "CH2?" => Some(Reply::Screen(Channel::Ch2)),
+                _ => None,
+            },
+            ("MEAS", Some(channel), Some(query), None) => {
+                let channel = parse_token(&[Channel::Ch1, Channel::Ch2], channel)?;
+                self.measurement(channel, query.strip_suffix('?')?)
+                    .map(|reply| Reply::Bytes(reply.into_bytes()))
+            }
+            ("HORIzontal", Some("OFFSet"), None, None) => {
+                let samples_per_div = ScreenGeometry::HDS200S.samples_per_div;
+                self.h_offset = (arg.parse::<f64>().ok()? * samples_per_div).round() as i64;
+                None
+            }
+            ("HORIzontal", Some("SCALe"), None, None) => {
+                self.time_scale = arg.parse().ok()?;
+                None
+            }
+            ("HORIzontal", Some("SCALe?"), None, None) => text(format!("{:#}", self.time_scale)),
+            (channel @ ("CH1" | "CH2"), Some(setting), None, None) => {
+                let channel = parse_token(&[Channel::Ch1, Channel::Ch2], channel)?;
+                self.channel_setting(channel, setting, arg).and_then(text)
+            }
+            ("TRIGger", Some("HOLDoff"), None, None) => {
+                self.trigger.holdoff = arg.parse().ok()?;
+                None
+            }
+            ("TRIGger", Some("HOLDoff?"), None, None) => {
+                text(format!("{:#}", self.trigger.holdoff))
+            }
+            ("TRIGger", Some("SINGle"), Some(setting), level) => {
+                self.trigger_setting(setting, level, arg);
+                None
+            }
+            ("ACQuire", Some("MODe"), None, None) => {
+                self.sample_type = parse_token(&[SampleType::Sample, SampleType::Peak], arg)?;
+                None
+            }
+            ("ACQuire", Some("DEPMem"), None, None) => {
+                self.depth = parse_token(&[MemoryDepth::FourK, MemoryDepth::EightK], arg)?;
+                None
+            }
+            ("AUToset", None, None, None) => {
+                self.autoset();
+                None
+            }
+            ("FUNC" | "CHAN" | "FUNC?" | "CHAN?", ..) => {
+                let command = command.strip_prefix(':')?;
+                self.awg_setting(command, arg).and_then(text)
+            }
+            _ => None,
+        }
+    }
+
+    fn channel_setting(&mut self, channel: Channel, setting: &str, arg: &str) -> Option<String> {
+        let info = &mut self.channels[channel as usize];
+        match setting {
+            "DISPlay" => info.display = arg.parse().ok()?,
+            "OFFSet" => info.offset = (arg.parse::<f64>().ok()? * GRID_DIV_SIZE).round() as i64,
+            // the attenuated scale
+            "SCALe" => info.scale = arg.parse::<Voltage>().ok()? * (1.0 / info.probe.0 as f64),
+            "SCALe?" => return Some(format!("{:#}", info.scale_attenuated())),
+            "COUPling" => {
+                info.coupling = parse_token(
+                    &[
+                        ChannelCoupling::Dc,
+                        ChannelCoupling::Ac,
+                        ChannelCoupling::Gnd,
+                    ],
+                    arg,
+                )?
+            }
+            "PROBe" => info.probe = arg.parse().ok()?,
+            _ => {}
+        }
+        None
+    }
+
+    fn trigger_setting(&mut self, setting: &str, level: Option<&str>, arg: &str) -> Option<()> {
+        let trigger = &mut self.trigger;
+        match (setting, level) {
+            ("SOURce", None) => trigger.source = parse_token(&[Channel::Ch1, Channel::Ch2], arg)?,
+            ("MODE", None) => trigger.ty = arg.parse().ok()?,
+            ("EDGe", None) => {
+                trigger.edge = parse_token(&[TriggerEdge::Rising, TriggerEdge::Falling], arg)?
+            }
+            ("EDGe", Some("LEVel")) => trigger.level = arg.parse().ok()?,
+            ("SWEep", None) => {
+                trigger.sweep = parse_token(
+                    &[
+                        TriggerSweep::Auto,
+                        TriggerSweep::Normal,
+                        TriggerSweep::Single,
+                    ],
+                    arg,
+                )?
+            }
+            // coupling and pulse settings don't change the simulation
+            _ => {}
+        }
+        Some(())
+    }
+
+    /// Handles the `:FUNC`/`:CHAN` commands. Settings are in Hz and V, the replies in the
+    /// generator's units (µHz, mV).
+    fn awg_setting(&mut self, command: &str, arg: &str) -> Option<String> {
+        let awg = &mut self.awg;
+        match command {
+            "FUNC?" => return Some(awg.mode.to_string()),
+            "CHAN?" => return Some(AwgChannelDisplay::from(awg.enabled).to_string()),
+            "FUNC:FREQ?" => {
+                let raw = awg.frequency.0 * DEMO_AWG_UNITS.frequency_scale;
+                return Some(raw.to_string());
+            }
+            "FUNC:AMPL?" => {
+                return Some((awg.amplitude.0 * DEMO_AWG_UNITS.voltage_scale).to_string())
+            }
+            "FUNC:OFFS?" => return Some((awg.offset.0 * DEMO_AWG_UNITS.voltage_scale).to_string()),
+            "FUNC:DTYC?" => return Some(awg.duty_cycle.to_string()),
+            "FUNC:SYMM?" => return Some(awg.symmetry.to_string()),
+            "FUNC" => awg.mode = arg.parse().ok()?,
+            "CHAN" => awg.enabled = arg.parse::<AwgChannelDisplay>().ok()?.into(),
+            "FUNC:FREQ" => awg.frequency = Frequency(arg.parse().ok()?),
+            "FUNC:AMPL" => awg.amplitude = Voltage(arg.parse().ok()?),
+            "FUNC:OFFS" => awg.offset = Voltage(arg.parse().ok()?),
+            "FUNC:DTYC" => awg.duty_cycle = arg.parse().ok()?,
+            "FUNC:SYMM" => awg.symmetry = arg.parse().ok()?,
+            _ => {}
+        }
+        None
+    }
+
+    /// Parses the IEEE 488.2 definite length block of `:FUNC:ARB:DATA`.
+    fn upload_arbitrary(&mut self, block: &[u8]) {
+        let Some((&digits, rest)) = block.strip_prefix(b"#").and_then(<[u8]>::split_first) else {
+            return;
+        };
+        let digits = (digits as char).to_digit(10).unwrap_or(0) as usize;
+        let Some(samples) = rest.get(digits..) else {
+            return;
+        };
+
+        let max = *ARB_SAMPLE_RANGE.end() as f64;
+        self.arbitrary = samples
+            .chunks_exact(2)
+            .map(|sample| i16::from_le_bytes([sample[0], sample[1]]) as f64 / max)
+            .collect();
+    }
+}
+
+impl Waveform {
+    /// The voltage at `phase` (0..1) of a period.
+    fn value(&self, phase: f64, arbitrary: &[f64]) -> f64 {
+        let normalized = match self.mode {
+            AwgMode::Square | AwgMode::Pulse => {
+                if phase * 100.0 < self.duty_cycle {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            AwgMode::Ramp => {
+                let peak = (self.symmetry / 100.0).clamp(1e-6, 1.0 - 1e-6);
+                if phase < peak {
+                    -1.0 + 2.0 * phase / peak
+                } else {
+                    1.0 - 2.0 * (phase - peak) / (1.0 - peak)
+                }
+            }
+            AwgMode::Arbitrary if !arbitrary.is_empty() => {
+                arbitrary[(phase * arbitrary.len() as f64) as usize % arbitrary.len()]
+            }
+            // NOTE: the more exotic generator modes are all shown as a sine
+            _ => (TAU * phase).sin(),
+        };
+
+        self.offset + normalized * self.amplitude / 2.0
+    }
+}
+
+impl Period {
+    /// The voltage at time `t` (seconds since the demo started).
+    fn voltage_at(&self, t: f64) -> f64 {
+        let len = self.points.len();
+        match self.frequency {
+            Some(frequency) => {
+                let phase = (t * frequency).rem_euclid(1.0);
+                self.points[(phase * len as f64) as usize % len]
+            }
+            None => self.points[0],
+        }
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Finds the option that's written as `token`, for the types without a `FromStr`.
+fn parse_token<T: Display + Copy>(options: &[T], token: &str) -> Option<T> {
+    options
+        .iter()
+        .copied()
+        .find(|option| option.to_string() == token)
+}
+
+impl Transport for DemoIo {
+    async fn send(&mut self, command: &[u8]) -> Result<(), IoError> {
+        self.raw_send_nowait(command).await
+    }
+
+    async fn send_with_writer(
+        &mut self,
+        f: impl FnOnce(&mut dyn Write) -> Result<(), std::io::Error>,
+    ) -> Result<(), IoError> {
+        let mut command = Vec::new();
+        f(&mut command)?;
+        self.raw_send_nowait(&command).await
+    }
+
+    async fn raw_send_nowait(&mut self, command: &[u8]) -> Result<(), IoError> {
+        if command == b":DATa:WAVe:SCReen:HEAD?" {
+            tokio::time::sleep(FRAME_INTERVAL).await;
+        }
+        self.handle_command(command);
+        Ok(())
+    }
+
+    async fn recv<'a>(&mut self, buf: &'a mut [u8]) -> Result<&'a mut [u8], IoError> {
+        let reply = match self.replies.pop_front() {
+            Some(Reply::Bytes(bytes)) => bytes,
+            Some(Reply::Screen(channel)) => self.screen_data(channel),
+            None => {
+                // like the device, unknown queries just don't get an answer
+                tokio::time::timeout(Duration::ZERO, std::future::pending::<()>()).await?;
+                unreachable!("pending future completed");
+            }
+        };
+
+        let len = reply.len().min(buf.len());
+        buf[..len].copy_from_slice(&reply[..len]);
+        Ok(&mut buf[..len])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data::measurement::{MeasurementKind, Measurements},
+        device::{get_measurements, get_signal, identify, read_awg_config, send_command},
+        OscilloscopeCommand,
+    };
+
+    #[tokio::test]
+    async fn acquires_triggered_signal() {
+        let mut io = DemoIo::new();
+        assert_eq!(identify(&mut io).await.screen, ScreenGeometry::HDS200S);
+
+        let signal = get_signal(&mut io, &[true, true], None).await.unwrap();
+        let head = signal.header;
+        assert!(matches!(
+            head.run_status,
+            crate::data::head::RunStatus::Triggering
+        ));
+        assert_eq!(head.channel(Channel::Ch1).scale_attenuated(), Voltage(1.0));
+
+        // the trigger point is the sample just left of the center, see `TimeBase::sample_times`
+        let voltages = signal.channel_voltages(Channel::Ch1).collect::<Vec<_>>();
+        assert_eq!(voltages.len(), SAMPLES);
+        let center = SAMPLES / 2 - 1;
+        assert!(voltages[center - 5].0 < 1.65 && voltages[center + 5].0 > 1.65);
+        assert!(signal.channel_data(Channel::Ch2).is_some());
+        assert!(io.replies.is_empty());
+    }
+
+    #[tokio::test]
+    async fn peak_detect_sends_min_max_pairs() {
+        let mut io = DemoIo::new();
+        send_command(
+            OscilloscopeCommand::SetAcquisitionMode(SampleType::Peak),
+            &mut io,
+            &ScreenGeometry::HDS200S,
+        )
+        .await
+        .unwrap();
+
+        let signal = get_signal(&mut io, &[true, true], None).await.unwrap();
+        assert_eq!(signal.header.sample.data_len as usize, SAMPLES * 2);
+        let data = signal.channel_data(Channel::Ch1).unwrap();
+        assert_eq!(data.len(), SAMPLES * 2);
+        assert!(data
+            .chunks_exact(2)
+            .all(|pair| pair[0] as i8 <= pair[1] as i8));
+        assert_eq!(signal.channel_voltages(Channel::Ch1).count(), SAMPLES);
+    }
+
+    #[tokio::test]
+    async fn commands_change_the_simulation() {
+        let mut io = DemoIo::new();
+        for command in [
+            OscilloscopeCommand::SetTimeScale(Time(1e-3)),
+            OscilloscopeCommand::SetChannelVScale(Channel::Ch2, Voltage(0.5)),
+            OscilloscopeCommand::SetChannelCoupling(Channel::Ch1, ChannelCoupling::Gnd),
+            OscilloscopeCommand::SetHorizontalOffset(2.0),
+        ] {
+            send_command(command, &mut io, &ScreenGeometry::HDS200S)
+                .await
+                .unwrap();
+        }
+        assert!(io.replies.is_empty());
+
+        let head = get_signal(&mut io, &[true, true], None)
+            .await
+            .unwrap()
+            .header;
+        assert_eq!(head.time_base.scale, Time(1e-3));
+        assert_eq!(head.time_base.h_offset, 50);
+        assert_eq!(head.channel(Channel::Ch2).scale_attenuated(), Voltage(0.5));
+        assert_eq!(head.channel(Channel::Ch1).coupling, ChannelCoupling::Gnd);
+    }
+
+    #[tokio::test]
+    async fn measures_the_signals() {
+        let mut io = DemoIo::new();
+        let kinds = MeasurementKind::all();
+
+        let ch1 = get_measurements(
+            &mut io,
+            &Measurements::measurement_commands(Channel::Ch1, &kinds),
+        )
+        .await
+        .unwrap();
+        let pkpk = ch1.value(MeasurementKind::PeakToPeak).unwrap().0;
+        assert!((pkpk - 3.3).abs() < 0.01, "{pkpk}");
+        let frequency = ch1.value(MeasurementKind::Frequency).unwrap().0;
+        assert!((frequency - 1e3).abs() < 1.0, "{frequency}");
+        let average = ch1.value(MeasurementKind::Average).unwrap().0;
+        assert!((average - 1.65).abs() < 0.01, "{average}");
+
+        // the generator's 2kHz square wave, 50% duty cycle
+        let ch2 = get_measurements(
+            &mut io,
+            &Measurements::measurement_commands(Channel::Ch2, &kinds),
+        )
+        .await
+        .unwrap();
+        let width = ch2.value(MeasurementKind::PeakWidth).unwrap().0;
+        assert!((width - 250e-6).abs() < 1e-6, "{width}");
+        let rms = ch2.value(MeasurementKind::Rms).unwrap().0;
+        assert!((rms - 1.0).abs() < 0.01, "{rms}");
+    }
+
+    #[tokio::test]
+    async fn generator_drives_ch2() {
+        let mut io = DemoIo::new();
+        let mut config = read_awg_config(&mut io, 1, &DEMO_AWG_UNITS).await.unwrap();
+        assert_eq!(config.channels[0].mode, AwgMode::Square);
+
+        config.channels[0].enabled = false;
+        crate::device::set_awg_config(&mut io, config)
+            .await
+            .unwrap();
+        assert!(
+            !read_awg_config(&mut io, 1, &DEMO_AWG_UNITS)
+                .await
+                .unwrap()
+                .channels[0]
+                .enabled
+        );
+
+        let ch2 = get_measurements(&mut io, &[":MEAS:CH2:PER?".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(ch2.value(MeasurementKind::Period), None);
+    }
+}
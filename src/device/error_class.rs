@@ -0,0 +1,181 @@
+use super::{DeviceInitializationError, FromUsbDeviceError, IoError, RunError};
+use std::error::Error;
+use tokio::time::error::Elapsed;
+use windows::core::HRESULT;
+
+/// Rough cause of a device error, for telling the user what to do about it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// The device isn't a supported oscilloscope (VID/PID mismatch)
+    WrongDevice,
+    /// The device doesn't expose the expected bulk endpoints, e.g. another USB mode is active
+    PipeNotFound,
+    /// The device stopped answering
+    Timeout,
+    /// The device is opened by another application (or another instance)
+    DeviceBusy,
+    Other,
+}
+
+impl ErrorClass {
+    /// Classifies the first cause in `error`'s source chain that's recognized.
+    pub fn of(error: &(dyn Error + 'static)) -> Self {
+        let mut current = Some(error);
+        while let Some(error) = current {
+            if let Some(class) = classify(error) {
+                return class;
+            }
+            current = error.source();
+        }
+
+        ErrorClass::Other
+    }
+}
+
+impl FromUsbDeviceError {
+    pub fn class(&self) -> ErrorClass {
+        ErrorClass::of(self)
+    }
+}
+
+impl RunError {
+    pub fn class(&self) -> ErrorClass {
+        ErrorClass::of(self)
+    }
+
+    /// Whether talking to the device failed (e.g. it dropped off USB), as opposed to it answering
+    /// something unexpected. Only the former can be fixed by reconnecting.
+    pub fn is_transport_error(&self) -> bool {
+        let mut current: Option<&(dyn Error + 'static)> = Some(self);
+        while let Some(error) = current {
+            if error.is::<IoError>()
+                || matches!(
+                    error.downcast_ref::<DeviceInitializationError>(),
+                    Some(DeviceInitializationError::Windows { .. })
+                )
+            {
+                return true;
+            }
+            current = error.source();
+        }
+
+        false
+    }
+}
+
+// Win32 errors as HRESULTs (`HRESULT_FROM_WIN32`)
+const ERROR_SEM_TIMEOUT: HRESULT = HRESULT(0x8007_0079_u32 as i32);
+const ERROR_TIMEOUT: HRESULT = HRESULT(0x8007_05B4_u32 as i32);
+
+fn classify(error: &(dyn Error + 'static)) -> Option<ErrorClass> {
+    if let Some(error) = error.downcast_ref::<FromUsbDeviceError>() {
+        if let FromUsbDeviceError::WrongVidPid { .. }
+        | FromUsbDeviceError::NoMatchingDevice { .. } = error
+        {
+            return Some(ErrorClass::WrongDevice);
+        }
+    } else if let Some(error) = error.downcast_ref::<DeviceInitializationError>() {
+        match error {
+            DeviceInitializationError::BulkInPipeNotFound
+            | DeviceInitializationError::BulkOutPipeNotFound => {
+                return Some(ErrorClass::PipeNotFound)
+            }
+            DeviceInitializationError::DeviceBusy => return Some(ErrorClass::DeviceBusy),
+            DeviceInitializationError::Windows { .. } => {}
+        }
+    } else if error.is::<Elapsed>() {
+        // the source of `IoError::Timeout`
+        return Some(ErrorClass::Timeout);
+    } else if let Some(error) = error.downcast_ref::<windows::core::Error>() {
+        // busy devices are only told apart when opening them, see `DeviceInitializationError`
+        if matches!(error.code(), ERROR_SEM_TIMEOUT | ERROR_TIMEOUT) {
+            return Some(ErrorClass::Timeout);
+        }
+        return Some(ErrorClass::Other);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{CommandIoError, IoError};
+    use std::time::Duration;
+
+    #[test]
+    fn classifies_device_errors() {
+        assert_eq!(
+            FromUsbDeviceError::WrongVidPid { vid: 1, pid: 2 }.class(),
+            ErrorClass::WrongDevice
+        );
+        assert_eq!(
+            FromUsbDeviceError::NoMatchingDevice { found: 2 }.class(),
+            ErrorClass::WrongDevice
+        );
+        assert_eq!(FromUsbDeviceError::NoDeviceFound.class(), ErrorClass::Other);
+        assert_eq!(
+            FromUsbDeviceError::DeviceInitialization {
+                source: DeviceInitializationError::BulkOutPipeNotFound
+            }
+            .class(),
+            ErrorClass::PipeNotFound
+        );
+        assert_eq!(
+            RunError::IoOpen {
+                source: DeviceInitializationError::DeviceBusy
+            }
+            .class(),
+            ErrorClass::DeviceBusy
+        );
+    }
+
+    #[tokio::test]
+    async fn classifies_nested_timeouts() {
+        let elapsed = tokio::time::timeout(Duration::ZERO, std::future::pending::<()>())
+            .await
+            .unwrap_err();
+        let error = RunError::SendCommand {
+            source: CommandIoError::SetTriggerLevel {
+                source: IoError::Timeout { source: elapsed },
+            },
+        };
+        assert_eq!(error.class(), ErrorClass::Timeout);
+
+        let error = RunError::SendCommand {
+            source: CommandIoError::SetTriggerLevel {
+                source: std::io::Error::other("broken pipe").into(),
+            },
+        };
+        assert_eq!(error.class(), ErrorClass::Other);
+    }
+
+    #[test]
+    fn access_denied_after_opening_isnt_busy() {
+        const E_ACCESSDENIED: HRESULT = HRESULT(0x8007_0005_u32 as i32);
+        let error = RunError::IoOpen {
+            source: windows::core::Error::from(E_ACCESSDENIED).into(),
+        };
+        assert_eq!(error.class(), ErrorClass::Other);
+    }
+
+    #[test]
+    fn only_io_failures_are_transport_errors() {
+        let error = RunError::SendCommand {
+            source: CommandIoError::SetTriggerLevel {
+                source: std::io::Error::other("broken pipe").into(),
+            },
+        };
+        assert!(error.is_transport_error());
+
+        let error = RunError::IoOpen {
+            source: DeviceInitializationError::BulkInPipeNotFound,
+        };
+        assert!(!error.is_transport_error());
+
+        let error = RunError::ReadAwgConfig {
+            source: "x".parse::<f64>().unwrap_err().into(),
+        };
+        assert!(!error.is_transport_error());
+    }
+}
@@ -0,0 +1,94 @@
+use super::IoError;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, MutexGuard, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+
+/// Number of round trips kept.
+pub const LATENCY_LEN: usize = 500;
+
+/// Round-trip latencies and error counts of the USB link, recorded by [`Io`](super::Io) and read
+/// by the UI.
+///
+/// Nothing is recorded until [`Self::set_enabled`], until then each read only costs an atomic
+/// load.
+#[derive(Debug, Default)]
+pub struct LinkHealth {
+    enabled: AtomicBool,
+    stats: Mutex<LinkStats>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LinkStats {
+    /// When each round trip ended and how long it took, from the last command sent to its reply
+    pub latencies: VecDeque<(Instant, Duration)>,
+    pub timeouts: u32,
+    /// Failed reads other than timeouts
+    pub errors: u32,
+}
+
+impl LinkHealth {
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn stats(&self) -> MutexGuard<'_, LinkStats> {
+        // the stats stay consistent even if a panic interrupted an update
+        self.stats.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn clear(&self) {
+        *self.stats() = LinkStats::default();
+    }
+
+    pub(super) fn record<T>(&self, result: &Result<T, IoError>, round_trip: Duration) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut stats = self.stats();
+        match result {
+            Ok(_) => {
+                if stats.latencies.len() == LATENCY_LEN {
+                    stats.latencies.pop_front();
+                }
+                stats.latencies.push_back((Instant::now(), round_trip));
+            }
+            Err(IoError::Timeout { .. }) => stats.timeouts += 1,
+            Err(_) => stats.errors += 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_only_while_enabled() {
+        let health = LinkHealth::default();
+        let ok = Ok::<_, IoError>(());
+        health.record(&ok, Duration::from_millis(2));
+        assert!(health.stats().latencies.is_empty());
+
+        health.set_enabled(true);
+        for _ in 0..LATENCY_LEN + 1 {
+            health.record(&ok, Duration::from_millis(2));
+        }
+        let failed = Err::<(), _>(IoError::from(std::io::Error::other("stall")));
+        health.record(&failed, Duration::ZERO);
+
+        let stats = health.stats();
+        assert_eq!(stats.latencies.len(), LATENCY_LEN);
+        assert_eq!(stats.errors, 1);
+        assert_eq!(stats.timeouts, 0);
+    }
+}
@@ -0,0 +1,131 @@
+//! Replays device transcripts (`tests/fixtures/*_transcript.json`) instead of talking to a device.
+
+use super::{IoError, Transport};
+use serde::Deserialize;
+use std::{collections::VecDeque, io::Write, time::Duration};
+
+#[derive(Deserialize)]
+struct Transcript {
+    exchanges: VecDeque<Exchange>,
+}
+
+/// A command and the reply (if any) the device sends for it
+#[derive(Deserialize)]
+struct Exchange {
+    send: String,
+    reply: Option<Reply>,
+    /// The next read times out, the reply only arrives for the reads after it
+    #[serde(default)]
+    late: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Reply {
+    Text(String),
+    /// Text behind a 4 byte (little-endian) length prefix, like the signal header
+    FramedText(String),
+    /// Binary data, e.g. the signal data including its prefix
+    Hex(String),
+}
+
+impl Reply {
+    fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Reply::Text(text) => text.into_bytes(),
+            Reply::FramedText(text) => {
+                let mut bytes = (text.len() as u32).to_le_bytes().to_vec();
+                bytes.extend(text.into_bytes());
+                bytes
+            }
+            Reply::Hex(hex) => (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("invalid hex reply"))
+                .collect(),
+        }
+    }
+}
+
+/// Checks every command sent against the transcript, and answers with the recorded replies.
+pub struct MockIo {
+    exchanges: VecDeque<Exchange>,
+    replies: VecDeque<Vec<u8>>,
+    /// Longest reply a single `recv` returns, the rest is left for the next one
+    max_transfer: usize,
+    /// Reads that time out before the next reply is returned, see [`Exchange::late`]
+    timeouts: usize,
+}
+
+impl MockIo {
+    pub fn from_transcript(json: &str) -> Self {
+        let transcript: Transcript = serde_json::from_str(json).expect("invalid transcript");
+        Self {
+            exchanges: transcript.exchanges,
+            replies: VecDeque::new(),
+            max_transfer: usize::MAX,
+            timeouts: 0,
+        }
+    }
+
+    /// Splits replies into reads of at most `max_transfer` bytes, like bulk reads of a real device.
+    pub fn with_max_transfer(mut self, max_transfer: usize) -> Self {
+        self.max_transfer = max_transfer;
+        self
+    }
+
+    pub fn assert_finished(&self) {
+        assert!(
+            self.exchanges.is_empty(),
+            "commands left unsent: {:?}",
+            self.exchanges.iter().map(|e| &e.send).collect::<Vec<_>>()
+        );
+        assert!(self.replies.is_empty(), "replies left unread");
+    }
+}
+
+impl Transport for MockIo {
+    async fn send(&mut self, command: &[u8]) -> Result<(), IoError> {
+        self.raw_send_nowait(command).await
+    }
+
+    async fn send_with_writer(
+        &mut self,
+        f: impl FnOnce(&mut dyn Write) -> Result<(), std::io::Error>,
+    ) -> Result<(), IoError> {
+        let mut command = Vec::new();
+        f(&mut command)?;
+        self.raw_send_nowait(&command).await
+    }
+
+    async fn raw_send_nowait(&mut self, command: &[u8]) -> Result<(), IoError> {
+        // past the end of the transcript: never answer, so a running device loop just stalls
+        let Some(exchange) = self.exchanges.pop_front() else {
+            return std::future::pending().await;
+        };
+
+        assert_eq!(String::from_utf8_lossy(command), exchange.send);
+        if exchange.late {
+            self.timeouts += 1;
+        }
+        if let Some(reply) = exchange.reply {
+            self.replies.push_back(reply.into_bytes());
+        }
+
+        Ok(())
+    }
+
+    async fn recv<'a>(&mut self, buf: &'a mut [u8]) -> Result<&'a mut [u8], IoError> {
+        if self.timeouts > 0 {
+            self.timeouts -= 1;
+            tokio::time::timeout(Duration::ZERO, std::future::pending::<()>()).await?;
+        }
+        let mut reply = self.replies.pop_front().expect("nothing to receive");
+        let len = reply.len().min(buf.len()).min(self.max_transfer);
+        if len < reply.len() {
+            self.replies.push_front(reply.split_off(len));
+        }
+        let buf = &mut buf[..len];
+        buf.copy_from_slice(&reply);
+        Ok(buf)
+    }
+}
@@ -0,0 +1,81 @@
+use snafu::{ensure, Snafu};
+
+/// The 4 byte prefix of the signal replies (`:DATa:WAVe:SCReen:...?`): the length of the payload
+/// following it, little-endian.
+///
+/// Replies longer than one USB transfer (e.g. deep memory reads) have to be reassembled until
+/// [`Self::payload_len`] bytes arrived.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketHeader {
+    pub payload_len: u32,
+}
+
+impl PacketHeader {
+    pub const LEN: usize = 4;
+
+    pub fn parse(packet: &[u8]) -> Result<Self, PacketError> {
+        let prefix = packet
+            .first_chunk::<{ Self::LEN }>()
+            .ok_or(PacketError::TooShort { len: packet.len() })?;
+        Ok(Self {
+            payload_len: u32::from_le_bytes(*prefix),
+        })
+    }
+
+    /// Splits a received packet into its header and payload, failing if the payload is shorter
+    /// than declared (a truncated transfer).
+    ///
+    /// NOTE: bytes beyond the declared length are cut off, none have been seen so far.
+    pub fn split(packet: &[u8]) -> Result<(Self, &[u8]), PacketError> {
+        let header = Self::parse(packet)?;
+        let payload = &packet[Self::LEN..];
+        let declared = header.payload_len as usize;
+        ensure!(
+            payload.len() >= declared,
+            TruncatedSnafu {
+                declared: header.payload_len,
+                received: payload.len(),
+            }
+        );
+        Ok((header, &payload[..declared]))
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum PacketError {
+    #[snafu(display("TooShort({len} bytes)"))]
+    TooShort { len: usize },
+    #[snafu(display("Truncated({received} of {declared} payload bytes)"))]
+    Truncated { declared: u32, received: usize },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_by_declared_length() {
+        let packet = [3, 0, 0, 0, 1, 2, 3];
+        let (header, payload) = PacketHeader::split(&packet).unwrap();
+        assert_eq!(header, PacketHeader { payload_len: 3 });
+        assert_eq!(payload, [1, 2, 3]);
+
+        let (_, payload) = PacketHeader::split(&[2, 0, 0, 0, 1, 2, b'\n']).unwrap();
+        assert_eq!(payload, [1, 2]);
+    }
+
+    #[test]
+    fn detects_truncated_packets() {
+        assert!(matches!(
+            PacketHeader::split(&[0x2c, 0x01, 0, 0, 1, 2, 3]),
+            Err(PacketError::Truncated {
+                declared: 300,
+                received: 3
+            })
+        ));
+        assert!(matches!(
+            PacketHeader::split(&[1, 0]),
+            Err(PacketError::TooShort { len: 2 })
+        ));
+    }
+}
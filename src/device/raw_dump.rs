@@ -0,0 +1,66 @@
+use std::{
+    fmt::Write as _,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Appends the exact bytes of every read of an acquisition to a file, for attaching to bug reports
+/// about models whose replies don't parse.
+///
+/// One JSON object per line: `{"timestamp":<unix seconds>,"read":<n>,"hex":"<bytes>"}`. `read` is
+/// the read number of the acquisition (as in `AcquireSignalDataError::RecvSignal`), `hex` includes
+/// the [`PacketHeader`](super::PacketHeader) and can be pasted as is into a transcript's
+/// `"reply": {"hex": ...}`.
+///
+/// The first failed write ends the dump, as a partially written acquisition would make every line
+/// after it useless. See [`RawDump::take_error`].
+pub struct RawDump {
+    file: File,
+    failed: bool,
+    error: Option<io::Error>,
+}
+
+impl RawDump {
+    /// Appends to the file at `path`, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file,
+            failed: false,
+            error: None,
+        })
+    }
+
+    /// Why the dump stopped, if it did. Only returned once, the dump stays stopped.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    pub(super) fn record(&mut self, read_number: u8, bytes: &[u8]) {
+        if self.failed {
+            return;
+        }
+        if let Err(e) = self.write(read_number, bytes) {
+            self.failed = true;
+            self.error = Some(e);
+        }
+    }
+
+    fn write(&mut self, read_number: u8, bytes: &[u8]) -> io::Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let mut line = format!(r#"{{"timestamp":{timestamp:.3},"read":{read_number},"hex":""#);
+        line.reserve(bytes.len() * 2 + 3);
+        for byte in bytes {
+            let _ = write!(line, "{byte:02x}");
+        }
+        line.push_str("\"}\n");
+
+        // one write per line, so a crash doesn't leave half a line behind
+        self.file.write_all(line.as_bytes())
+    }
+}
@@ -0,0 +1,119 @@
+//! Acquisitions as a [`Stream`] of frames, for embedding in other async applications without
+//! handling the channels of [`run_device_loop`](super::run_device_loop) themselves.
+
+use super::{run_device_loop, run_transport_loop, Device, RunError, Transport};
+use crate::{
+    InitialDeviceRunConfig, OscilloscopeData, OscilloscopeMessage, OscilloscopeRunCommand,
+};
+use futures_core::Stream;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::sync::mpsc;
+
+/// Frames waiting to be taken from the stream, older ones would only be stale.
+const FRAME_BUFFER: usize = 1;
+const COMMAND_BUFFER: usize = 16;
+
+impl Device {
+    /// Drives the acquisition while the stream is polled, yielding each frame.
+    ///
+    /// The stream ends after [`OscilloscopeRunCommand::Shutdown`] (the frame already being acquired
+    /// is still yielded) or once the [`StreamHandle`] is dropped, and after yielding the error if
+    /// the device loop fails. Needs a tokio runtime with the time driver enabled.
+    pub fn into_stream(
+        self,
+        config: InitialDeviceRunConfig,
+    ) -> (
+        impl Stream<Item = Result<OscilloscopeData, RunError>>,
+        StreamHandle,
+    ) {
+        stream_with(|message_tx, commands_rx| {
+            run_device_loop(self, message_tx, commands_rx, config, || {})
+        })
+    }
+}
+
+/// [`Device::into_stream`] over any [`Transport`].
+pub fn transport_stream(
+    io: impl Transport,
+    config: InitialDeviceRunConfig,
+) -> (
+    impl Stream<Item = Result<OscilloscopeData, RunError>>,
+    StreamHandle,
+) {
+    stream_with(|message_tx, commands_rx| {
+        run_transport_loop(io, message_tx, commands_rx, config, || {})
+    })
+}
+
+fn stream_with<F: Future<Output = Result<(), RunError>>>(
+    device_loop: impl FnOnce(
+        mpsc::Sender<OscilloscopeMessage>,
+        mpsc::Receiver<OscilloscopeRunCommand>,
+    ) -> F,
+) -> (DeviceStream<F>, StreamHandle) {
+    let (message_tx, message_rx) = mpsc::channel(FRAME_BUFFER);
+    let (commands_tx, commands_rx) = mpsc::channel(COMMAND_BUFFER);
+    let stream = DeviceStream {
+        device_loop: Some(Box::pin(device_loop(message_tx, commands_rx))),
+        message_rx,
+        error: None,
+    };
+    (stream, StreamHandle { commands_tx })
+}
+
+/// Sends commands and settings to the device loop behind a [`Device::into_stream`].
+#[derive(Clone)]
+pub struct StreamHandle {
+    commands_tx: mpsc::Sender<OscilloscopeRunCommand>,
+}
+
+impl StreamHandle {
+    /// Queues `command` for before the next frame. Returns `false` if the stream already ended.
+    pub async fn send(&self, command: impl Into<OscilloscopeRunCommand>) -> bool {
+        self.commands_tx.send(command.into()).await.is_ok()
+    }
+}
+
+struct DeviceStream<F> {
+    /// `None` once it returned, the remaining frames are still yielded
+    device_loop: Option<Pin<Box<F>>>,
+    message_rx: mpsc::Receiver<OscilloscopeMessage>,
+    /// Yielded after the remaining frames
+    error: Option<RunError>,
+}
+
+impl<F: Future<Output = Result<(), RunError>>> Stream for DeviceStream<F> {
+    type Item = Result<OscilloscopeData, RunError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            // limits, settings and notices are only of interest to the GUI, every frame brings
+            // its header anyway
+            match this.message_rx.poll_recv(cx) {
+                Poll::Ready(Some(OscilloscopeMessage::Data(data))) => {
+                    return Poll::Ready(Some(Ok(data)))
+                }
+                Poll::Ready(Some(_)) => continue,
+                // the loop returned and dropped its sender
+                Poll::Ready(None) => return Poll::Ready(this.error.take().map(Err)),
+                Poll::Pending => {}
+            }
+
+            let Some(device_loop) = &mut this.device_loop else {
+                return Poll::Pending;
+            };
+            match device_loop.as_mut().poll(cx) {
+                Poll::Ready(result) => {
+                    this.device_loop = None;
+                    this.error = result.err();
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
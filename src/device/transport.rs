@@ -0,0 +1,154 @@
+use super::{Io, IoError, PacketHeader};
+use std::io::Write;
+
+/// Size of the first read of a message, enough for the screen data and most headers in one go.
+const RECV_CHUNK: usize = 1024 + PacketHeader::LEN;
+/// Declared lengths beyond this are taken as garbage instead of allocating for them.
+const MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024;
+
+/// The IO the device loop needs. Implemented by [`Io`] for the WinRT USB API, other backends (or
+/// mocks) only need to provide these.
+#[allow(async_fn_in_trait)]
+pub trait Transport {
+    /// Sends a command, keeping the minimum pause the device needs between two commands
+    async fn send(&mut self, command: &[u8]) -> Result<(), IoError>;
+
+    /// Sends a command written by `f`, waiting like [`Transport::send`]
+    async fn send_with_writer(
+        &mut self,
+        f: impl FnOnce(&mut dyn Write) -> Result<(), std::io::Error>,
+    ) -> Result<(), IoError>;
+
+    /// Sends a command right away
+    async fn raw_send_nowait(&mut self, command: &[u8]) -> Result<(), IoError>;
+
+    async fn recv<'a>(&mut self, buf: &'a mut [u8]) -> Result<&'a mut [u8], IoError>;
+
+    /// Reads one part of a message for [`Transport::recv_message`], like [`Transport::recv`] by
+    /// default
+    async fn recv_chunk<'a>(&mut self, buf: &'a mut [u8]) -> Result<&'a mut [u8], IoError> {
+        self.recv(buf).await
+    }
+
+    /// Receives a whole packet (see [`PacketHeader`]) into `message`, reading until the declared
+    /// payload arrived. Each read times out on its own.
+    ///
+    /// A message that stops short (or declares an absurd length) is returned as far as it got,
+    /// [`PacketHeader::split`] reports it as truncated.
+    async fn recv_message(&mut self, message: &mut Vec<u8>) -> Result<(), IoError> {
+        read_message(self, message).await
+    }
+
+    async fn send_with_output<'b>(
+        &mut self,
+        command: &[u8],
+        buf: &'b mut [u8],
+    ) -> Result<&'b mut [u8], IoError> {
+        self.send(command).await?;
+        self.recv(buf).await
+    }
+
+    /// Sends several commands in a single write, see [`Io::send_batch`]
+    async fn send_batch(&mut self, commands: &[&[u8]]) -> Result<(), IoError> {
+        self.send_with_writer(|w| {
+            for (i, command) in commands.iter().enumerate() {
+                if i > 0 {
+                    w.write_all(b";")?;
+                }
+                w.write_all(command)?;
+            }
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// The body of [`Transport::recv_message`], for implementations that wrap it.
+pub(super) async fn read_message<T: Transport + ?Sized>(
+    io: &mut T,
+    message: &mut Vec<u8>,
+) -> Result<(), IoError> {
+    message.clear();
+    let mut declared = None;
+    loop {
+        let start = message.len();
+        // never read past the declared length, that would eat into the next reply
+        let want = declared.map_or(RECV_CHUNK, |len: usize| len - start);
+        message.resize(start + want, 0);
+        let read = io.recv_chunk(&mut message[start..]).await?.len();
+        message.truncate(start + read);
+
+        if declared.is_none() {
+            if let Ok(header) = PacketHeader::parse(message) {
+                let len = PacketHeader::LEN + header.payload_len as usize;
+                if len > MAX_MESSAGE_LEN {
+                    return Ok(());
+                }
+                declared = Some(len);
+            }
+        }
+        match declared {
+            Some(len) if message.len() >= len => return Ok(()),
+            _ if read == 0 => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+impl Transport for Io {
+    async fn send(&mut self, command: &[u8]) -> Result<(), IoError> {
+        Io::send(self, command).await
+    }
+
+    async fn send_with_writer(
+        &mut self,
+        f: impl FnOnce(&mut dyn Write) -> Result<(), std::io::Error>,
+    ) -> Result<(), IoError> {
+        Io::send_with_writer(self, |w| f(w)).await
+    }
+
+    async fn raw_send_nowait(&mut self, command: &[u8]) -> Result<(), IoError> {
+        Io::raw_send_nowait(self, command).await
+    }
+
+    async fn recv<'a>(&mut self, buf: &'a mut [u8]) -> Result<&'a mut [u8], IoError> {
+        Io::recv(self, buf).await
+    }
+
+    async fn recv_chunk<'a>(&mut self, buf: &'a mut [u8]) -> Result<&'a mut [u8], IoError> {
+        Io::recv_chunk(self, buf).await
+    }
+
+    async fn recv_message(&mut self, message: &mut Vec<u8>) -> Result<(), IoError> {
+        Io::recv_message(self, message).await
+    }
+}
+
+impl<T: Transport> Transport for &mut T {
+    async fn send(&mut self, command: &[u8]) -> Result<(), IoError> {
+        T::send(self, command).await
+    }
+
+    async fn send_with_writer(
+        &mut self,
+        f: impl FnOnce(&mut dyn Write) -> Result<(), std::io::Error>,
+    ) -> Result<(), IoError> {
+        T::send_with_writer(self, f).await
+    }
+
+    async fn raw_send_nowait(&mut self, command: &[u8]) -> Result<(), IoError> {
+        T::raw_send_nowait(self, command).await
+    }
+
+    async fn recv<'a>(&mut self, buf: &'a mut [u8]) -> Result<&'a mut [u8], IoError> {
+        T::recv(self, buf).await
+    }
+
+    async fn recv_chunk<'a>(&mut self, buf: &'a mut [u8]) -> Result<&'a mut [u8], IoError> {
+        T::recv_chunk(self, buf).await
+    }
+
+    async fn recv_message(&mut self, message: &mut Vec<u8>) -> Result<(), IoError> {
+        T::recv_message(self, message).await
+    }
+}
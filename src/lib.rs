@@ -1,24 +1,28 @@
 use arrayvec::ArrayVec;
+use consts::{MAX_CHANNELS, SAMPLE_CLIP_LEVEL};
 use data::{
     awg::AwgConfig,
     head::{
-        Channel, ChannelCoupling, ChannelDisplay, DataHeader, MemoryDepth, SampleType,
-        TriggerCoupling, TriggerEdge, TriggerSweep,
+        Channel, ChannelCoupling, ChannelDisplay, DataHeader, MemoryDepth, PulseCondition,
+        SampleType, TriggerCoupling, TriggerEdge, TriggerSweep, TriggerType,
     },
-    measurement::Measurements,
+    limits::DeviceLimits,
+    measurement::{MeasurementKind, Measurements},
     units::{ProbeAttenuation, Time, Voltage},
 };
-use std::time::Duration;
+use std::{collections::BTreeSet, path::PathBuf, sync::Arc, time::Duration};
 
 pub mod consts;
 pub mod data;
 pub mod device;
 pub mod scaled_number;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum OscilloscopeRunCommand {
     Command(OscilloscopeCommand),
     RunSetting(OscilloscopeRunSetting),
+    /// Ends the device loop once the commands queued before it have been sent
+    Shutdown,
 }
 
 impl From<OscilloscopeCommand> for OscilloscopeRunCommand {
@@ -32,7 +36,7 @@ impl From<OscilloscopeRunSetting> for OscilloscopeRunCommand {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum OscilloscopeCommand {
     SetHorizontalOffset(f64),
     SetChannelDisplay(Channel, ChannelDisplay),
@@ -40,20 +44,55 @@ pub enum OscilloscopeCommand {
     SetChannelVScale(Channel, Voltage),
     SetChannelCoupling(Channel, ChannelCoupling),
     SetChannelAttenuation(Channel, ProbeAttenuation),
+    SetChannelBandwidthLimit(Channel, bool),
     SetTimeScale(Time),
     SetTriggerSource(Channel),
+    SetTriggerType(TriggerType),
     SetTriggerEdge(TriggerEdge),
+    SetTriggerPulseCondition(PulseCondition),
+    SetTriggerPulseWidth(Time),
     SetTriggerLevel(Voltage),
     SetTriggerSweep(TriggerSweep),
+    SetTriggerHoldoff(Time),
     SetTriggerCoupling(TriggerCoupling),
     SetAcquisitionMode(SampleType),
     SetAcquisitionDepth(MemoryDepth),
     Auto,
 }
 
-#[derive(Debug)]
+impl OscilloscopeCommand {
+    /// Whether the device has to be polled (or given time to settle) after this command before it
+    /// accepts further commands.
+    ///
+    /// Commands that don't can be batched into a single USB write.
+    pub fn needs_readback(&self) -> bool {
+        matches!(
+            self,
+            Self::SetChannelVScale(..)
+                | Self::SetTimeScale(_)
+                | Self::SetTriggerHoldoff(_)
+                | Self::Auto
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum OscilloscopeRunSetting {
     SetMeasurementsEnabled(bool),
+    SetMeasurementChannels([bool; 2]),
+    /// See [`InitialDeviceRunConfig::measure_hidden_channels`]
+    SetMeasureHiddenChannels(bool),
+    SetMeasurementKinds(BTreeSet<MeasurementKind>),
+    /// See [`InitialDeviceRunConfig::min_frame_interval`]
+    SetMinFrameInterval(Duration),
+    /// Starts appending the raw bytes of every acquisition to the file, or stops with `None`. See
+    /// [`RawDump`](device::RawDump).
+    SetRawDump(Option<PathBuf>),
+    /// Sends `cmd` as is, for probing undocumented commands
+    RawCommand {
+        cmd: String,
+        expect_reply: bool,
+    },
     ReadAwgConfig,
     SetAwgConfig(AwgConfig),
 }
@@ -63,6 +102,31 @@ pub enum OscilloscopeRunSetting {
 pub enum OscilloscopeMessage {
     Data(OscilloscopeData),
     Awg(AwgConfig),
+    Limits(DeviceLimits),
+    /// The device's settings on connect, sent once before the first [`Self::Data`]
+    Settings(DataHeader),
+    /// Reply to [`OscilloscopeRunSetting::RawCommand`], empty if the device didn't answer
+    RawReply(Vec<u8>),
+    /// One-time message for the user, e.g. about a feature the model doesn't support
+    Notice(String),
+    /// The device applied a setting differently than requested. Just a warning, the next header
+    /// shows the applied value anyway.
+    SettingMismatch(SettingMismatch),
+}
+
+/// A setting read back after applying it, e.g. coerced to the nearest supported step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SettingMismatch {
+    /// Scales per division as shown on the device, i.e. including the probe attenuation
+    ChannelVScale {
+        channel: Channel,
+        requested: Voltage,
+        applied: Voltage,
+    },
+    TimeScale {
+        requested: Time,
+        applied: Time,
+    },
 }
 
 #[derive(Debug, Default)]
@@ -75,11 +139,97 @@ pub struct OscilloscopeData {
 #[derive(Debug, Default)]
 pub struct SignalData {
     pub header: DataHeader,
-    pub ch0_data: Option<ArrayVec<u8, 1024>>,
-    pub ch1_data: Option<ArrayVec<u8, 1024>>,
+    /// Screen data indexed by [`Channel`], `None` for channels that weren't transmitted
+    pub data: [Option<ArrayVec<u8, 1024>>; MAX_CHANNELS],
 }
 
-#[derive(Debug, Default)]
+impl SignalData {
+    pub fn channel_data(&self, ch: Channel) -> Option<&[u8]> {
+        self.data[ch as usize].as_deref()
+    }
+
+    /// The voltages of `ch`, one per screen column. Empty if the channel wasn't transmitted.
+    ///
+    /// See [`ChannelInfo::voltages`](data::head::ChannelInfo::voltages).
+    pub fn channel_voltages(&self, ch: Channel) -> impl Iterator<Item = Voltage> + '_ {
+        self.header.channel(ch).voltages(
+            self.channel_data(ch).unwrap_or_default(),
+            self.header.screen_samples(),
+        )
+    }
+
+    /// The lowest and highest sample of `ch`, `None` if it wasn't transmitted.
+    pub fn channel_extent(&self, ch: Channel) -> Option<(i8, i8)> {
+        let mut samples = self.channel_data(ch)?.iter().map(|&s| s as i8);
+        let first = samples.next()?;
+        Some(samples.fold((first, first), |(min, max), s| (min.min(s), max.max(s))))
+    }
+
+    /// Whether `ch` hits the top or bottom of the sample range ([`SAMPLE_CLIP_LEVEL`]), i.e. the
+    /// signal is cut off and its measurements can't be trusted.
+    pub fn channel_clipping(&self, ch: Channel) -> bool {
+        self.channel_extent(ch)
+            .is_some_and(|(min, max)| min <= -SAMPLE_CLIP_LEVEL || max == SAMPLE_CLIP_LEVEL)
+    }
+}
+
+#[derive(Debug)]
 pub struct InitialDeviceRunConfig {
     pub measurements_enabled: bool,
+    /// Which channels (CH1, CH2) to query measurements for, if displayed
+    pub measurement_channels: [bool; 2],
+    /// Whether [`Self::measurement_channels`] are also measured while their trace is hidden
+    pub measure_hidden_channels: bool,
+    /// Which measurements to query, others aren't read from the device at all
+    pub measurement_kinds: BTreeSet<MeasurementKind>,
+    /// Minimum time from the start of one acquisition to the next, zero for as fast as possible
+    pub min_frame_interval: Duration,
+    /// Where USB devices record their round trips, see [`LinkHealth`](device::LinkHealth)
+    pub link_health: Option<Arc<device::LinkHealth>>,
+    /// Raw SCPI lines sent once on connect, before the settings are read. Queries (ending with
+    /// `?`) are skipped with a notice, an unanswered one would leave its reply for the next read.
+    pub connect_script: Vec<String>,
+    /// Whether settings that don't have to be read back are sent `;`-joined in a single USB
+    /// write, see [`OscilloscopeCommand::needs_readback`]. Off by default, it hasn't been
+    /// verified on a device yet.
+    pub batch_commands: bool,
+}
+
+impl Default for InitialDeviceRunConfig {
+    fn default() -> Self {
+        Self {
+            measurements_enabled: Default::default(),
+            measurement_channels: [true; 2],
+            measure_hidden_channels: false,
+            measurement_kinds: MeasurementKind::all(),
+            min_frame_interval: Duration::ZERO,
+            link_health: None,
+            connect_script: Vec::new(),
+            batch_commands: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal_of(ch1: &[i8]) -> SignalData {
+        let mut signal = SignalData::default();
+        signal.data[Channel::Ch1 as usize] = Some(ch1.iter().map(|&s| s as u8).collect());
+        signal
+    }
+
+    #[test]
+    fn finds_channel_extent_and_clipping() {
+        let signal = signal_of(&[3, -20, 50, 0]);
+        assert_eq!(signal.channel_extent(Channel::Ch1), Some((-20, 50)));
+        assert!(!signal.channel_clipping(Channel::Ch1));
+        assert_eq!(signal.channel_extent(Channel::Ch2), None);
+        assert!(!signal.channel_clipping(Channel::Ch2));
+
+        assert!(signal_of(&[0, 127]).channel_clipping(Channel::Ch1));
+        assert!(signal_of(&[-128, 0]).channel_clipping(Channel::Ch1));
+        assert_eq!(signal_of(&[]).channel_extent(Channel::Ch1), None);
+    }
 }
@@ -1,4 +1,5 @@
 use derive_more::From;
+use serde::{Deserialize, Serialize};
 use std::{
     fmt::{Display, Write},
     str::FromStr,
@@ -15,7 +16,8 @@ impl Display for DynamicDecimals {
         let prec = if abs == 0.0 {
             self.1
         } else {
-            self.1 - abs.log10().floor().max(0.0) as usize
+            // saturating: low precisions can be smaller than the number of integer digits
+            self.1.saturating_sub(abs.log10().floor().max(0.0) as usize)
         };
 
         if f.sign_plus() {
@@ -26,17 +28,64 @@ impl Display for DynamicDecimals {
     }
 }
 
+/// Precision of [`ScaledNumber`] if the format doesn't specify one.
+pub const DEFAULT_PRECISION: usize = 3;
+
 #[derive(Debug, Clone, Copy, From, PartialEq)]
 pub struct ScaledNumber(pub f64);
 
-impl Display for ScaledNumber {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let (unscaled, scale) = self.0.unscale();
+/// How a [`ScaledNumber`] is written, see [`ScaledNumber::fmt_with`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NumberFormat {
+    /// `1.000µ`, the [`Display`] format
+    #[default]
+    SiPrefix,
+    /// `1.000e-6`
+    Scientific,
+    /// `0.000001`, the shortest representation that parses back to the same number. Ignores the
+    /// precision.
+    Plain,
+}
+
+impl ScaledNumber {
+    /// Writes the number in the given `format`, with the formatter's precision (if applicable).
+    pub fn fmt_with(
+        &self,
+        format: NumberFormat,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        let precision = f.precision().unwrap_or(DEFAULT_PRECISION);
+        match format {
+            NumberFormat::SiPrefix => {
+                let (unscaled, scale) = self.0.unscale();
 
-        DynamicDecimals::from((unscaled, f.precision().unwrap_or(3))).fmt(f)?;
+                DynamicDecimals::from((unscaled, precision)).fmt(f)?;
 
-        // respects alternate formatting
-        scale.fmt(f)
+                // respects alternate formatting
+                scale.fmt(f)
+            }
+            NumberFormat::Scientific => f.write_fmt(format_args!("{:.precision$e}", self.0)),
+            NumberFormat::Plain => f.write_fmt(format_args!("{}", self.0)),
+        }
+    }
+
+    /// `Display`s the number in the given `format`.
+    pub fn display_with(self, format: NumberFormat) -> impl Display {
+        struct WithFormat(ScaledNumber, NumberFormat);
+
+        impl Display for WithFormat {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.0.fmt_with(self.1, f)
+            }
+        }
+
+        WithFormat(self, format)
+    }
+}
+
+impl Display for ScaledNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_with(NumberFormat::SiPrefix, f)
     }
 }
 
@@ -69,7 +118,9 @@ impl ScaledNumberExt for f64 {
         let sign = self.signum();
         self = self.abs();
 
-        let exp = ((self.log10() / 3.0).floor() * 3.0) as i32;
+        // saturate instead of running out of prefixes for bogus readings (or infinity)
+        let exp = ((self.log10() / 3.0).floor() * 3.0)
+            .clamp(SiScale::Atto as i8 as f64, SiScale::Peta as i8 as f64) as i32;
         self *= 10.0f64.powi(-exp);
         (
             self * sign,
@@ -77,27 +128,43 @@ impl ScaledNumberExt for f64 {
         )
     }
 
+    /// Parses a number with an optional SI prefix, e.g. `3.72m`. Tolerates the spacing seen in
+    /// different firmware replies, like `+ 3.72 m`.
     fn parse_scaled(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let (sign, s) = match s.strip_prefix('-') {
+            Some(s) => (-1.0, s),
+            None => (1.0, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let s = s.trim_start();
+        if s.starts_with(['+', '-']) {
+            return None;
+        }
+
         let mut scale = SiScale::None;
         let last = s.chars().next_back()?;
 
         let mut val = if last.is_ascii_digit() {
             s.parse::<f64>().ok()?
         } else {
-            assert!(last.len_utf8() == 1);
             scale = SiScale::try_from(last).ok()?;
-            s[..s.len() - 1].trim_end().parse::<f64>().ok()?
+            s[..s.len() - last.len_utf8()]
+                .trim_end()
+                .parse::<f64>()
+                .ok()?
         };
 
         val = scale.apply_to(val);
 
-        Some(val)
+        Some(sign * val)
     }
 }
 
 #[derive(Debug, Default, FromRepr, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(i8)]
 pub enum SiScale {
+    Atto = -18,
+    Femto = -15,
     Pico = -12,
     Nano = -9,
     Micro = -6,
@@ -107,6 +174,8 @@ pub enum SiScale {
     Kilo = 3,
     Mega = 6,
     Giga = 9,
+    Tera = 12,
+    Peta = 15,
 }
 
 impl SiScale {
@@ -127,13 +196,18 @@ impl TryFrom<char> for SiScale {
     type Error = ();
     fn try_from(c: char) -> Result<Self, Self::Error> {
         match c {
+            'a' => Ok(SiScale::Atto),
+            'f' => Ok(SiScale::Femto),
             'p' => Ok(SiScale::Pico),
             'n' => Ok(SiScale::Nano),
-            'u' => Ok(SiScale::Micro),
+            // micro sign (U+00B5) and greek small letter mu (U+03BC)
+            'u' | 'µ' | 'μ' => Ok(SiScale::Micro),
             'm' => Ok(SiScale::Milli),
             'k' | 'K' => Ok(SiScale::Kilo),
             'M' => Ok(SiScale::Mega),
             'G' => Ok(SiScale::Giga),
+            'T' => Ok(SiScale::Tera),
+            'P' => Ok(SiScale::Peta),
             _ => Err(()),
         }
     }
@@ -142,6 +216,8 @@ impl TryFrom<char> for SiScale {
 impl Display for SiScale {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let c = match self {
+            SiScale::Atto => 'a',
+            SiScale::Femto => 'f',
             SiScale::Pico => 'p',
             SiScale::Nano => 'n',
             SiScale::Micro => {
@@ -156,8 +232,112 @@ impl Display for SiScale {
             SiScale::Kilo => 'k',
             SiScale::Mega => 'M',
             SiScale::Giga => 'G',
+            SiScale::Tera => 'T',
+            SiScale::Peta => 'P',
         };
 
         f.write_char(c)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::units::{Time, Voltage};
+
+    #[test]
+    fn parses_signs_and_spacing() {
+        for (s, expected) in [
+            ("3.72", 3.72),
+            ("+3.72", 3.72),
+            ("-3.72", -3.72),
+            ("+ 3.72", 3.72),
+            ("- 3.72", -3.72),
+            ("3.72m", 3.72e-3),
+            ("3.72 m", 3.72e-3),
+            (" + 3.72 m ", 3.72e-3),
+        ] {
+            let parsed = f64::parse_scaled(s).unwrap();
+            assert!((parsed - expected).abs() < 1e-12, "{s:?}: {parsed}");
+        }
+
+        assert_eq!(f64::parse_scaled("+-3.72"), None);
+        assert_eq!(f64::parse_scaled("- "), None);
+    }
+
+    #[test]
+    fn parses_firmware_unit_spacings() {
+        assert_eq!("+ 3.72 V".parse(), Ok(Voltage(3.72)));
+        assert_eq!("3.72 V".parse(), Ok(Voltage(3.72)));
+        assert_eq!(" -3.72V ".parse(), Ok(Voltage(-3.72)));
+        let millivolts = "3.72 mV".parse::<Voltage>().unwrap();
+        assert!((millivolts.0 - 3.72e-3).abs() < 1e-12);
+        assert_eq!("500 ms".parse(), Ok(Time(0.5)));
+    }
+
+    #[test]
+    fn formats_with_precision() {
+        assert_eq!(ScaledNumber(123.456).to_string(), "123.5");
+        assert_eq!(format!("{:.1}", ScaledNumber(123.456)), "123");
+        assert_eq!(format!("{:.4}", ScaledNumber(1.23456e-3)), "1.2346m");
+    }
+
+    #[test]
+    fn formats_number_formats() {
+        let number = ScaledNumber(1.5e-6);
+        assert_eq!(
+            number.display_with(NumberFormat::SiPrefix).to_string(),
+            "1.500µ"
+        );
+        assert_eq!(
+            number.display_with(NumberFormat::Scientific).to_string(),
+            "1.500e-6"
+        );
+        assert_eq!(
+            format!("{:.1}", number.display_with(NumberFormat::Scientific)),
+            "1.5e-6"
+        );
+        assert_eq!(
+            number.display_with(NumberFormat::Plain).to_string(),
+            "0.0000015"
+        );
+    }
+
+    #[test]
+    fn unscales_extreme_values() {
+        let (val, scale) = 1e15.unscale();
+        assert!((val - 1.0).abs() < 1e-12);
+        assert_eq!(scale, SiScale::Peta);
+
+        let (val, scale) = 1e-15.unscale();
+        assert!((val - 1.0).abs() < 1e-12);
+        assert_eq!(scale, SiScale::Femto);
+
+        // out of range saturates to the extreme prefixes
+        let (val, scale) = 1e21.unscale();
+        assert!((val - 1e6).abs() < 1e-3);
+        assert_eq!(scale, SiScale::Peta);
+        assert_eq!(1e-24.unscale().1, SiScale::Atto);
+        assert_eq!(f64::INFINITY.unscale().1, SiScale::Peta);
+
+        assert_eq!(ScaledNumber(1e15).to_string(), "1.000P");
+        assert_eq!(ScaledNumber(-1e-15).to_string(), "-1.000f");
+    }
+
+    #[test]
+    fn parses_micro_prefixes() {
+        for s in ["10us", "10µs", "10\u{3bc}s", "10 µs"] {
+            let time = s.parse::<Time>().unwrap();
+            assert!((time.0 - 10e-6).abs() < 1e-15, "{s:?}: {time:?}");
+        }
+    }
+
+    #[test]
+    fn micro_display_round_trips() {
+        let time = Time(10e-6);
+        for s in [time.to_string(), format!("{time:#}")] {
+            let parsed = s.parse::<Time>().unwrap();
+            assert!((parsed.0 - time.0).abs() < 1e-15, "{s:?}: {parsed:?}");
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use owowon::data::{
+    head::{
+        Channel, ChannelCoupling, ChannelDisplay, DataHeader, MemoryDepth, RunStatus, SampleType,
+        TriggerCoupling, TriggerEdge, TriggerSweep, TriggerType,
+    },
+    units::{ProbeAttenuation, SamplingRate, Time, Voltage},
+};
+use strum::IntoEnumIterator;
+
+const HDS2102S_HEAD: &str = include_str!("fixtures/hds2102s_head.json");
+// SYNTHETIC, not captured from a device: the HDS2102S header with the quirks reported for the
+// HDS272S (quoted numbers, whitespace-padded unit values) and extra fields. Replace it with a real
+// capture once someone has that model.
+const HDS272S_HEAD: &str = include_str!("fixtures/synthetic_hds272s_head.json");
+
+#[test]
+fn parses_hds2102s_header() {
+    let head: DataHeader = serde_json::from_str(HDS2102S_HEAD).unwrap();
+
+    assert_eq!(head.time_base.scale, Time(500e-6));
+    assert_eq!(head.time_base.h_offset, 0);
+    assert_eq!(head.sample.data_len, 600);
+    assert_eq!(head.sample.sampling_rate, SamplingRate(1e6));
+    assert_eq!(head.sample.sample_type, SampleType::Sample);
+    assert_eq!(head.sample.depmem, MemoryDepth::EightK);
+    assert_eq!(head.sample.sample_interval(), Some(Time(1e-6)));
+    let record_time = head.sample.record_time().unwrap();
+    // 600 samples at 1µs
+    assert!((record_time.0 - 600e-6).abs() < 1e-12);
+
+    let ch1 = head.channel(Channel::Ch1);
+    assert_eq!(ch1.display, ChannelDisplay::On);
+    assert_eq!(ch1.probe, ProbeAttenuation(10));
+    assert_eq!(ch1.scale, Voltage(0.2));
+    assert_eq!(ch1.offset, 25);
+    assert_eq!(ch1.frequency, 1000.0);
+
+    let ch2 = head.channel(Channel::Ch2);
+    assert_eq!(ch2.channel, Channel::Ch2);
+    assert_eq!(ch2.display, ChannelDisplay::Off);
+    assert_eq!(ch2.coupling, ChannelCoupling::Ac);
+    assert_eq!(ch2.offset, -50);
+
+    assert_eq!(head.run_status, RunStatus::Triggering);
+    assert_eq!(head.trigger.items.channel, Channel::Ch1);
+    assert_eq!(head.trigger.items.level, Voltage(1.2));
+    assert_eq!(head.trigger.items.sweep, TriggerSweep::Auto);
+}
+
+#[test]
+fn decodes_hds2102s_screen_data_into_screen_columns() {
+    let head: DataHeader = serde_json::from_str(HDS2102S_HEAD).unwrap();
+    assert_eq!(head.screen_samples(), 300);
+
+    // DATALEN counts bytes: 600 of them are two per column, even in SAMPle mode
+    let data = (0..head.sample.data_len)
+        .map(|i| if i % 2 == 0 { 20 } else { 30 })
+        .collect::<Vec<u8>>();
+    let ch1 = head.channel(Channel::Ch1);
+    let voltages = ch1
+        .voltages(&data, head.screen_samples())
+        .collect::<Vec<_>>();
+
+    assert_eq!(voltages.len(), 300);
+    assert!(voltages.iter().all(|&v| v == ch1.units_to_voltage(25.0)));
+}
+
+#[test]
+fn parses_synthetic_hds272s_header() {
+    let head: DataHeader = serde_json::from_str(HDS272S_HEAD).unwrap();
+
+    assert_eq!(head.time_base.scale, Time(1e-3));
+    assert_eq!(head.time_base.h_offset, -25);
+    assert_eq!(head.sample.fullscreen, 300);
+    assert_eq!(head.sample.data_len, 300);
+    assert_eq!(head.sample.sampling_rate, SamplingRate(500e3));
+    assert_eq!(head.sample.sample_type, SampleType::Peak);
+    assert_eq!(head.sample.depmem, MemoryDepth::FourK);
+
+    let ch1 = head.channel(Channel::Ch1);
+    assert_eq!(ch1.probe, ProbeAttenuation(10));
+    assert_eq!(ch1.scale, Voltage(0.5));
+    assert_eq!(ch1.offset, 0);
+    assert_eq!(ch1.frequency, 1000.0);
+
+    let ch2 = head.channel(Channel::Ch2);
+    assert_eq!(ch2.coupling, ChannelCoupling::Gnd);
+    assert_eq!(ch2.probe, ProbeAttenuation(1));
+    assert_eq!(ch2.scale, Voltage(2.0));
+    assert_eq!(ch2.offset, 75);
+
+    assert_eq!(head.run_status, RunStatus::Auto);
+    assert_eq!(head.trigger.items.channel, Channel::Ch2);
+    assert_eq!(head.trigger.items.level, Voltage(-0.5));
+    assert_eq!(head.trigger.items.edge, TriggerEdge::Falling);
+    assert_eq!(head.trigger.items.coupling, TriggerCoupling::Ac);
+    assert_eq!(head.trigger.items.sweep, TriggerSweep::Normal);
+}
+
+#[test]
+fn parses_four_channel_header() {
+    let mut head: serde_json::Value = serde_json::from_str(HDS2102S_HEAD).unwrap();
+    let channels = head["CHANNEL"].as_array_mut().unwrap();
+    for name in ["CH3", "CH4"] {
+        let mut channel = channels[1].clone();
+        channel["NAME"] = name.into();
+        channel["DISPLAY"] = "ON".into();
+        channels.push(channel);
+    }
+    let head: DataHeader = serde_json::from_value(head).unwrap();
+
+    assert_eq!(head.channels.len(), 4);
+    assert_eq!(head.channel(Channel::Ch4).channel, Channel::Ch4);
+    assert_eq!(head.channels_enabled(), [true, false, true, true]);
+
+    // the two channel models leave the rest off
+    let head: DataHeader = serde_json::from_str(HDS2102S_HEAD).unwrap();
+    assert_eq!(head.channels.len(), 2);
+    assert_eq!(head.channels_enabled(), [true, false, false, false]);
+}
+
+#[test]
+fn parses_bandwidth_limit_if_reported() {
+    let head: DataHeader = serde_json::from_str(HDS2102S_HEAD).unwrap();
+    assert_eq!(head.channel(Channel::Ch1).bandwidth_limit, None);
+
+    let head = HDS2102S_HEAD.replace(r#""PROBE":"10X","#, r#""PROBE":"10X","BWLIMIT":"ON","#);
+    let head: DataHeader = serde_json::from_str(&head).unwrap();
+    assert_eq!(
+        head.channel(Channel::Ch1).bandwidth_limit,
+        Some(ChannelDisplay::On)
+    );
+    assert_eq!(head.channel(Channel::Ch2).bandwidth_limit, None);
+}
+
+#[test]
+fn parses_pulse_trigger_type() {
+    // pulse mode headers don't necessarily carry the edge items
+    let head = HDS2102S_HEAD
+        .replace(r#""Type":"Edge""#, r#""Type":"Pulse""#)
+        .replace(r#""Edge":"RISE","#, "");
+    let head: DataHeader = serde_json::from_str(&head).unwrap();
+
+    assert_eq!(head.trigger.r#type, TriggerType::Pulse);
+    assert_eq!(head.trigger.items.channel, Channel::Ch1);
+}
+
+#[test]
+fn trigger_type_scpi_tokens_round_trip() {
+    for ty in TriggerType::iter() {
+        assert_eq!(ty.to_string().parse::<TriggerType>(), Ok(ty));
+        let json = format!("\"{ty}\"");
+        assert_eq!(serde_json::from_str::<TriggerType>(&json).unwrap(), ty);
+    }
+}